@@ -0,0 +1,229 @@
+//! ASCII armor for shares, so they can be pasted into emails or tickets.
+//!
+//! ```text
+//! -----BEGIN ONETIME SHARE-----
+//! Index: 0
+//! Set: 0123456789abcdef0123456789abcdef
+//!
+//! base64 of the binary share, wrapped at 64 columns
+//! =CRC24 of the binary share, base64
+//! -----END ONETIME SHARE-----
+//! ```
+//!
+//! The headers are only informational; the binary share contains the same
+//! information.
+
+use std::io::{self, Write};
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+
+use crate::stream::Sink;
+
+const BEGIN_LINE: &str = "-----BEGIN ONETIME SHARE-----";
+const END_LINE: &str = "-----END ONETIME SHARE-----";
+
+/// Number of bytes encoded per line of 64 characters
+const BYTES_PER_LINE: usize = 48;
+
+/// CRC-24 as used by OpenPGP
+const CRC24_INIT: u32 = 0xb704ce;
+const CRC24_POLY: u32 = 0x1864cfb;
+
+fn crc24_update(mut crc: u32, data: &[u8]) -> u32 {
+    for &byte in data {
+        crc ^= (byte as u32) << 16;
+
+        for _ in 0..8 {
+            crc <<= 1;
+            if crc & 0x1000000 != 0 {
+                crc ^= CRC24_POLY;
+            }
+        }
+    }
+
+    crc & 0xffffff
+}
+
+/// Writes the data passed to it as an armored share.
+pub struct ArmorWriter<W: Sink> {
+    inner: W,
+    buf: Vec<u8>,
+    crc: u32,
+}
+
+impl<W: Sink> ArmorWriter<W> {
+    pub fn new(mut inner: W, index: u8, set_id: &[u8; 16]) -> io::Result<Self> {
+        let set_id: String = set_id.iter().map(|b| format!("{b:02x}")).collect();
+        write!(inner, "{BEGIN_LINE}\nIndex: {index}\nSet: {set_id}\n\n")?;
+
+        Ok(Self {
+            inner,
+            buf: Vec::with_capacity(BYTES_PER_LINE),
+            crc: CRC24_INIT,
+        })
+    }
+
+    fn write_line(&mut self, bytes: usize) -> io::Result<()> {
+        let mut line = STANDARD.encode(&self.buf[..bytes]);
+        line.push('\n');
+        self.buf.drain(..bytes);
+
+        self.inner.write_all(line.as_bytes())
+    }
+}
+
+impl<W: Sink> Write for ArmorWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.crc = crc24_update(self.crc, buf);
+        self.buf.extend_from_slice(buf);
+
+        while self.buf.len() >= BYTES_PER_LINE {
+            self.write_line(BYTES_PER_LINE)?;
+        }
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl<W: Sink> Sink for ArmorWriter<W> {
+    fn finish(mut self: Box<Self>) -> io::Result<()> {
+        if !self.buf.is_empty() {
+            self.write_line(self.buf.len())?;
+        }
+
+        let crc = STANDARD.encode(&self.crc.to_be_bytes()[1..]);
+        write!(self.inner, "={crc}\n{END_LINE}\n")?;
+
+        Box::new(self.inner).finish()
+    }
+}
+
+/// Returns whether `start` looks like the beginning of an armored share.
+pub fn is_armored(start: &[u8]) -> bool {
+    start.starts_with(BEGIN_LINE.as_bytes())
+}
+
+/// Decodes an armored share and checks its checksum.
+pub fn decode(text: &[u8]) -> Result<Vec<u8>, String> {
+    let text = std::str::from_utf8(text).map_err(|_| "invalid armor: not ASCII".to_string())?;
+    let mut lines = text.lines().map(str::trim);
+
+    if lines.next() != Some(BEGIN_LINE) {
+        return Err("invalid armor: missing BEGIN line".to_string());
+    }
+
+    // Skip the headers, which end with an empty line
+    for line in lines.by_ref() {
+        if line.is_empty() {
+            break;
+        }
+    }
+
+    let mut data = String::new();
+    let mut crc = None;
+
+    for line in lines.by_ref() {
+        if line == END_LINE {
+            let data = STANDARD
+                .decode(&data)
+                .map_err(|e| format!("invalid armor: {e}"))?;
+
+            return match crc {
+                Some(crc) if crc != crc24_update(CRC24_INIT, &data) => {
+                    Err("armor checksum mismatch".to_string())
+                }
+                _ => Ok(data),
+            };
+        }
+
+        match line.strip_prefix('=') {
+            Some(checksum) => {
+                let bytes = STANDARD
+                    .decode(checksum)
+                    .ok()
+                    .filter(|b| b.len() == 3)
+                    .ok_or_else(|| "invalid armor checksum".to_string())?;
+
+                crc = Some(u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]));
+            }
+            None if crc.is_some() => return Err("invalid armor: data after checksum".to_string()),
+            None => data.push_str(line),
+        }
+    }
+
+    Err("invalid armor: missing END line".to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    struct VecSink(Rc<RefCell<Vec<u8>>>);
+
+    impl Write for VecSink {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Sink for VecSink {
+        fn finish(self: Box<Self>) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn armor(data: &[u8]) -> String {
+        let out = Rc::default();
+        let mut writer =
+            Box::new(ArmorWriter::new(VecSink(Rc::clone(&out)), 1, &[0xab; 16]).unwrap());
+
+        for piece in data.chunks(13) {
+            writer.write_all(piece).unwrap();
+        }
+        writer.finish().unwrap();
+
+        String::from_utf8(out.take()).unwrap()
+    }
+
+    #[test]
+    fn test_crc24() {
+        assert_eq!(crc24_update(CRC24_INIT, b"123456789"), 0x21cf02);
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let data: Vec<u8> = (0..=255).cycle().take(1000).collect();
+        let text = armor(&data);
+
+        assert!(is_armored(text.as_bytes()));
+        assert!(text.lines().all(|l| l.len() <= 64));
+        assert!(text.contains(&format!("\nSet: {}\n", "ab".repeat(16))));
+        assert_eq!(decode(text.as_bytes()), Ok(data.clone()));
+
+        // Line endings of emails
+        assert_eq!(decode(text.replace('\n', "\r\n").as_bytes()), Ok(data));
+    }
+
+    #[test]
+    fn test_checksum_mismatch() {
+        let text = armor(b"some share data");
+        let damaged = text.replacen("c29t", "c29u", 1);
+
+        assert_eq!(
+            decode(damaged.as_bytes()),
+            Err("armor checksum mismatch".to_string())
+        );
+    }
+}
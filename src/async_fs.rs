@@ -0,0 +1,54 @@
+//! Async file helpers for use with [`encrypt_async`](crate::encrypt_async)
+//! and [`decrypt_async`](crate::decrypt_async).
+//!
+//! These mirror the helpers used by [`encrypt_file`](crate::encrypt_file)
+//! and [`decrypt_file`](crate::decrypt_file), but work on
+//! [`tokio::fs::File`]s. Errors name the file they occurred on, like all
+//! other [`Error::IoError`]s.
+
+use std::path::Path;
+
+use tokio::fs::File;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+use crate::fs::io_error;
+pub use crate::fs::Mode;
+use crate::{Error, IoOperation};
+
+/// Opens the file at `path` for reading, or creates it for writing.
+pub async fn open_file(path: &Path, mode: Mode) -> Result<File, Error> {
+    let res = match mode {
+        Mode::Open => File::open(path).await,
+        Mode::Create => File::create(path).await,
+    };
+
+    match res {
+        Ok(f) => Ok(f),
+        Err(e) => Err(io_error(path, IoOperation::Open, e)),
+    }
+}
+
+/// Reads up to `buf.len()` bytes from `file`, which was opened from `path`.
+/// Returns the number of bytes read, which is 0 at the end of the file.
+pub async fn read(file: &mut File, path: &Path, buf: &mut [u8]) -> Result<usize, Error> {
+    match file.read(buf).await {
+        Ok(bytes) => Ok(bytes),
+        Err(e) => Err(io_error(path, IoOperation::Read, e)),
+    }
+}
+
+/// Writes all of `buf` to `file`, which was opened from `path`.
+pub async fn write(file: &mut File, path: &Path, buf: &[u8]) -> Result<usize, Error> {
+    match file.write_all(buf).await {
+        Ok(_) => Ok(buf.len()),
+        Err(e) => Err(io_error(path, IoOperation::Write, e)),
+    }
+}
+
+/// Removes the file at `path`.
+pub async fn remove_file(path: &Path) -> Result<(), Error> {
+    match tokio::fs::remove_file(path).await {
+        Ok(_) => Ok(()),
+        Err(e) => Err(io_error(path, IoOperation::Remove, e)),
+    }
+}
@@ -0,0 +1,297 @@
+use std::io;
+
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+use crate::config::check_buffer;
+use crate::share::MAGIC;
+use crate::{decrypt, encrypt, ConfigError, Error, IoError, IoOperation};
+
+/// Encrypts everything read from `input` and writes the two encrypted parts
+/// to `output1` and `output2`. Returns the number of bytes encrypted.
+///
+/// The data is processed in chunks of `buffer` bytes, like
+/// [`Config::buffer`](crate::Config::buffer) does for [`encrypt_file`](crate::encrypt_file).
+/// The outputs are raw encrypted data without a header or trailer, so they
+/// carry no digest, metadata or hash tree. [`decrypt_file`](crate::decrypt_file)
+/// accepts them like files from earlier versions. The other way around
+/// doesn't work: files written by [`encrypt_file`](crate::encrypt_file) or
+/// the `onetime-cli` binary can't be decrypted with [`decrypt_async`].
+///
+/// ## Error
+/// Will return an [`Error::InvalidConfig`] if `buffer` is 0 or larger than
+/// [`MAX_BUFFER`](crate::MAX_BUFFER), like [`Config::validate`](crate::Config::validate).
+///
+/// ## Example
+/// ```
+/// # #[tokio::main(flavor = "current_thread")]
+/// # async fn main() -> Result<(), onetime_cli::Error> {
+/// use onetime_cli::{decrypt_async, encrypt_async};
+///
+/// let mut out1 = Vec::new();
+/// let mut out2 = Vec::new();
+/// encrypt_async(&mut &b"secret"[..], &mut out1, &mut out2, 1024).await?;
+///
+/// let mut data = Vec::new();
+/// decrypt_async(&mut &out1[..], &mut &out2[..], &mut data, 1024).await?;
+///
+/// assert_eq!(data, b"secret");
+/// # Ok(())
+/// # }
+/// ```
+pub async fn encrypt_async<R, W1, W2>(
+    input: &mut R,
+    output1: &mut W1,
+    output2: &mut W2,
+    buffer: u32,
+) -> Result<u64, Error>
+where
+    R: AsyncRead + Unpin + ?Sized,
+    W1: AsyncWrite + Unpin + ?Sized,
+    W2: AsyncWrite + Unpin + ?Sized,
+{
+    check_buffer(buffer)?;
+
+    let mut buf_in = vec![0u8; buffer as usize];
+    let mut buf_out1 = vec![0u8; buffer as usize];
+    let mut buf_out2 = vec![0u8; buffer as usize];
+    let mut length = 0;
+
+    loop {
+        let bytes = read_full(input, &mut buf_in).await?;
+
+        if bytes == 0 {
+            break;
+        }
+
+        encrypt(&buf_in[..bytes], &mut buf_out1, &mut buf_out2)?;
+
+        write(output1, &buf_out1[..bytes]).await?;
+        write(output2, &buf_out2[..bytes]).await?;
+        length += bytes as u64;
+    }
+
+    flush(output1).await?;
+    flush(output2).await?;
+
+    Ok(length)
+}
+
+/// Decrypts the two encrypted parts read from `input1` and `input2` and
+/// writes the data to `output`. Returns the number of bytes decrypted.
+///
+/// This is the counterpart of [`encrypt_async`], and processes the data in
+/// chunks of `buffer` bytes as well. The inputs must be raw encrypted data
+/// of the same length. Files written by [`encrypt_file`](crate::encrypt_file)
+/// or the `onetime-cli` binary start with a header and may be split into
+/// volumes or encoded as text, so they can't be decrypted this way. Use
+/// [`decrypt_file`](crate::decrypt_file) for them.
+///
+/// ## Error
+/// Will return an [`Error::InvalidConfig`] if `buffer` is 0, smaller than
+/// the 8 bytes needed to recognize files written by
+/// [`encrypt_file`](crate::encrypt_file) or larger than
+/// [`MAX_BUFFER`](crate::MAX_BUFFER).
+///
+/// Will return an [`Error::InvalidInput`] if:
+///   * an input starts with the header of a file written by
+///     [`encrypt_file`](crate::encrypt_file). Nothing is written to
+///     `output` in this case.
+///   * the inputs differ in length.
+pub async fn decrypt_async<R1, R2, W>(
+    input1: &mut R1,
+    input2: &mut R2,
+    output: &mut W,
+    buffer: u32,
+) -> Result<u64, Error>
+where
+    R1: AsyncRead + Unpin + ?Sized,
+    R2: AsyncRead + Unpin + ?Sized,
+    W: AsyncWrite + Unpin + ?Sized,
+{
+    check_buffer(buffer)?;
+
+    // The first chunk must be long enough to tell whether it starts with a header
+    if (buffer as usize) < MAGIC.len() {
+        return Err(Error::InvalidConfig(ConfigError::BufferTooSmall));
+    }
+
+    let mut buf_in1 = vec![0u8; buffer as usize];
+    let mut buf_in2 = vec![0u8; buffer as usize];
+    let mut buf_out = vec![0u8; buffer as usize];
+    let mut length = 0;
+
+    loop {
+        let bytes_1 = read_full(input1, &mut buf_in1).await?;
+        let bytes_2 = read_full(input2, &mut buf_in2).await?;
+
+        if bytes_1 != bytes_2 {
+            return Err(Error::InvalidInput(
+                "The two inputs differ in size!".to_string(),
+            ));
+        }
+
+        if bytes_1 == 0 {
+            break;
+        }
+
+        if length == 0 && (buf_in1.starts_with(MAGIC) || buf_in2.starts_with(MAGIC)) {
+            return Err(Error::InvalidInput(
+                "The inputs were written by encrypt_file, which decrypt_async doesn't support"
+                    .to_string(),
+            ));
+        }
+
+        decrypt(&buf_in1[..bytes_1], &buf_in2[..bytes_2], &mut buf_out)?;
+
+        write(output, &buf_out[..bytes_1]).await?;
+        length += bytes_1 as u64;
+    }
+
+    flush(output).await?;
+
+    Ok(length)
+}
+
+/// Reads until `buf` is full or the end of the stream is reached, so both
+/// inputs of [`decrypt_async`] are read in chunks of the same size.
+async fn read_full<R>(r: &mut R, buf: &mut [u8]) -> Result<usize, Error>
+where
+    R: AsyncRead + Unpin + ?Sized,
+{
+    let mut filled = 0;
+
+    while filled < buf.len() {
+        match r.read(&mut buf[filled..]).await {
+            Ok(0) => break,
+            Ok(bytes) => filled += bytes,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => (),
+            Err(e) => return Err(io_error(IoOperation::Read, e)),
+        }
+    }
+
+    Ok(filled)
+}
+
+async fn write<W>(w: &mut W, buf: &[u8]) -> Result<(), Error>
+where
+    W: AsyncWrite + Unpin + ?Sized,
+{
+    w.write_all(buf)
+        .await
+        .map_err(|e| io_error(IoOperation::Write, e))
+}
+
+async fn flush<W>(w: &mut W) -> Result<(), Error>
+where
+    W: AsyncWrite + Unpin + ?Sized,
+{
+    w.flush().await.map_err(|e| io_error(IoOperation::Write, e))
+}
+
+/// Streams have no path, so errors don't name a file
+fn io_error(operation: IoOperation, error: io::Error) -> Error {
+    Error::IoError(IoError {
+        path: None,
+        operation,
+        error,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_roundtrip() {
+        let data: Vec<u8> = (0..10_000).map(|i| (i % 251) as u8).collect();
+
+        let mut out1 = Vec::new();
+        let mut out2 = Vec::new();
+        let length = encrypt_async(&mut &data[..], &mut out1, &mut out2, 1000)
+            .await
+            .unwrap();
+
+        assert_eq!(length, 10_000);
+        assert_eq!(out1.len(), 10_000);
+        assert_ne!(out2, data);
+
+        let mut decrypted = Vec::new();
+        decrypt_async(&mut &out1[..], &mut &out2[..], &mut decrypted, 333)
+            .await
+            .unwrap();
+
+        assert_eq!(decrypted, data);
+    }
+
+    #[tokio::test]
+    async fn test_size_mismatch() {
+        let mut decrypted = Vec::new();
+        let err = decrypt_async(&mut &[0u8; 10][..], &mut &[0u8; 9][..], &mut decrypted, 8)
+            .await
+            .unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "Invalid input: The two inputs differ in size!"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_invalid_buffer() {
+        let invalid = |e: Error, expected| matches!(e, Error::InvalidConfig(e) if e == expected);
+
+        let (mut out1, mut out2) = (Vec::new(), Vec::new());
+        let err = encrypt_async(&mut &b"secret"[..], &mut out1, &mut out2, 0)
+            .await
+            .unwrap_err();
+
+        assert!(invalid(err, ConfigError::ZeroBuffer));
+
+        let cases = [
+            (0, ConfigError::ZeroBuffer),
+            (7, ConfigError::BufferTooSmall),
+            (crate::MAX_BUFFER + 1, ConfigError::BufferTooLarge),
+        ];
+
+        for (buffer, expected) in cases {
+            let mut decrypted = Vec::new();
+            let err = decrypt_async(
+                &mut &[0u8; 4][..],
+                &mut &[0u8; 4][..],
+                &mut decrypted,
+                buffer,
+            )
+            .await
+            .unwrap_err();
+
+            assert!(invalid(err, expected));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rejects_shares_with_header() {
+        let dir = std::env::temp_dir().join("onetime-cli-test-async-header");
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("secret.txt");
+        std::fs::write(&file, b"secret data").unwrap();
+
+        let c = crate::Config::new(file.to_str().unwrap());
+        let [share1, share2] = crate::encrypt_file(&c).unwrap().shares;
+        let (share1, share2) = (
+            std::fs::read(share1).unwrap(),
+            std::fs::read(share2).unwrap(),
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        // Even with the smallest buffer
+        for buffer in [8, 1024] {
+            let mut decrypted = Vec::new();
+            let err = decrypt_async(&mut &share1[..], &mut &share2[..], &mut decrypted, buffer)
+                .await
+                .unwrap_err();
+
+            assert!(matches!(err, Error::InvalidInput(_)));
+            assert!(decrypted.is_empty());
+        }
+    }
+}
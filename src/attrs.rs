@@ -0,0 +1,185 @@
+//! Capturing and restoring file attributes such as permissions and timestamps.
+
+use std::fs::{File, FileTimes};
+use std::io;
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+#[cfg(not(feature = "xattr"))]
+use log::warn;
+
+use crate::fs::io_error;
+use crate::{Error, IoOperation};
+
+/// Attributes of the original file, stored in the split metadata of the shares
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FileAttributes {
+    /// Unix permission bits
+    pub mode: Option<u32>,
+
+    pub modified: Option<SystemTime>,
+    pub accessed: Option<SystemTime>,
+
+    /// Unix user and group id
+    pub owner: Option<(u32, u32)>,
+
+    /// Extended attributes as name-value pairs
+    pub xattrs: Vec<(Vec<u8>, Vec<u8>)>,
+}
+
+impl FileAttributes {
+    /// Reads the attributes of `file`. Owner and extended attributes are
+    /// only read if requested.
+    pub fn read(path: &Path, file: &File, owner: bool, xattrs: bool) -> Result<Self, Error> {
+        let io_err = |error: io::Error| io_error(path, IoOperation::Read, error);
+
+        let meta = file.metadata().map_err(io_err)?;
+
+        let mut attrs = FileAttributes {
+            modified: meta.modified().ok(),
+            accessed: meta.accessed().ok(),
+            ..FileAttributes::default()
+        };
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+
+            attrs.mode = Some(meta.mode() & 0o7777);
+
+            if owner {
+                attrs.owner = Some((meta.uid(), meta.gid()));
+            }
+        }
+
+        #[cfg(all(unix, feature = "xattr"))]
+        if xattrs && xattr::SUPPORTED_PLATFORM {
+            use std::os::unix::ffi::OsStrExt;
+            use xattr::FileExt;
+
+            for name in file.list_xattr().map_err(io_err)? {
+                if let Some(value) = file.get_xattr(&name).map_err(io_err)? {
+                    attrs.xattrs.push((name.as_bytes().to_vec(), value));
+                }
+            }
+        }
+
+        #[cfg(not(unix))]
+        let _ = owner;
+
+        // Rejected by `Config::validate`
+        #[cfg(not(feature = "xattr"))]
+        let _ = xattrs;
+
+        Ok(attrs)
+    }
+
+    /// Applies the attributes to `file`. Restoring the owner is skipped if
+    /// the permissions to do so are missing.
+    pub fn apply(&self, path: &Path, file: &File) -> Result<(), Error> {
+        let io_err = |error: io::Error| io_error(path, IoOperation::Write, error);
+
+        #[cfg(all(unix, feature = "xattr"))]
+        {
+            use std::ffi::OsStr;
+            use std::os::unix::ffi::OsStrExt;
+            use xattr::FileExt;
+
+            for (name, value) in &self.xattrs {
+                file.set_xattr(OsStr::from_bytes(name), value)
+                    .map_err(io_err)?;
+            }
+        }
+
+        #[cfg(not(feature = "xattr"))]
+        if !self.xattrs.is_empty() {
+            warn!("Extended attributes of {path:?} are not restored without the cargo feature `xattr`");
+        }
+
+        #[cfg(unix)]
+        {
+            use std::fs::Permissions;
+            use std::os::unix::fs::{fchown, PermissionsExt};
+
+            if let Some((uid, gid)) = self.owner {
+                match fchown(file, Some(uid), Some(gid)) {
+                    Err(e) if e.kind() == io::ErrorKind::PermissionDenied => (),
+                    res => res.map_err(io_err)?,
+                }
+            }
+
+            // After changing the owner, which may clear the setuid and setgid bits
+            if let Some(mode) = self.mode {
+                file.set_permissions(Permissions::from_mode(mode))
+                    .map_err(io_err)?;
+            }
+        }
+
+        let mut times = FileTimes::new();
+        if let Some(modified) = self.modified {
+            times = times.set_modified(modified);
+        }
+        if let Some(accessed) = self.accessed {
+            times = times.set_accessed(accessed);
+        }
+
+        file.set_times(times).map_err(io_err)
+    }
+}
+
+/// Converts a time to seconds and nanoseconds relative to the Unix epoch.
+pub fn time_to_bytes(time: SystemTime) -> [u8; 12] {
+    let (secs, nanos) = match time.duration_since(UNIX_EPOCH) {
+        Ok(d) => (d.as_secs() as i64, d.subsec_nanos()),
+        Err(e) => {
+            let d = e.duration();
+
+            match d.subsec_nanos() {
+                0 => (-(d.as_secs() as i64), 0),
+                n => (-(d.as_secs() as i64) - 1, 1_000_000_000 - n),
+            }
+        }
+    };
+
+    let mut buf = [0u8; 12];
+    buf[..8].copy_from_slice(&secs.to_le_bytes());
+    buf[8..].copy_from_slice(&nanos.to_le_bytes());
+
+    buf
+}
+
+/// Inverse of [`time_to_bytes`]
+pub fn time_from_bytes(buf: &[u8]) -> Option<SystemTime> {
+    if buf.len() != 12 {
+        return None;
+    }
+
+    let secs = i64::from_le_bytes(buf[..8].try_into().unwrap());
+    let nanos = u32::from_le_bytes(buf[8..].try_into().unwrap());
+
+    if secs >= 0 {
+        UNIX_EPOCH.checked_add(Duration::new(secs as u64, nanos))
+    } else {
+        UNIX_EPOCH
+            .checked_sub(Duration::from_secs(secs.unsigned_abs()))?
+            .checked_add(Duration::from_nanos(nanos as u64))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_time_roundtrip() {
+        let times = [
+            UNIX_EPOCH,
+            UNIX_EPOCH + Duration::new(1_700_000_000, 123_456_789),
+            UNIX_EPOCH - Duration::new(86_400, 250_000_000),
+        ];
+
+        for time in times {
+            assert_eq!(time_from_bytes(&time_to_bytes(time)), Some(time));
+        }
+    }
+}
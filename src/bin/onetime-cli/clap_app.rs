@@ -1,65 +1,328 @@
+use std::ffi::OsString;
+use std::ops::Range;
 use std::path::PathBuf;
 
 use clap::{
     crate_authors, crate_description, crate_name, crate_version, value_parser, Arg, ArgAction,
-    ArgMatches, Command,
+    ArgMatches, ColorChoice, Command,
 };
 
-use onetime_cli::Config;
+use onetime_cli::{Compression, Config, FecParams, OnDamage, Padding, ShareFormat};
+
+use crate::qr::QrOutput;
+use crate::report::OutputFormat;
 
 pub enum Subcommand {
-    Encrypt(Config),
-    Decrypt(Config),
+    Encrypt {
+        cfg: Config,
+        qr: Option<QrOutput>,
+    },
+    Decrypt {
+        cfg: Config,
+        range: Option<Range<u64>>,
+    },
+    Verify {
+        cfg: Config,
+        locate: bool,
+    },
+    ExportPaper {
+        share: PathBuf,
+        quiet: bool,
+    },
+    ImportPaper {
+        input: PathBuf,
+        output: PathBuf,
+        quiet: bool,
+    },
     None,
 }
 
 impl From<ArgMatches> for Subcommand {
     fn from(value: ArgMatches) -> Self {
         match value.subcommand() {
-            Some(("encrypt", args)) | Some(("decrypt", args)) => {
+            Some((name @ ("encrypt" | "decrypt" | "verify"), args)) => {
+                let files: Vec<&String> = args.get_many("file").unwrap().collect();
+
                 let cfg = Config {
-                    file: PathBuf::from(args.get_one::<String>("file").unwrap()),
+                    file: match files[..] {
+                        [file] => PathBuf::from(file),
+                        _ => PathBuf::new(),
+                    },
+                    shares: match files[..] {
+                        [share1, share2] => Some([PathBuf::from(share1), PathBuf::from(share2)]),
+                        _ => None,
+                    },
+                    output: get_opt::<String>(args, "output").map(PathBuf::from),
+                    dir1: get_opt::<String>(args, "dir1").map(PathBuf::from),
+                    dir2: get_opt::<String>(args, "dir2").map(PathBuf::from),
                     suffix1: args.get_one::<String>("suffix1").unwrap().to_string(),
                     suffix2: args.get_one::<String>("suffix2").unwrap().to_string(),
+                    name_template: get_opt(args, "name_template"),
                     buffer: *args.get_one::<u32>("buffer").unwrap(),
-                    rm: args.get_flag("remove_input"),
-                    quiet: value.get_flag("quiet"),
+                    rm: get_opt(args, "remove_input").unwrap_or(false),
+                    quiet: quiet(&value),
+                    fec: fec_params(args),
+                    compression: match get_opt::<String>(args, "compress").as_deref() {
+                        Some("deflate") => Some(Compression::Deflate),
+                        _ => None,
+                    },
+                    padding: get_opt(args, "pad_length"),
+                    volume_size: get_opt(args, "volume_size"),
+                    resume: get_opt(args, "resume").unwrap_or(false),
+                    on_damage: match get_opt::<String>(args, "on_damage").as_deref() {
+                        Some("skip") => OnDamage::Skip,
+                        Some("zero") => OnDamage::Zero,
+                        _ => OnDamage::Fail,
+                    },
+                    preserve: !get_opt(args, "no_preserve").unwrap_or(false),
+                    preserve_owner: get_opt(args, "preserve_owner").unwrap_or(false),
+                    preserve_xattrs: get_opt(args, "preserve_xattrs").unwrap_or(false),
+                    format: share_format(args),
+                    cancel: None,
                 };
 
-                match value.subcommand_name().unwrap() {
-                    "encrypt" => Subcommand::Encrypt(cfg),
-                    "decrypt" => Subcommand::Decrypt(cfg),
+                match name {
+                    "encrypt" => Subcommand::Encrypt {
+                        cfg,
+                        qr: match get_opt::<String>(args, "qr").as_deref() {
+                            Some("png") => Some(QrOutput::Png),
+                            Some("terminal") => Some(QrOutput::Terminal),
+                            _ => None,
+                        },
+                    },
+                    "decrypt" => Subcommand::Decrypt {
+                        cfg,
+                        range: get_opt(args, "range"),
+                    },
+                    "verify" => Subcommand::Verify {
+                        cfg,
+                        locate: args.get_flag("locate"),
+                    },
                     _ => unreachable!("there are no other possible subcommands"),
                 }
             }
+            Some(("export-paper", args)) => Subcommand::ExportPaper {
+                share: PathBuf::from(args.get_one::<String>("share").unwrap()),
+                quiet: quiet(&value),
+            },
+            Some(("import-paper", args)) => Subcommand::ImportPaper {
+                input: PathBuf::from(args.get_one::<String>("input").unwrap()),
+                output: PathBuf::from(args.get_one::<String>("output").unwrap()),
+                quiet: quiet(&value),
+            },
             _ => Self::None,
         }
     }
 }
 
+/// Returns the format of the results printed on stdout.
+pub fn output_format(args: &ArgMatches) -> OutputFormat {
+    match args.get_one::<String>("message_format").map(String::as_str) {
+        Some("json") => OutputFormat::Json,
+        _ => OutputFormat::Text,
+    }
+}
+
+/// Returns the value of `--color` in the raw arguments `args`. It is needed
+/// before the arguments are parsed, so clap's own messages follow it as
+/// well. Invalid values are left to clap to report.
+pub fn color_choice(args: impl IntoIterator<Item = OsString>) -> ColorChoice {
+    let mut args = args.into_iter().skip(1);
+    let mut value = None;
+
+    while let Some(arg) = args.next() {
+        if arg == "--" {
+            break;
+        } else if arg == "--color" {
+            value = args.next();
+        } else if let Some(v) = arg.to_str().and_then(|a| a.strip_prefix("--color=")) {
+            value = Some(OsString::from(v));
+        }
+    }
+
+    match value.as_ref().and_then(|v| v.to_str()) {
+        Some("always") => ColorChoice::Always,
+        Some("never") => ColorChoice::Never,
+        _ => ColorChoice::Auto,
+    }
+}
+
+/// Text output is suppressed with JSON output, so stdout only has the result.
+fn quiet(args: &ArgMatches) -> bool {
+    args.get_flag("quiet") || output_format(args) == OutputFormat::Json
+}
+
+/// Returns the value of an argument that not all subcommands have.
+fn get_opt<T: Clone + Send + Sync + 'static>(args: &ArgMatches, id: &str) -> Option<T> {
+    args.try_get_one::<T>(id).ok().flatten().cloned()
+}
+
+fn share_format(args: &ArgMatches) -> ShareFormat {
+    if get_opt(args, "armor").unwrap_or(false) {
+        ShareFormat::Armor
+    } else if get_opt(args, "words").unwrap_or(false) {
+        ShareFormat::Words
+    } else {
+        ShareFormat::Binary
+    }
+}
+
+/// Parses a size like `4096`, `64KiB` or `1GB`. Units are case-insensitive.
+fn parse_size(s: &str) -> Result<u64, String> {
+    let s = s.trim();
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (number, unit) = s.split_at(split);
+
+    let number: u64 = number.parse().map_err(|_| format!("invalid size '{s}'"))?;
+
+    let factor: u64 = match unit.trim().to_ascii_lowercase().as_str() {
+        "" | "b" => 1,
+        "k" | "kib" => 1 << 10,
+        "m" | "mib" => 1 << 20,
+        "g" | "gib" => 1 << 30,
+        "t" | "tib" => 1 << 40,
+        "kb" => 1_000,
+        "mb" => 1_000_000,
+        "gb" => 1_000_000_000,
+        "tb" => 1_000_000_000_000,
+        _ => return Err(format!("unknown unit '{unit}'")),
+    };
+
+    number
+        .checked_mul(factor)
+        .ok_or_else(|| format!("size '{s}' is too large"))
+}
+
+/// Parses a byte range like `1G..1G+4M`. Both ends may be sums of sizes,
+/// and the end may be left out.
+fn parse_range(s: &str) -> Result<Range<u64>, String> {
+    let (start, end) = s
+        .split_once("..")
+        .ok_or_else(|| "expected a range like '1G..1G+4M'".to_string())?;
+
+    let sum = |expr: &str| match expr.trim() {
+        "" => Ok(None),
+        expr => expr
+            .split('+')
+            .try_fold(0u64, |sum, term| {
+                sum.checked_add(parse_size(term)?)
+                    .ok_or_else(|| format!("range '{s}' is too large"))
+            })
+            .map(Some),
+    };
+
+    let start = sum(start)?.unwrap_or(0);
+    let end = sum(end)?.unwrap_or(u64::MAX);
+
+    match start <= end {
+        true => Ok(start..end),
+        false => Err(format!("the end of range '{s}' is before its start")),
+    }
+}
+
+fn parse_padding(s: &str) -> Result<Padding, String> {
+    match s {
+        "random" => Ok(Padding::Random),
+        "pow2" => Ok(Padding::PowerOfTwo),
+        _ => parse_size(s).map(Padding::Block),
+    }
+}
+
+fn fec_params(args: &ArgMatches) -> Option<FecParams> {
+    let parity = get_opt::<u8>(args, "fec")?;
+    let block_size = *args.get_one::<u32>("fec_block_size").unwrap();
+
+    Some(FecParams {
+        block_size,
+        ..FecParams::new(parity)
+    })
+}
+
 pub fn build_clap_app() -> Command {
     let mut cmd = Command::new(crate_name!())
         .author(crate_authors!())
         .about(crate_description!())
         .version(crate_version!())
         .after_help("Use --help for more detailed help information.")
-        .after_long_help("Use -h for shorter help information.");
+        .after_long_help(
+            "Exit status:\n  \
+                0    success\n  \
+                1    any other error\n  \
+                2    invalid arguments or configuration, or no subcommand\n  \
+                3    a file could not be opened, read, written or removed\n  \
+                4    invalid input files, or files that differ in size or don't belong together\n  \
+                5    damaged input files, or decrypted data that doesn't match the original\n  \
+                130  cancelled with Ctrl-C, partial output files are removed\n\n\
+            Use -h for shorter help information.",
+        );
 
     cmd = build_subcommand_encrypt(cmd);
     cmd = build_subcommand_decrypt(cmd);
+    cmd = build_subcommand_verify(cmd);
+    cmd = build_subcommand_export_paper(cmd);
+    cmd = build_subcommand_import_paper(cmd);
     cmd = build_main_args(cmd);
     cmd
 }
 
 fn build_main_args(cmd: Command) -> Command {
-    cmd
-        .arg(
-            Arg::new("quiet")
-                .short('q')
-                .long("quiet")
-                .action(ArgAction::SetTrue)
-                .help("Don't print anything to stdout")
-        )
+    cmd.arg(
+        Arg::new("quiet")
+            .short('q')
+            .long("quiet")
+            .action(ArgAction::SetTrue)
+            .help("Don't print anything to stdout"),
+    )
+    .arg(
+        Arg::new("message_format")
+            .long("message-format")
+            .value_name("format")
+            .value_parser(["text", "json"])
+            .default_value("text")
+            .global(true)
+            .help("Format of the results printed to stdout")
+            .long_help(
+                "Format of the results printed to stdout, e.g.\n\
+                    `onetime-cli --message-format json encrypt file`.\n\n  \
+                    text:  messages for humans (default)\n  \
+                    json:  one JSON object per operation, with the paths and SHA-256\n         \
+                    digests of all outputs, the number of bytes processed, the\n         \
+                    duration and any error with its category",
+            ),
+    )
+    .arg(
+        Arg::new("verbose")
+            .short('v')
+            .long("verbose")
+            .action(ArgAction::Count)
+            .global(true)
+            .help("Log what is being done to stderr, -vv for more details")
+            .long_help(
+                "Log what is being done to stderr, like the files opened, buffer sizes,\n\
+                    chunk counts and timings. -v logs a summary of each operation, -vv every\n\
+                    step and -vvv every chunk. The data itself is never logged.\n\n\
+                    RUST_LOG overrides the level for some or all messages, e.g.\n\
+                    RUST_LOG=onetime_cli::otp=trace. It takes a comma-separated list of\n\
+                    levels (off, error, warn, info, debug, trace), optionally prefixed\n\
+                    with a module and '='.",
+            ),
+    )
+    .arg(
+        Arg::new("color")
+            .long("color")
+            .value_name("when")
+            .value_parser(["auto", "always", "never"])
+            .default_value("auto")
+            .global(true)
+            .help("When to use colors in messages")
+            .long_help(
+                "When to use colors in messages.\n\n  \
+                    auto:    if stderr is a terminal and NO_COLOR is not set, or if\n           \
+                    CLICOLOR_FORCE is set (default)\n  \
+                    always:  always, regardless of NO_COLOR\n  \
+                    never:   never, regardless of CLICOLOR_FORCE",
+            ),
+    )
 }
 
 fn build_subcommand_encrypt(cmd: Command) -> Command {
@@ -97,6 +360,48 @@ fn build_subcommand_encrypt(cmd: Command) -> Command {
                     .help("Suffix for the name of the second output file")
                     .long_help("The same as --out1-suffix, but for the second output file."),
             )
+            .arg(
+                Arg::new("dir1")
+                    .long("out1-dir")
+                    .value_name("dir")
+                    .action(ArgAction::Set)
+                    .help("Directory for the first output file")
+                    .long_help(
+                        "Directory for the first output file. Defaults to the directory of\n\
+                            the input file. To keep the output files apart, use different\n\
+                            devices for --out1-dir and --out2-dir; a warning is printed if\n\
+                            both are on the same device.",
+                    ),
+            )
+            .arg(
+                Arg::new("dir2")
+                    .long("out2-dir")
+                    .value_name("dir")
+                    .action(ArgAction::Set)
+                    .help("Directory for the second output file")
+                    .long_help("The same as --out1-dir, but for the second output file."),
+            )
+            .arg(
+                Arg::new("name_template")
+                    .long("name-template")
+                    .value_name("template")
+                    .action(ArgAction::Set)
+                    .conflicts_with_all(["suffix1", "suffix2"])
+                    .help("Template for the names of the output files")
+                    .long_help(
+                        "Template for the names of the output files, used instead of the\n\
+                            suffixes. Supported placeholders:\n  \
+                            {dir}    directory of the input file, or --out1-dir/--out2-dir\n  \
+                            {name}   name of the input file\n  \
+                            {stem}   name of the input file without its extension\n  \
+                            {ext}    extension of the input file\n  \
+                            {index}  0 for the first output file, 1 for the second\n  \
+                            {set}    random id shared by both output files\n  \
+                            {date}   date of encryption (YYYY-MM-DD, UTC)\n\n\
+                            Example:\n  \
+                            --name-template '{dir}/{stem}.{index}.share'",
+                    ),
+            )
             .arg(
                 Arg::new("buffer")
                     .short('b')
@@ -111,6 +416,162 @@ fn build_subcommand_encrypt(cmd: Command) -> Command {
                     .long("rm")
                     .action(ArgAction::SetTrue)
                     .help("Delete input file after encryption"),
+            )
+            .arg(
+                Arg::new("fec")
+                    .long("fec")
+                    .value_name("parity")
+                    .value_parser(value_parser!(u8).range(1..=224))
+                    .action(ArgAction::Set)
+                    .help("Add error correction data to the output files")
+                    .long_help(
+                        "Add error correction data to the output files.\n\n\
+                            The output files are divided into groups of 32 blocks. For every\n\
+                            group, <PARITY> additional blocks are stored, which allows to repair\n\
+                            up to <PARITY> damaged blocks per group when decrypting. For example,\n\
+                            '--fec 4' grows the output files by 12.5 % and repairs up to 4\n\
+                            damaged blocks out of every 36.",
+                    ),
+            )
+            .arg(
+                Arg::new("fec_block_size")
+                    .long("fec-block-size")
+                    .value_name("size")
+                    .value_parser(value_parser!(u32).range(1..=16777216))
+                    .default_value("4096")
+                    .requires("fec")
+                    .action(ArgAction::Set)
+                    .help("Size of the error correction blocks in bytes"),
+            )
+            .arg(
+                Arg::new("no_preserve")
+                    .long("no-preserve")
+                    .action(ArgAction::SetTrue)
+                    .help("Don't store permissions and timestamps of the input file"),
+            )
+            .arg(
+                Arg::new("preserve_owner")
+                    .long("preserve-owner")
+                    .action(ArgAction::SetTrue)
+                    .conflicts_with("no_preserve")
+                    .help("Also store the owner of the input file"),
+            )
+            .arg(
+                Arg::new("preserve_xattrs")
+                    .long("preserve-xattrs")
+                    .action(ArgAction::SetTrue)
+                    .conflicts_with("no_preserve")
+                    .help("Also store the extended attributes of the input file"),
+            )
+            .arg(
+                Arg::new("armor")
+                    .long("armor")
+                    .action(ArgAction::SetTrue)
+                    .help("Write the output files as ASCII armor")
+                    .long_help(
+                        "Write the output files as ASCII armor: base64 wrapped at 64 columns\n\
+                            between BEGIN and END lines, which can be pasted into emails or\n\
+                            tickets. decrypt detects armored input files automatically.",
+                    ),
+            )
+            .arg(
+                Arg::new("pad_length")
+                    .long("pad-length")
+                    .value_name("mode")
+                    .value_parser(parse_padding)
+                    .action(ArgAction::Set)
+                    .help("Pad the input file to hide its length: random, pow2 or a block size")
+                    .long_help(
+                        "Pad the input file before encrypting it, so the size of the output\n\
+                            files doesn't reveal its exact length. The true length is stored\n\
+                            in the output files, and decrypt removes the padding.\n\n\
+                            Modes:\n  \
+                            random  Add a random number of bytes, up to an eighth of the\n          \
+                            size of the file, but at least up to 4 KiB\n  \
+                            pow2    Round the size up to the next power of two\n  \
+                            <SIZE>  Round the size up to a multiple of <SIZE>, e.g. 1MiB",
+                    ),
+            )
+            .arg(
+                Arg::new("volume_size")
+                    .long("volume-size")
+                    .value_name("size")
+                    .value_parser(parse_size)
+                    .action(ArgAction::Set)
+                    .help("Split the output files into volumes of at most <SIZE>, e.g. 4GiB")
+                    .long_help(
+                        "Split the output files into volumes of at most <SIZE>, e.g. 4GiB.\n\n\
+                            The volumes are named after the output files with a number\n\
+                            appended, e.g. 'secret.txt.otp.0.001', 'secret.txt.otp.0.002' and so\n\
+                            on. decrypt finds and joins them automatically and reports missing\n\
+                            volumes and volumes that are out of order.",
+                    ),
+            )
+            .arg(
+                Arg::new("resume")
+                    .long("resume")
+                    .action(ArgAction::SetTrue)
+                    .conflicts_with_all(["armor", "words", "fec", "compress", "volume_size"])
+                    .help("Record the progress in a journal and continue an interrupted run")
+                    .long_help(
+                        "Record the progress in a journal next to the first output file, and\n\
+                            continue an interrupted run that was started with --resume as well.\n\
+                            Before continuing, the output files are checked against digests in\n\
+                            the journal, and the input file against the output files. The journal\n\
+                            contains nothing about the input file.",
+                    ),
+            )
+            .arg(
+                Arg::new("compress")
+                    .long("compress")
+                    .value_name("codec")
+                    .value_parser(["deflate"])
+                    .num_args(0..=1)
+                    .default_missing_value("deflate")
+                    .action(ArgAction::Set)
+                    .help("Compress the input file before encrypting it")
+                    .long_help(
+                        "Compress the input file before encrypting it, which makes the output\n\
+                            files of compressible files smaller. The codec is stored in the\n\
+                            output files, so decrypt doesn't need this option.\n\n\
+                            Damaged data of compressed output files can't be skipped or\n\
+                            zero-filled with --on-damage.",
+                    ),
+            )
+            .arg(
+                Arg::new("words")
+                    .long("words")
+                    .action(ArgAction::SetTrue)
+                    .conflicts_with_all(["armor", "fec", "compress", "pad_length", "volume_size"])
+                    .help("Write the output files as lists of words")
+                    .long_help(
+                        "Write the output files as lists of words, which can be written down\n\
+                            by hand and typed in again. Meant for short secrets like passwords,\n\
+                            at most 1024 bytes. The name and attributes of the input file are not\n\
+                            stored, and the decrypted file can't be checked against a digest.\n\n\
+                            decrypt detects word lists automatically. Only the first four letters\n\
+                            of each word are needed, so typos after them don't matter.",
+                    ),
+            )
+            .arg(
+                Arg::new("qr")
+                    .long("qr")
+                    .value_name("output")
+                    .value_parser(["png", "terminal"])
+                    .num_args(0..=1)
+                    .default_missing_value("png")
+                    .action(ArgAction::Set)
+                    .conflicts_with("volume_size")
+                    .help("Also render the output files as QR codes")
+                    .long_help(
+                        "Also render the output files as QR codes, for paper backups.\n\n  \
+                            png:       write PNG images next to the output files (default)\n  \
+                            terminal:  print the codes to stdout, even with --quiet\n\n\
+                            Output files that don't fit into a single code are split across\n\
+                            several numbered codes. To decrypt, put the scanned texts of all\n\
+                            codes of an output file into a text file, one per line, and pass\n\
+                            it to decrypt instead of the output file.",
+                    ),
             ),
     )
 }
@@ -123,9 +584,25 @@ fn build_subcommand_decrypt(cmd: Command) -> Command {
                 .arg(
                     Arg::new("file")
                         .value_name("FILE")
+                        .num_args(1..=2)
                         .action(ArgAction::Set)
                         .required(true)
-                        .help("Output file name. This is the name of the decrypted file.")
+                        .help("Output file name, or the two encrypted files")
+                        .long_help(
+                            "Output file name. This is the name of the decrypted file. The names\n\
+                            of the encrypted files are derived from it using the suffixes.\n\n\
+                            Alternatively, the two encrypted files can be passed directly. The\n\
+                            decrypted file then gets its original name, which is stored in the\n\
+                            encrypted files, unless --output is given."
+                        )
+                )
+                .arg(
+                    Arg::new("output")
+                        .short('o')
+                        .long("output")
+                        .value_name("file")
+                        .action(ArgAction::Set)
+                        .help("Write the decrypted file to <FILE> instead")
                 )
                 .arg(
                     Arg::new("suffix1")
@@ -153,6 +630,39 @@ fn build_subcommand_decrypt(cmd: Command) -> Command {
                             "The same as --in1-suffix, but for the second input file."
                         )
                 )
+                .arg(
+                    Arg::new("dir1")
+                        .long("in1-dir")
+                        .value_name("dir")
+                        .action(ArgAction::Set)
+                        .help("Directory of the first input file")
+                        .long_help(
+                            "Directory of the first input file. Defaults to the directory of\n\
+                            the output file."
+                        )
+                )
+                .arg(
+                    Arg::new("dir2")
+                        .long("in2-dir")
+                        .value_name("dir")
+                        .action(ArgAction::Set)
+                        .help("Directory of the second input file")
+                        .long_help("The same as --in1-dir, but for the second input file.")
+                )
+                .arg(
+                    Arg::new("name_template")
+                        .long("name-template")
+                        .value_name("template")
+                        .action(ArgAction::Set)
+                        .conflicts_with_all(["suffix1", "suffix2"])
+                        .help("Template for the names of the input files")
+                        .long_help(
+                            "Template for the names of the input files, used instead of the\n\
+                            suffixes. Takes the same placeholders as --name-template of\n\
+                            subcommand encrypt. {set} and {date} match any value, but only in\n\
+                            the file name, not in the directory."
+                        )
+                )
                 .arg(
                     Arg::new("buffer")
                         .short('b')
@@ -168,5 +678,230 @@ fn build_subcommand_decrypt(cmd: Command) -> Command {
                         .action(ArgAction::SetTrue)
                         .help("Delete input file after encryption")
                 )
+                .arg(
+                    Arg::new("on_damage")
+                        .long("on-damage")
+                        .value_name("action")
+                        .value_parser(["fail", "skip", "zero"])
+                        .default_value("fail")
+                        .action(ArgAction::Set)
+                        .help("What to do with damaged chunks of the input files")
+                        .long_help(
+                            "What to do with chunks of the input files that don't match their\n\
+                            hash trees.\n\n  \
+                            fail: stop and delete the output file\n  \
+                            skip: leave the damaged chunks out of the output file\n  \
+                            zero: fill the damaged chunks of the output file with zeros\n\n\
+                            If any chunks are skipped or zero-filled, the input files are kept\n\
+                            even if --rm is given."
+                        )
+                )
+                .arg(
+                    Arg::new("range")
+                        .long("range")
+                        .value_name("range")
+                        .value_parser(parse_range)
+                        .action(ArgAction::Set)
+                        .conflicts_with_all(["remove_input", "resume"])
+                        .help("Only decrypt a range of bytes and write it to stdout or <FILE> of --output")
+                        .long_help(
+                            "Only decrypt a range of bytes of the original file, e.g. 1G..1G+4M\n\
+                            for 4 MiB after the first GiB. Both ends may be sums of sizes, and\n\
+                            either end may be left out. The bytes are written to stdout, or to\n\
+                            the file given by --output.\n\n\
+                            Only the parts of the input files that contain the range are read,\n\
+                            so the decrypted bytes can't be checked against the stored digest.\n\
+                            Not supported for compressed input files."
+                        )
+                )
+                .arg(
+                    Arg::new("resume")
+                        .long("resume")
+                        .action(ArgAction::SetTrue)
+                        .help("Record the progress in a journal and continue an interrupted run")
+                        .long_help(
+                            "Record the progress in a journal next to the output file, and\n\
+                            continue an interrupted run that was started with --resume as well.\n\
+                            Before continuing, the output file is checked against the input files.\n\
+                            Not supported for compressed input files or with --on-damage."
+                        )
+                )
+                .arg(
+                    Arg::new("no_preserve")
+                        .long("no-preserve")
+                        .action(ArgAction::SetTrue)
+                        .help("Don't restore the stored permissions, timestamps, owner and extended attributes")
+                )
         )
 }
+
+fn build_subcommand_verify(cmd: Command) -> Command {
+    cmd.subcommand(
+        Command::new("verify")
+            .about("Check the encrypted files of a file for damage")
+            .arg(
+                Arg::new("file")
+                    .value_name("FILE")
+                    .num_args(1..=2)
+                    .action(ArgAction::Set)
+                    .required(true)
+                    .help("Name of the decrypted file, or the two encrypted files"),
+            )
+            .arg(
+                Arg::new("suffix1")
+                    .long("in1-suffix")
+                    .value_name("suffix")
+                    .default_value("otp.0")
+                    .help("Suffix for the name of the first input file")
+                    .long_help("The same as --in1-suffix of subcommand decrypt."),
+            )
+            .arg(
+                Arg::new("suffix2")
+                    .long("in2-suffix")
+                    .value_name("suffix")
+                    .default_value("otp.1")
+                    .help("Suffix for the name of the second input file")
+                    .long_help("The same as --in2-suffix of subcommand decrypt."),
+            )
+            .arg(
+                Arg::new("dir1")
+                    .long("in1-dir")
+                    .value_name("dir")
+                    .action(ArgAction::Set)
+                    .help("Directory of the first input file"),
+            )
+            .arg(
+                Arg::new("dir2")
+                    .long("in2-dir")
+                    .value_name("dir")
+                    .action(ArgAction::Set)
+                    .help("Directory of the second input file"),
+            )
+            .arg(
+                Arg::new("name_template")
+                    .long("name-template")
+                    .value_name("template")
+                    .action(ArgAction::Set)
+                    .conflicts_with_all(["suffix1", "suffix2"])
+                    .help("Template for the names of the input files")
+                    .long_help("The same as --name-template of subcommand decrypt."),
+            )
+            .arg(
+                Arg::new("buffer")
+                    .short('b')
+                    .value_name("size")
+                    .value_parser(value_parser!(u32))
+                    .default_value("1048576")
+                    .action(ArgAction::Set)
+                    .help("Buffer size in bytes"),
+            )
+            .arg(
+                Arg::new("locate")
+                    .long("locate")
+                    .action(ArgAction::SetTrue)
+                    .help("List the damaged byte ranges of each input file"),
+            ),
+    )
+}
+
+fn build_subcommand_export_paper(cmd: Command) -> Command {
+    cmd.subcommand(
+        Command::new("export-paper")
+            .about("Create a printable backup of an encrypted file")
+            .long_about(
+                "Create a printable backup of an encrypted file, as plain text (<SHARE>.paper.txt)\n\
+                    and as PDF (<SHARE>.paper.pdf). The backup contains the encrypted file in\n\
+                    numbered lines of base32 with a checksum per line, along with instructions\n\
+                    for recovering it.",
+            )
+            .arg(
+                Arg::new("share")
+                    .value_name("SHARE")
+                    .action(ArgAction::Set)
+                    .required(true)
+                    .help("Encrypted file to back up"),
+            ),
+    )
+}
+
+fn build_subcommand_import_paper(cmd: Command) -> Command {
+    cmd.subcommand(
+        Command::new("import-paper")
+            .about("Restore an encrypted file from a typed-in paper backup")
+            .long_about(
+                "Restore an encrypted file from a typed-in paper backup. Only the numbered\n\
+                    lines of <INPUT> are read; lines with typos are reported by their numbers.",
+            )
+            .arg(
+                Arg::new("input")
+                    .value_name("INPUT")
+                    .action(ArgAction::Set)
+                    .required(true)
+                    .help("Text file with the lines of the paper backup"),
+            )
+            .arg(
+                Arg::new("output")
+                    .value_name("OUTPUT")
+                    .action(ArgAction::Set)
+                    .required(true)
+                    .help("Path of the restored encrypted file"),
+            ),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("4096"), Ok(4096));
+        assert_eq!(parse_size("64KiB"), Ok(64 << 10));
+        assert_eq!(parse_size("4 gib"), Ok(4 << 30));
+        assert_eq!(parse_size("1GB"), Ok(1_000_000_000));
+        assert!(parse_size("1.5G").is_err());
+        assert!(parse_size("GiB").is_err());
+        assert!(parse_size("100000000TiB").is_err());
+    }
+
+    #[test]
+    fn test_parse_range() {
+        assert_eq!(
+            parse_range("1G..1G+4M"),
+            Ok((1 << 30)..(1 << 30) + (4 << 20))
+        );
+        assert_eq!(parse_range("100..200"), Ok(100..200));
+        assert_eq!(parse_range("..4K"), Ok(0..4096));
+        assert_eq!(parse_range("1M.."), Ok((1 << 20)..u64::MAX));
+        assert!(parse_range("200..100").is_err());
+        assert!(parse_range("100").is_err());
+    }
+
+    #[test]
+    fn test_color_choice() {
+        let args = |args: &[&str]| args.iter().map(OsString::from).collect::<Vec<_>>();
+
+        assert_eq!(
+            color_choice(args(&["onetime-cli", "encrypt", "file"])),
+            ColorChoice::Auto
+        );
+        assert_eq!(
+            color_choice(args(&[
+                "onetime-cli",
+                "--color",
+                "never",
+                "encrypt",
+                "file"
+            ])),
+            ColorChoice::Never
+        );
+        assert_eq!(
+            color_choice(args(&["onetime-cli", "decrypt", "--color=always", "file"])),
+            ColorChoice::Always
+        );
+        assert_eq!(
+            color_choice(args(&["onetime-cli", "decrypt", "--", "--color=always"])),
+            ColorChoice::Auto
+        );
+    }
+}
@@ -1,58 +1,171 @@
 use onetime_cli::Error;
 
-const RED_ERROR_TEXT: &str = "\x1b[1;91mError\x1b[0m";
-const BOLD_START: &str = "\x1b[01m";
-const STYLE_END: &str = "\x1b[0m";
+use crate::report;
+use crate::style::Style;
+
+/// Exit codes of the process for each category of failure. Documented in
+/// the long help, see `build_clap_app`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExitCode {
+    /// Any other error
+    Failure = 1,
+
+    /// Invalid command line arguments or configuration. clap uses the same
+    /// code for its parsing errors.
+    Usage = 2,
+
+    /// A file could not be opened, read, written or removed
+    Io = 3,
+
+    /// The input files are invalid, differ in size or don't belong together
+    InvalidInput = 4,
+
+    /// The input files are damaged or the decrypted data doesn't match the
+    /// original
+    Integrity = 5,
+
+    /// The operation was cancelled with Ctrl-C, like the shell reports for
+    /// processes killed by SIGINT
+    Cancelled = 130,
+}
+
+impl ExitCode {
+    /// Returns the exit code for `e`.
+    pub fn of(e: &Error) -> Self {
+        match e {
+            Error::IoError(_) => ExitCode::Io,
+            Error::InvalidConfig(_) | Error::OutputExists(_) => ExitCode::Usage,
+            Error::InvalidInput(_) | Error::InvalidBufferSizes | Error::ShareMismatch(_) => {
+                ExitCode::InvalidInput
+            }
+            Error::DigestMismatch | Error::IntegrityFailure { .. } => ExitCode::Integrity,
+            Error::Cancelled => ExitCode::Cancelled,
+            _ => ExitCode::Failure,
+        }
+    }
+
+    /// Name of the category in JSON output, see `report`
+    pub fn category(self) -> &'static str {
+        match self {
+            ExitCode::Failure => "failure",
+            ExitCode::Usage => "usage",
+            ExitCode::Io => "io",
+            ExitCode::InvalidInput => "invalid_input",
+            ExitCode::Integrity => "integrity",
+            ExitCode::Cancelled => "cancelled",
+        }
+    }
+}
 
 pub trait ErrorHandler<T> {
-    fn unwrap_or_exit(self, code: i32) -> T;
+    fn unwrap_or_exit(self) -> T;
 }
 
 impl<T> ErrorHandler<T> for Result<T, Error> {
-    fn unwrap_or_exit(self, code: i32) -> T {
+    fn unwrap_or_exit(self) -> T {
         match self {
             Ok(d) => d,
             Err(e) => {
+                let code = ExitCode::of(&e);
+                report::error(&e.to_string(), code, Vec::new());
+
                 eprintln!("{}", err_to_text(e));
-                std::process::exit(code);
+                std::process::exit(code as i32);
             }
         }
     }
 }
 
+pub fn warn(message: &str) {
+    eprintln!("{}: {message}", Style::Warning.paint("Warning"));
+}
+
+pub fn exit_with_error(message: &str, code: ExitCode) -> ! {
+    report::error(message, code, Vec::new());
+
+    eprintln!("{}: {message}", Style::Error.paint("Error"));
+    std::process::exit(code as i32);
+}
+
 pub fn err_to_text(e: Error) -> String {
+    let label = Style::Error.paint("Error");
+
     match e {
         Error::IoError(io_e) => {
+            let target = match &io_e.path {
+                Some(path) => format!(" {path:?}"),
+                None => String::new(),
+            };
+
             format!(
-                "{RED_ERROR_TEXT}: File: {0}; {BOLD_START}{1}{STYLE_END}",
-                io_e.file, io_e.error
+                "{label}: Failed to {}{target}: {}",
+                io_e.operation,
+                Style::Bold.paint(&io_e.error.to_string())
             )
         }
         Error::InvalidInput(s) => {
-            format!("{RED_ERROR_TEXT}: {s}")
+            format!("{label}: {s}")
         }
         Error::InvalidBufferSizes => {
-            format!("{RED_ERROR_TEXT}: Invalid buffer sizes")
+            format!("{label}: Invalid buffer sizes")
         }
+        Error::DigestMismatch => {
+            format!(
+                "{label}: {}: the decrypted data does not match the original",
+                Style::Bold.paint("Integrity check failed")
+            )
+        }
+        Error::InvalidConfig(e) => {
+            format!("{label}: Invalid configuration: {e}")
+        }
+        e => format!("{label}: {e}"),
     }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
-    use onetime_cli::IoError;
+    use onetime_cli::{ConfigError, IoError, IoOperation};
     use std::io;
 
     #[test]
     fn test_print_err_with_io_error() {
-        let e = Error::IoError(IoError {
-            file: "picture.png".to_string(),
-            error: io::Error::from(io::ErrorKind::NotFound),
-        });
+        let e = Error::IoError(IoError::new(
+            "picture.png",
+            IoOperation::Open,
+            io::Error::from(io::ErrorKind::NotFound),
+        ));
 
         assert_eq!(
             err_to_text(e),
-            format!("{RED_ERROR_TEXT}: File: picture.png; {BOLD_START}entity not found{STYLE_END}"),
+            "Error: Failed to open \"picture.png\": entity not found",
+        );
+    }
+
+    #[test]
+    fn test_exit_codes() {
+        let io_e = |kind| {
+            Error::IoError(IoError::new(
+                "picture.png",
+                IoOperation::Read,
+                io::Error::from(kind),
+            ))
+        };
+
+        assert_eq!(ExitCode::of(&io_e(io::ErrorKind::NotFound)), ExitCode::Io);
+        assert_eq!(
+            ExitCode::of(&io_e(io::ErrorKind::Interrupted)),
+            ExitCode::Io
+        );
+        assert_eq!(
+            ExitCode::of(&Error::InvalidInput(String::new())),
+            ExitCode::InvalidInput
+        );
+        assert_eq!(ExitCode::of(&Error::DigestMismatch), ExitCode::Integrity);
+        assert_eq!(ExitCode::of(&Error::Cancelled), ExitCode::Cancelled);
+        assert_eq!(
+            ExitCode::of(&Error::InvalidConfig(ConfigError::VolumeTooSmall)),
+            ExitCode::Usage
         );
     }
 
@@ -60,6 +173,6 @@ mod test {
     fn test_print_err_with_input_error() {
         let e = Error::InvalidInput("param1 is None".to_string());
 
-        assert_eq!(err_to_text(e), format!("{RED_ERROR_TEXT}: param1 is None"));
+        assert_eq!(err_to_text(e), "Error: param1 is None");
     }
 }
@@ -0,0 +1,136 @@
+//! Log messages of the library on stderr.
+//!
+//! The messages to print are selected by a filter like the one of
+//! `env_logger`: a comma-separated list of directives, each either a level
+//! (`debug`) or a target with a level (`onetime_cli::otp=trace`). The most
+//! specific directive for the target of a message applies.
+
+use log::{Level, LevelFilter, Log, Metadata, Record};
+
+use crate::style::Style;
+
+struct Logger {
+    /// Level of messages with a target not matching any directive
+    default: LevelFilter,
+
+    /// Targets with their levels, sorted by the length of the target
+    directives: Vec<(String, LevelFilter)>,
+}
+
+impl Logger {
+    fn parse(filter: &str, default: LevelFilter) -> Self {
+        let mut logger = Logger {
+            default,
+            directives: Vec::new(),
+        };
+
+        for directive in filter.split(',').map(str::trim).filter(|d| !d.is_empty()) {
+            match directive.split_once('=') {
+                Some((target, level)) => {
+                    if let Ok(level) = level.trim().parse() {
+                        logger.directives.push((target.trim().to_string(), level));
+                    }
+                }
+                None => match directive.parse() {
+                    Ok(level) => logger.default = level,
+                    // A target without a level enables all of its messages
+                    Err(_) => logger
+                        .directives
+                        .push((directive.to_string(), LevelFilter::Trace)),
+                },
+            }
+        }
+
+        logger.directives.sort_by_key(|(target, _)| target.len());
+        logger
+    }
+
+    fn level(&self, target: &str) -> LevelFilter {
+        self.directives
+            .iter()
+            .rev()
+            .find(|(prefix, _)| is_within(target, prefix))
+            .map_or(self.default, |(_, level)| *level)
+    }
+
+    fn max_level(&self) -> LevelFilter {
+        self.directives
+            .iter()
+            .map(|(_, level)| *level)
+            .fold(self.default, |max, level| max.max(level))
+    }
+}
+
+/// Returns whether `target` is the module `prefix` or one of its submodules.
+fn is_within(target: &str, prefix: &str) -> bool {
+    match target.strip_prefix(prefix) {
+        Some(rest) => rest.is_empty() || rest.starts_with("::"),
+        None => false,
+    }
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level(metadata.target())
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        let label = match record.level() {
+            Level::Error => Style::Error.paint("ERROR"),
+            Level::Warn => Style::Warning.paint("WARN"),
+            level => Style::Bold.paint(level.as_str()),
+        };
+
+        eprintln!("[{label} {}] {}", record.target(), record.args());
+    }
+
+    fn flush(&self) {}
+}
+
+/// Installs the logger. `verbosity` is the number of `-v` flags, which sets
+/// the level of all messages; `RUST_LOG` overrides it if it is set.
+pub fn init(verbosity: u8) {
+    let default = match verbosity {
+        0 => LevelFilter::Warn,
+        1 => LevelFilter::Info,
+        2 => LevelFilter::Debug,
+        _ => LevelFilter::Trace,
+    };
+
+    let filter = std::env::var("RUST_LOG").unwrap_or_default();
+    let logger = Logger::parse(&filter, default);
+
+    log::set_max_level(logger.max_level());
+    let _ = log::set_logger(Box::leak(Box::new(logger)));
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let logger = Logger::parse(
+            "info, onetime_cli::otp=trace,onetime_cli=off",
+            LevelFilter::Warn,
+        );
+
+        assert_eq!(logger.level("onetime_cli::otp"), LevelFilter::Trace);
+        assert_eq!(logger.level("onetime_cli::otp::inner"), LevelFilter::Trace);
+        assert_eq!(logger.level("onetime_cli::otpx"), LevelFilter::Off);
+        assert_eq!(logger.level("onetime_cli::fs"), LevelFilter::Off);
+        assert_eq!(logger.level("other"), LevelFilter::Info);
+        assert_eq!(logger.max_level(), LevelFilter::Trace);
+
+        let logger = Logger::parse("", LevelFilter::Debug);
+        assert_eq!(logger.level("onetime_cli"), LevelFilter::Debug);
+
+        let logger = Logger::parse("onetime_cli", LevelFilter::Warn);
+        assert_eq!(logger.level("onetime_cli::otp"), LevelFilter::Trace);
+        assert_eq!(logger.level("other"), LevelFilter::Warn);
+    }
+}
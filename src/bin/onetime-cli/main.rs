@@ -1,30 +1,366 @@
 mod clap_app;
 mod error_handler;
+mod logger;
+mod pdf;
+mod qr;
+mod report;
+mod style;
 
 use clap_app::{build_clap_app, Subcommand};
-use error_handler::ErrorHandler;
+use error_handler::{exit_with_error, warn, ErrorHandler, ExitCode};
+use onetime_cli::{Config, Error, IoError, IoOperation, VerifySummary};
+use qr::QrOutput;
+use report::{Json, OutputFormat};
+use std::ffi::OsString;
+use std::fs::File;
+use std::io::{self, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 
 fn main() {
-    let args = build_clap_app().get_matches();
+    let color = clap_app::color_choice(std::env::args_os());
+    style::init(color);
+
+    let args = build_clap_app().color(color).get_matches();
+    logger::init(args.get_count("verbose"));
+
+    if let (OutputFormat::Json, Some(operation)) =
+        (clap_app::output_format(&args), args.subcommand_name())
+    {
+        report::init_json(operation);
+    }
 
     match Subcommand::from(args) {
-        Subcommand::Encrypt(e) => {
-            onetime_cli::encrypt_file(&e).unwrap_or_exit(1);
+        Subcommand::Encrypt { cfg: mut e, qr } => {
+            if report::is_json() && qr == Some(QrOutput::Terminal) {
+                exit_with_error(
+                    "QR codes can't be printed to stdout with JSON output",
+                    ExitCode::Usage,
+                );
+            }
+
+            if (e.dir1.is_some() || e.dir2.is_some()) && e.shares_on_same_device() == Some(true) {
+                warn("both output files are on the same device");
+            }
+
+            e.cancel = Some(cancel_on_ctrl_c());
+            let summary = onetime_cli::encrypt_file(&e).unwrap_or_exit();
 
             if !e.quiet {
                 println!("Successfully encrypted {}", e.file.to_string_lossy());
             }
+
+            let mut outputs = summary.shares.to_vec();
+
+            if let Some(output) = qr {
+                for share in &summary.shares {
+                    let images = qr::export(share, output).unwrap_or_exit();
+
+                    if !e.quiet {
+                        for image in &images {
+                            println!("Wrote QR code {}", image.to_string_lossy());
+                        }
+                    }
+
+                    outputs.extend(images);
+                }
+            }
+
+            report::ok(vec![
+                ("input", Json::path(&e.file)),
+                ("bytes", Json::Number(summary.length)),
+                ("outputs", Json::outputs(&outputs)),
+            ]);
+        }
+        Subcommand::Decrypt {
+            cfg: d,
+            range: Some(range),
+        } => {
+            if report::is_json() && d.output.is_none() {
+                exit_with_error(
+                    "--range needs --output with JSON output, stdout is used for the result",
+                    ExitCode::Usage,
+                );
+            }
+
+            d.validate().unwrap_or_exit();
+
+            let [share1, share2] = d.share_paths().unwrap_or_exit();
+            let len = range.end - range.start;
+            let cancel = cancel_on_ctrl_c();
+
+            let result = match &d.output {
+                Some(output) => decrypt_range_to_file(&[share1, share2], &range, output, &cancel),
+                None => {
+                    let mut stdout = Cancellable {
+                        inner: std::io::stdout().lock(),
+                        cancel: &cancel,
+                    };
+                    onetime_cli::decrypt_range(&share1, &share2, range.start, len, &mut stdout)
+                }
+            };
+            let written = result
+                .map_err(|e| match cancel.load(Ordering::Relaxed) {
+                    true => Error::Cancelled,
+                    false => e,
+                })
+                .unwrap_or_exit();
+
+            if let (Some(output), false) = (&d.output, d.quiet) {
+                println!(
+                    "Decrypted bytes {}..{} to {}",
+                    range.start,
+                    range.start + written,
+                    output.to_string_lossy()
+                );
+            }
+
+            if let Some(output) = &d.output {
+                report::ok(vec![
+                    ("offset", Json::Number(range.start)),
+                    ("bytes", Json::Number(written)),
+                    ("outputs", Json::outputs(&[output])),
+                ]);
+            }
         }
-        Subcommand::Decrypt(d) => {
-            onetime_cli::decrypt_file(&d).unwrap_or_exit(1);
+        Subcommand::Decrypt {
+            cfg: mut d,
+            range: None,
+        } => {
+            d.cancel = Some(cancel_on_ctrl_c());
+            let summary = onetime_cli::decrypt_file(&d).unwrap_or_exit();
 
             if !d.quiet {
-                println!("Successfully decrypted {}", d.file.to_string_lossy());
+                if summary.repaired_blocks > 0 {
+                    println!("Repaired {} damaged block(s)", summary.repaired_blocks);
+                }
+
+                println!(
+                    "Successfully decrypted {}",
+                    summary.output.to_string_lossy()
+                );
+            }
+
+            for range in &summary.damaged {
+                warn(&format!(
+                    "bytes {}..{} of {} are damaged",
+                    range.start,
+                    range.end,
+                    summary.output.to_string_lossy()
+                ));
+            }
+
+            report::ok(vec![
+                ("bytes", Json::Number(summary.length)),
+                ("outputs", Json::outputs(&[&summary.output])),
+                ("repaired_blocks", Json::Number(summary.repaired_blocks)),
+                ("damaged", Json::ranges(&summary.damaged)),
+            ]);
+        }
+        Subcommand::Verify { mut cfg, locate } => {
+            cfg.cancel = Some(cancel_on_ctrl_c());
+            let summary = onetime_cli::verify_file(&cfg).unwrap_or_exit();
+
+            if !cfg.quiet {
+                print_verify_summary(&cfg, &summary, locate);
+            }
+
+            let damaged = |ranges: &Option<Vec<Range<u64>>>| match ranges {
+                Some(ranges) => Json::ranges(ranges),
+                None => Json::Null,
+            };
+            let fields = vec![
+                ("bytes", Json::Number(summary.length)),
+                ("damaged1", damaged(&summary.damaged1)),
+                ("damaged2", damaged(&summary.damaged2)),
+                ("repaired_blocks", Json::Number(summary.repaired_blocks)),
+                (
+                    "digest_matches",
+                    summary.digest_matches.map_or(Json::Null, Json::Bool),
+                ),
+            ];
+
+            if !summary.is_ok() {
+                report::error("Verification failed", ExitCode::Integrity, fields);
+                exit_with_error("Verification failed", ExitCode::Integrity);
+            }
+
+            report::ok(fields);
+        }
+        Subcommand::ExportPaper { share, quiet } => {
+            let text = onetime_cli::export_paper(&share).unwrap_or_exit();
+
+            let txt = with_suffix(&share, ".paper.txt");
+            write_file(&txt, text.as_bytes()).unwrap_or_exit();
+
+            let pdf = with_suffix(&share, ".paper.pdf");
+            pdf::write_pdf(&pdf, &text).unwrap_or_exit();
+
+            if !quiet {
+                println!("Wrote paper backup {}", txt.to_string_lossy());
+                println!("Wrote paper backup {}", pdf.to_string_lossy());
             }
+
+            report::ok(vec![("outputs", Json::outputs(&[txt, pdf]))]);
+        }
+        Subcommand::ImportPaper {
+            input,
+            output,
+            quiet,
+        } => {
+            let text = std::fs::read_to_string(&input)
+                .map_err(|error| Error::IoError(IoError::new(&input, IoOperation::Read, error)))
+                .unwrap_or_exit();
+
+            let share = onetime_cli::import_paper(&text).unwrap_or_exit();
+            write_file(&output, &share).unwrap_or_exit();
+
+            if !quiet {
+                println!("Successfully restored {}", output.to_string_lossy());
+            }
+
+            report::ok(vec![
+                ("bytes", Json::Number(share.len() as u64)),
+                ("outputs", Json::outputs(&[output])),
+            ]);
         }
         Subcommand::None => {
             let mut cmd = clap_app::build_clap_app();
-            println!("{}", cmd.render_help());
+            eprintln!("{}", cmd.render_help());
+            std::process::exit(ExitCode::Usage as i32);
         }
     }
 }
+
+fn print_verify_summary(cfg: &Config, summary: &VerifySummary, locate: bool) {
+    let [share1, share2] = cfg.share_paths().unwrap_or_exit();
+    let shares = [(share1, &summary.damaged1), (share2, &summary.damaged2)];
+
+    for (share, damaged) in shares {
+        let name = share.to_string_lossy();
+
+        match damaged.as_deref() {
+            None => println!("{name}: no hash tree, cannot be checked"),
+            Some([]) => println!("{name}: OK"),
+            Some(ranges) => {
+                println!("{name}: damaged");
+
+                if locate {
+                    for Range { start, end } in ranges {
+                        println!("  bytes {start}..{end} of the encrypted data");
+                    }
+                }
+            }
+        }
+    }
+
+    if summary.repaired_blocks > 0 {
+        println!(
+            "{} damaged block(s) can be repaired by error correction",
+            summary.repaired_blocks
+        );
+    }
+
+    match summary.digest_matches {
+        Some(true) => println!("Decrypted data: OK"),
+        Some(false) => println!("Decrypted data: does not match the original"),
+        None => (),
+    }
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = OsString::from(path);
+    name.push(suffix);
+
+    PathBuf::from(name)
+}
+
+/// Returns a flag that is set on Ctrl-C, to cancel the running operation
+/// and remove its partial output files. A second Ctrl-C exits immediately.
+fn cancel_on_ctrl_c() -> Arc<AtomicBool> {
+    let cancel = Arc::new(AtomicBool::new(false));
+    let flag = Arc::clone(&cancel);
+
+    let result = ctrlc::set_handler(move || {
+        if flag.swap(true, Ordering::Relaxed) {
+            std::process::exit(ExitCode::Cancelled as i32);
+        }
+    });
+
+    if let Err(e) = result {
+        warn(&format!(
+            "Ctrl-C can't be handled, it won't remove partial output files: {e}"
+        ));
+    }
+
+    cancel
+}
+
+/// Writer that fails once `cancel` is set, which stops
+/// [`onetime_cli::decrypt_range`]
+struct Cancellable<'a, W> {
+    inner: W,
+    cancel: &'a AtomicBool,
+}
+
+impl<W: Write> Write for Cancellable<'_, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.cancel.load(Ordering::Relaxed) {
+            return Err(io::Error::other("cancelled"));
+        }
+
+        self.inner.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Decrypts `range` of the original file into `output`, which is removed
+/// again if that fails or is cancelled.
+fn decrypt_range_to_file(
+    shares: &[PathBuf; 2],
+    range: &Range<u64>,
+    output: &Path,
+    cancel: &AtomicBool,
+) -> Result<u64, Error> {
+    if shares.iter().any(|share| same_file(share, output)) {
+        return Err(Error::OutputExists(output.to_owned()));
+    }
+
+    let mut file = Cancellable {
+        inner: File::create(output)
+            .map_err(|error| Error::IoError(IoError::new(output, IoOperation::Open, error)))?,
+        cancel,
+    };
+    let result = onetime_cli::decrypt_range(
+        &shares[0],
+        &shares[1],
+        range.start,
+        range.end - range.start,
+        &mut file,
+    );
+
+    if result.is_err() {
+        drop(file);
+        let _ = std::fs::remove_file(output);
+    }
+
+    result
+}
+
+/// Returns whether both paths point to the same existing file.
+fn same_file(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+fn write_file(path: &Path, data: &[u8]) -> Result<(), Error> {
+    std::fs::write(path, data)
+        .map_err(|error| Error::IoError(IoError::new(path, IoOperation::Write, error)))
+}
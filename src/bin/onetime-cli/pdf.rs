@@ -0,0 +1,140 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use onetime_cli::{Error, IoError, IoOperation};
+
+/// A4 in points
+const PAGE_WIDTH: usize = 595;
+const PAGE_HEIGHT: usize = 842;
+
+const MARGIN: usize = 50;
+const FONT_SIZE: usize = 10;
+const LEADING: usize = 12;
+const LINES_PER_PAGE: usize = (PAGE_HEIGHT - 2 * MARGIN) / LEADING;
+
+/// Escapes a line for a PDF string literal. Characters outside of ASCII
+/// are replaced, since the standard fonts don't cover them.
+fn escape(line: &str) -> String {
+    line.chars()
+        .map(|c| match c {
+            '\\' | '(' | ')' => format!("\\{c}"),
+            c if c.is_ascii() && !c.is_ascii_control() => c.to_string(),
+            _ => "?".to_string(),
+        })
+        .collect()
+}
+
+/// Builds a PDF document showing `text` in a monospaced font, starting a
+/// new page whenever a page is full.
+pub fn render(text: &str) -> Vec<u8> {
+    let lines: Vec<&str> = text.lines().collect();
+    let pages: Vec<&[&str]> = match lines.is_empty() {
+        true => vec![&[]],
+        false => lines.chunks(LINES_PER_PAGE).collect(),
+    };
+
+    // Objects 1 and 2 are the catalog and the page tree, 3 is the font,
+    // followed by a page and its content for every page
+    let page_ids: Vec<usize> = (0..pages.len()).map(|i| 4 + 2 * i).collect();
+
+    let mut objects = vec![
+        "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+        format!(
+            "<< /Type /Pages /Kids [{}] /Count {} >>",
+            page_ids
+                .iter()
+                .map(|id| format!("{id} 0 R"))
+                .collect::<Vec<String>>()
+                .join(" "),
+            pages.len()
+        ),
+        "<< /Type /Font /Subtype /Type1 /BaseFont /Courier >>".to_string(),
+    ];
+
+    for (page, id) in pages.iter().zip(&page_ids) {
+        let mut content = format!(
+            "BT\n/F1 {FONT_SIZE} Tf\n{LEADING} TL\n{MARGIN} {} Td\n",
+            PAGE_HEIGHT - MARGIN - FONT_SIZE
+        );
+        for line in page.iter() {
+            content.push_str(&format!("({}) Tj T*\n", escape(line)));
+        }
+        content.push_str("ET");
+
+        objects.push(format!(
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {PAGE_WIDTH} {PAGE_HEIGHT}] \
+            /Resources << /Font << /F1 3 0 R >> >> /Contents {} 0 R >>",
+            id + 1
+        ));
+        objects.push(format!(
+            "<< /Length {} >>\nstream\n{content}\nendstream",
+            content.len()
+        ));
+    }
+
+    let mut pdf = b"%PDF-1.4\n".to_vec();
+    let mut offsets = Vec::with_capacity(objects.len());
+
+    for (i, object) in objects.iter().enumerate() {
+        offsets.push(pdf.len());
+        pdf.extend_from_slice(format!("{} 0 obj\n{object}\nendobj\n", i + 1).as_bytes());
+    }
+
+    let xref = pdf.len();
+    pdf.extend_from_slice(
+        format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1).as_bytes(),
+    );
+    for offset in offsets {
+        pdf.extend_from_slice(format!("{offset:010} 00000 n \n").as_bytes());
+    }
+    pdf.extend_from_slice(
+        format!(
+            "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{xref}\n%%EOF\n",
+            objects.len() + 1
+        )
+        .as_bytes(),
+    );
+
+    pdf
+}
+
+pub fn write_pdf(path: &Path, text: &str) -> Result<(), Error> {
+    let io_err = |error: io::Error| Error::IoError(IoError::new(path, IoOperation::Write, error));
+
+    let mut file = BufWriter::new(File::create(path).map_err(io_err)?);
+
+    file.write_all(&render(text))
+        .and_then(|_| file.flush())
+        .map_err(io_err)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(escape("a (b) \\ ä"), "a \\(b\\) \\\\ ?");
+    }
+
+    #[test]
+    fn test_render() {
+        let text = (1..=100).map(|i| format!("line {i}\n")).collect::<String>();
+        let pdf = String::from_utf8(render(&text)).unwrap();
+
+        assert!(pdf.starts_with("%PDF-1.4\n"));
+        assert!(pdf.ends_with("%%EOF\n"));
+        assert!(pdf.contains("/Count 2"));
+        assert!(pdf.contains("(line 100) Tj"));
+
+        // Every entry of the cross-reference table points to its object
+        let xref = pdf.rsplit("startxref\n").next().unwrap();
+        let xref: usize = xref.lines().next().unwrap().parse().unwrap();
+
+        for (i, entry) in pdf[xref..].lines().skip(3).take(7).enumerate() {
+            let offset: usize = entry[..10].parse().unwrap();
+            assert!(pdf[offset..].starts_with(&format!("{} 0 obj", i + 1)));
+        }
+    }
+}
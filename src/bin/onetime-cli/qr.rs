@@ -0,0 +1,130 @@
+use std::fs::File;
+use std::io::{self, BufWriter};
+use std::path::{Path, PathBuf};
+
+use qrcode::{Color, QrCode};
+
+use onetime_cli::{Error, IoError, IoOperation};
+
+/// Number of light modules around a code
+const QUIET_ZONE: usize = 4;
+
+/// Size of a module in the PNG images, in pixels
+const PNG_SCALE: usize = 8;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum QrOutput {
+    Png,
+    Terminal,
+}
+
+/// Renders the QR codes of a share, either as PNG images next to it or
+/// on stdout. Returns the paths of the images.
+pub fn export(share: &Path, output: QrOutput) -> Result<Vec<PathBuf>, Error> {
+    let payloads = onetime_cli::qr_payloads(share)?;
+    let count = payloads.len();
+    let mut images = Vec::new();
+
+    for (i, payload) in payloads.iter().enumerate() {
+        let code = QrCode::new(payload).map_err(|e| {
+            Error::InvalidInput(format!("Can't create a QR code for {share:?}: {e}"))
+        })?;
+
+        match output {
+            QrOutput::Png => {
+                let mut name = share.as_os_str().to_owned();
+                name.push(format!(".qr-{}.png", i + 1));
+
+                let path = PathBuf::from(name);
+                write_png(&code, &path)?;
+                images.push(path);
+            }
+            QrOutput::Terminal => {
+                println!("{}, code {} of {count}:", share.to_string_lossy(), i + 1);
+                print!("{}", render_terminal(&code));
+            }
+        }
+    }
+
+    Ok(images)
+}
+
+/// Module at the given position, including the quiet zone
+fn is_dark(code: &QrCode, colors: &[Color], x: usize, y: usize) -> bool {
+    let width = code.width();
+
+    match (x.checked_sub(QUIET_ZONE), y.checked_sub(QUIET_ZONE)) {
+        (Some(x), Some(y)) if x < width && y < width => colors[y * width + x] == Color::Dark,
+        _ => false,
+    }
+}
+
+/// Renders a code with half blocks, two rows of modules per line. Light
+/// modules are drawn as blocks, which suits terminals with a dark background.
+fn render_terminal(code: &QrCode) -> String {
+    let colors = code.to_colors();
+    let size = code.width() + 2 * QUIET_ZONE;
+    let mut out = String::new();
+
+    for y in (0..size).step_by(2) {
+        for x in 0..size {
+            let top = !is_dark(code, &colors, x, y);
+            let bottom = y + 1 < size && !is_dark(code, &colors, x, y + 1);
+
+            out.push(match (top, bottom) {
+                (true, true) => '█',
+                (true, false) => '▀',
+                (false, true) => '▄',
+                (false, false) => ' ',
+            });
+        }
+
+        out.push('\n');
+    }
+
+    out
+}
+
+fn write_png(code: &QrCode, path: &Path) -> Result<(), Error> {
+    let io_err = |error: io::Error| Error::IoError(IoError::new(path, IoOperation::Write, error));
+
+    let colors = code.to_colors();
+    let size = (code.width() + 2 * QUIET_ZONE) * PNG_SCALE;
+
+    let mut pixels = Vec::with_capacity(size * size);
+    for y in 0..size {
+        for x in 0..size {
+            let dark = is_dark(code, &colors, x / PNG_SCALE, y / PNG_SCALE);
+            pixels.push(if dark { 0 } else { 255 });
+        }
+    }
+
+    let file = File::create(path).map_err(io_err)?;
+
+    let mut encoder = png::Encoder::new(BufWriter::new(file), size as u32, size as u32);
+    encoder.set_color(png::ColorType::Grayscale);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    encoder
+        .write_header()
+        .and_then(|mut writer| writer.write_image_data(&pixels))
+        .map_err(|e| io_err(io::Error::other(e)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_render_terminal() {
+        let code = QrCode::new("OTPQR:1/1:00000000:").unwrap();
+        let art = render_terminal(&code);
+        let size = code.width() + 2 * QUIET_ZONE;
+
+        assert_eq!(art.lines().count(), size.div_ceil(2));
+        assert!(art.lines().all(|l| l.chars().count() == size));
+
+        // The first line is part of the quiet zone
+        assert!(art.lines().next().unwrap().chars().all(|c| c == '█'));
+    }
+}
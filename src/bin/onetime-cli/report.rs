@@ -0,0 +1,240 @@
+//! Machine-readable results for `--message-format json`.
+//!
+//! Each operation prints exactly one JSON object on stdout, whether it
+//! succeeded or failed:
+//!
+//! ```text
+//! {"operation":"encrypt","status":"ok","duration_ms":12,"bytes":1024,"outputs":[...]}
+//! {"operation":"decrypt","status":"error","duration_ms":3,"error":{"category":"io","code":3,"message":"..."}}
+//! ```
+
+use std::fmt::{self, Display, Write as _};
+use std::fs::File;
+use std::io;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+use std::time::Instant;
+
+use sha2::{Digest, Sha256};
+
+use crate::error_handler::ExitCode;
+
+/// Format of the results printed on stdout
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputFormat {
+    Text,
+    Json,
+}
+
+struct State {
+    operation: String,
+    start: Instant,
+    printed: AtomicBool,
+}
+
+static STATE: OnceLock<State> = OnceLock::new();
+
+/// Switches to JSON output for the given operation. Its duration is
+/// measured from now on.
+pub fn init_json(operation: &str) {
+    let _ = STATE.set(State {
+        operation: operation.to_string(),
+        start: Instant::now(),
+        printed: AtomicBool::new(false),
+    });
+}
+
+/// Returns whether results are printed as JSON.
+pub fn is_json() -> bool {
+    STATE.get().is_some()
+}
+
+/// A JSON value
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(u64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(&'static str, Json)>),
+}
+
+impl Json {
+    pub fn path(path: &Path) -> Self {
+        Json::String(path.to_string_lossy().into_owned())
+    }
+
+    pub fn ranges(ranges: &[Range<u64>]) -> Self {
+        Json::Array(
+            ranges
+                .iter()
+                .map(|r| Json::Array(vec![Json::Number(r.start), Json::Number(r.end)]))
+                .collect(),
+        )
+    }
+
+    /// Lists the files at `paths` with their SHA-256 digests. A path that
+    /// doesn't exist is assumed to be split into volumes, which are listed
+    /// instead.
+    pub fn outputs<P: AsRef<Path>>(paths: &[P]) -> Self {
+        let mut outputs = Vec::new();
+
+        for path in paths {
+            for file in files_of(path.as_ref()) {
+                let sha256 = match sha256_file(&file) {
+                    Ok(digest) => Json::String(digest),
+                    Err(_) => Json::Null,
+                };
+
+                outputs.push(Json::Object(vec![
+                    ("path", Json::path(&file)),
+                    ("sha256", sha256),
+                ]));
+            }
+        }
+
+        Json::Array(outputs)
+    }
+}
+
+impl Display for Json {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Json::Null => f.write_str("null"),
+            Json::Bool(b) => write!(f, "{b}"),
+            Json::Number(n) => write!(f, "{n}"),
+            Json::String(s) => write_string(f, s),
+            Json::Array(values) => {
+                f.write_char('[')?;
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        f.write_char(',')?;
+                    }
+                    write!(f, "{value}")?;
+                }
+                f.write_char(']')
+            }
+            Json::Object(fields) => {
+                f.write_char('{')?;
+                for (i, (key, value)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        f.write_char(',')?;
+                    }
+                    write_string(f, key)?;
+                    write!(f, ":{value}")?;
+                }
+                f.write_char('}')
+            }
+        }
+    }
+}
+
+fn write_string(f: &mut fmt::Formatter<'_>, s: &str) -> fmt::Result {
+    f.write_char('"')?;
+
+    for c in s.chars() {
+        match c {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            '\r' => f.write_str("\\r")?,
+            '\t' => f.write_str("\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => f.write_char(c)?,
+        }
+    }
+
+    f.write_char('"')
+}
+
+/// Prints the result of a successful operation.
+pub fn ok(fields: Vec<(&'static str, Json)>) {
+    print(Json::String("ok".to_string()), fields);
+}
+
+/// Prints the result of a failed operation, with the category of the error.
+/// Does nothing if a result was already printed, so callers may add fields
+/// before exiting through `exit_with_error`.
+pub fn error(message: &str, code: ExitCode, mut fields: Vec<(&'static str, Json)>) {
+    fields.push((
+        "error",
+        Json::Object(vec![
+            ("category", Json::String(code.category().to_string())),
+            ("code", Json::Number(code as u64)),
+            ("message", Json::String(message.to_string())),
+        ]),
+    ));
+
+    print(Json::String("error".to_string()), fields);
+}
+
+fn print(status: Json, fields: Vec<(&'static str, Json)>) {
+    let Some(state) = STATE.get() else {
+        return;
+    };
+
+    if state.printed.swap(true, Ordering::Relaxed) {
+        return;
+    }
+
+    let mut object = vec![
+        ("operation", Json::String(state.operation.clone())),
+        ("status", status),
+        (
+            "duration_ms",
+            Json::Number(state.start.elapsed().as_millis() as u64),
+        ),
+    ];
+    object.extend(fields);
+
+    println!("{}", Json::Object(object));
+}
+
+/// Returns the file at `path`, or its volumes if it is split into volumes.
+fn files_of(path: &Path) -> Vec<PathBuf> {
+    if path.exists() {
+        return vec![path.to_owned()];
+    }
+
+    (1..)
+        .map(|number| {
+            let mut name = path.as_os_str().to_owned();
+            name.push(format!(".{number:03}"));
+            PathBuf::from(name)
+        })
+        .take_while(|volume| volume.exists())
+        .collect()
+}
+
+fn sha256_file(path: &Path) -> io::Result<String> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut File::open(path)?, &mut hasher)?;
+
+    let mut hex = String::new();
+    for byte in hasher.finalize() {
+        let _ = write!(hex, "{byte:02x}");
+    }
+
+    Ok(hex)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_json_display() {
+        let json = Json::Object(vec![
+            ("name", Json::String("a \"b\"\n\u{1}".to_string())),
+            ("list", Json::Array(vec![Json::Number(1), Json::Null])),
+            ("ok", Json::Bool(true)),
+        ]);
+
+        assert_eq!(
+            json.to_string(),
+            r#"{"name":"a \"b\"\n\u0001","list":[1,null],"ok":true}"#
+        );
+    }
+}
@@ -0,0 +1,107 @@
+//! Colours and text styles of the messages on stderr.
+//!
+//! All escape codes are written here, and only if colours are enabled, see
+//! [`init`].
+
+use std::ffi::OsString;
+use std::io::IsTerminal;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+pub use clap::ColorChoice;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Style {
+    /// Bold red, for the "Error" label
+    Error,
+
+    /// Bold yellow, for the "Warning" label
+    Warning,
+
+    /// Bold, for details of a message
+    Bold,
+}
+
+impl Style {
+    fn escape_code(self) -> &'static str {
+        match self {
+            Style::Error => "\x1b[1;91m",
+            Style::Warning => "\x1b[1;93m",
+            Style::Bold => "\x1b[01m",
+        }
+    }
+
+    /// Returns `text` in this style, or unchanged if colours are disabled.
+    pub fn paint(self, text: &str) -> String {
+        self.paint_if(ENABLED.load(Ordering::Relaxed), text)
+    }
+
+    fn paint_if(self, enabled: bool, text: &str) -> String {
+        match enabled {
+            true => format!("{}{text}\x1b[0m", self.escape_code()),
+            false => text.to_string(),
+        }
+    }
+}
+
+/// Enables or disables colours for the rest of the process.
+pub fn init(choice: ColorChoice) {
+    let enabled = is_enabled(
+        choice,
+        std::env::var_os("NO_COLOR"),
+        std::env::var_os("CLICOLOR_FORCE"),
+        std::io::stderr().is_terminal(),
+    );
+
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// `--color always` and `--color never` take precedence over the
+/// environment. Otherwise, a non-empty `CLICOLOR_FORCE` other than `0`
+/// enables colours and a non-empty `NO_COLOR` disables them, and colours
+/// are used if stderr is a terminal.
+fn is_enabled(
+    choice: ColorChoice,
+    no_color: Option<OsString>,
+    clicolor_force: Option<OsString>,
+    is_terminal: bool,
+) -> bool {
+    let is_set = |var: &Option<OsString>| var.as_ref().is_some_and(|v| !v.is_empty() && v != "0");
+
+    match choice {
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+        ColorChoice::Auto if is_set(&clicolor_force) => true,
+        ColorChoice::Auto if no_color.is_some_and(|v| !v.is_empty()) => false,
+        ColorChoice::Auto => is_terminal,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_paint() {
+        assert_eq!(
+            Style::Error.paint_if(true, "Error"),
+            "\x1b[1;91mError\x1b[0m"
+        );
+        assert_eq!(Style::Error.paint_if(false, "Error"), "Error");
+    }
+
+    #[test]
+    fn test_is_enabled() {
+        let var = |v: &str| Some(OsString::from(v));
+
+        assert!(is_enabled(ColorChoice::Auto, None, None, true));
+        assert!(!is_enabled(ColorChoice::Auto, None, None, false));
+        assert!(!is_enabled(ColorChoice::Auto, var("1"), None, true));
+        assert!(is_enabled(ColorChoice::Auto, var(""), None, true));
+        assert!(is_enabled(ColorChoice::Auto, var("1"), var("1"), false));
+        assert!(!is_enabled(ColorChoice::Auto, None, var("0"), false));
+        assert!(is_enabled(ColorChoice::Always, var("1"), None, false));
+        assert!(!is_enabled(ColorChoice::Never, None, var("1"), true));
+    }
+}
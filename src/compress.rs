@@ -0,0 +1,144 @@
+//! Compression of the data before it is encrypted.
+//!
+//! Every byte of data costs one random byte per share, so compressing the
+//! data first makes the shares of compressible files smaller.
+//!
+//! The codecs need the cargo feature `compress`. Without it, [`Compression`]
+//! has no variants, so nothing is ever compressed, and shares with
+//! compressed data are rejected as using an unknown codec.
+
+use std::io;
+#[cfg(feature = "compress")]
+use std::io::Write;
+
+#[cfg(feature = "compress")]
+use flate2::write::{DeflateDecoder, DeflateEncoder};
+
+/// Compression codecs. See [`Config::compression`](crate::Config::compression).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Compression {
+    /// Deflate ([RFC 1951](https://www.rfc-editor.org/rfc/rfc1951))
+    #[cfg(feature = "compress")]
+    Deflate,
+}
+
+impl Compression {
+    pub(crate) fn to_byte(self) -> u8 {
+        match self {
+            #[cfg(feature = "compress")]
+            Compression::Deflate => 1,
+        }
+    }
+
+    pub(crate) fn from_byte(byte: u8) -> Option<Self> {
+        match byte {
+            #[cfg(feature = "compress")]
+            1 => Some(Compression::Deflate),
+            _ => None,
+        }
+    }
+}
+
+/// Compresses data passed to it in pieces.
+pub enum Compressor {
+    #[cfg(feature = "compress")]
+    Deflate(DeflateEncoder<Vec<u8>>),
+}
+
+impl Compressor {
+    pub fn new(compression: Compression) -> Self {
+        match compression {
+            #[cfg(feature = "compress")]
+            Compression::Deflate => Self::Deflate(DeflateEncoder::new(
+                Vec::new(),
+                flate2::Compression::default(),
+            )),
+        }
+    }
+
+    /// Returns the compressed data that is available so far.
+    #[cfg_attr(not(feature = "compress"), allow(unused_variables))]
+    pub fn compress(&mut self, data: &[u8]) -> io::Result<Vec<u8>> {
+        match *self {
+            #[cfg(feature = "compress")]
+            Self::Deflate(ref mut encoder) => {
+                encoder.write_all(data)?;
+
+                Ok(std::mem::take(encoder.get_mut()))
+            }
+        }
+    }
+
+    /// Returns the rest of the compressed data.
+    pub fn finish(self) -> io::Result<Vec<u8>> {
+        match self {
+            #[cfg(feature = "compress")]
+            Self::Deflate(encoder) => encoder.finish(),
+        }
+    }
+}
+
+/// Decompresses data passed to it in pieces.
+pub enum Decompressor {
+    #[cfg(feature = "compress")]
+    Deflate(DeflateDecoder<Vec<u8>>),
+}
+
+impl Decompressor {
+    pub fn new(compression: Compression) -> Self {
+        match compression {
+            #[cfg(feature = "compress")]
+            Compression::Deflate => Self::Deflate(DeflateDecoder::new(Vec::new())),
+        }
+    }
+
+    /// Returns the decompressed data that is available so far.
+    #[cfg_attr(not(feature = "compress"), allow(unused_variables))]
+    pub fn decompress(&mut self, data: &[u8]) -> io::Result<Vec<u8>> {
+        match *self {
+            #[cfg(feature = "compress")]
+            Self::Deflate(ref mut decoder) => {
+                decoder.write_all(data)?;
+
+                Ok(std::mem::take(decoder.get_mut()))
+            }
+        }
+    }
+
+    /// Returns the rest of the decompressed data.
+    pub fn finish(self) -> io::Result<Vec<u8>> {
+        match self {
+            #[cfg(feature = "compress")]
+            Self::Deflate(decoder) => decoder.finish(),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "compress"))]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_roundtrip() {
+        let data = b"log line\n".repeat(1000);
+
+        let mut compressor = Compressor::new(Compression::Deflate);
+        let mut compressed = Vec::new();
+        for piece in data.chunks(100) {
+            compressed.extend(compressor.compress(piece).unwrap());
+        }
+        compressed.extend(compressor.finish().unwrap());
+
+        assert!(compressed.len() < data.len() / 10);
+
+        let mut decompressor = Decompressor::new(Compression::Deflate);
+        let mut decompressed = Vec::new();
+        for piece in compressed.chunks(7) {
+            decompressed.extend(decompressor.decompress(piece).unwrap());
+        }
+        decompressed.extend(decompressor.finish().unwrap());
+
+        assert_eq!(decompressed, data);
+    }
+}
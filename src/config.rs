@@ -1,18 +1,56 @@
-use std::path::PathBuf;
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use crate::fs::{extend_extension, same_device};
+use crate::template::{NameTemplate, Values};
+use crate::volume::VOLUME_HEADER_LEN;
+use crate::{Compression, Error, Padding};
+
+/// Largest supported buffer size, 256 MiB. Decrypting allocates three
+/// buffers of this size.
+pub const MAX_BUFFER: u32 = 256 * 1024 * 1024;
 
 /// Configuration passed to [`encrypt_file`](crate::encrypt_file) or [`decrypt_file`](crate::decrypt_file)
 #[derive(Clone)]
 pub struct Config {
-    /// File to be encrypted
+    /// File to be encrypted or decrypted. The names of the encrypted
+    /// files are derived from it, unless [`shares`](Config::shares) is set.
     pub file: PathBuf,
 
+    /// Paths of the two encrypted files. If set, `file`, the suffixes and
+    /// the directories are not used to find the encrypted files.
+    pub shares: Option<[PathBuf; 2]>,
+
+    /// Directory of the first encrypted file. Defaults to the directory of `file`.
+    pub dir1: Option<PathBuf>,
+
+    /// Directory of the second encrypted file. Defaults to the directory of `file`.
+    pub dir2: Option<PathBuf>,
+
+    /// Path of the file written by [`decrypt_file`](crate::decrypt_file).
+    /// Defaults to `file`, or, if [`shares`](Config::shares) is set, to the
+    /// original name of the file stored in the encrypted files.
+    pub output: Option<PathBuf>,
+
     /// Suffix for the name of the first input/output file
     pub suffix1: String,
 
     /// Suffix for the name of the second input/output file
     pub suffix2: String,
 
-    /// Buffer size in bytes
+    /// Template for the names of the encrypted files, used instead of the
+    /// suffixes. Supports the placeholders `{dir}` (the directory of `file`,
+    /// or `dir1`/`dir2` if set), `{name}`, `{stem}`, `{ext}`, `{index}`,
+    /// `{set}` (the random id of the pair of files) and `{date}` (the date
+    /// of encryption, `YYYY-MM-DD`). When decrypting, `{set}` and `{date}`
+    /// match any value.
+    pub name_template: Option<String>,
+
+    /// Buffer size in bytes. When encrypting, this is also the size of the
+    /// chunks that are hashed to detect damaged data later on.
     pub buffer: u32,
 
     /// Delete input file after encryption
@@ -20,6 +58,172 @@ pub struct Config {
 
     /// Don't print anything to stdout
     pub quiet: bool,
+
+    /// Add error correction data to the output files of
+    /// [`encrypt_file`](crate::encrypt_file). Ignored by
+    /// [`decrypt_file`](crate::decrypt_file), which reads the
+    /// parameters from its input files. Needs the cargo feature `fec`.
+    pub fec: Option<FecParams>,
+
+    /// Compress the data before encrypting it, which makes the output files
+    /// of [`encrypt_file`](crate::encrypt_file) smaller for compressible
+    /// data. Ignored by [`decrypt_file`](crate::decrypt_file), which reads
+    /// the codec from its input files. Needs the cargo feature `compress`.
+    pub compression: Option<Compression>,
+
+    /// Pad the data before encrypting it, so the length of the output files
+    /// of [`encrypt_file`](crate::encrypt_file) doesn't reveal the length of
+    /// the file. Ignored by [`decrypt_file`](crate::decrypt_file), which
+    /// reads the true length from its input files.
+    pub padding: Option<Padding>,
+
+    /// Split the output files of [`encrypt_file`](crate::encrypt_file) into
+    /// volumes of at most this many bytes, named `<name>.001`, `<name>.002`
+    /// and so on. Volumes are found automatically when decrypting.
+    pub volume_size: Option<u64>,
+
+    /// Record the progress of [`encrypt_file`](crate::encrypt_file) and
+    /// [`decrypt_file`](crate::decrypt_file) in a journal next to the first
+    /// output file, and continue an interrupted run that recorded one.
+    /// Only supported for binary files without error correction,
+    /// compression or volumes.
+    pub resume: bool,
+
+    /// What [`decrypt_file`](crate::decrypt_file) does with chunks of the
+    /// input files that don't match their hash trees
+    pub on_damage: OnDamage,
+
+    /// Store permissions and timestamps of the file when encrypting and
+    /// restore them when decrypting
+    pub preserve: bool,
+
+    /// Also store the owner of the file when encrypting. Restoring the
+    /// owner usually requires root privileges and is skipped otherwise.
+    pub preserve_owner: bool,
+
+    /// Also store the extended attributes of the file when encrypting.
+    /// Needs the cargo feature `xattr`.
+    pub preserve_xattrs: bool,
+
+    /// Format of the files written by [`encrypt_file`](crate::encrypt_file).
+    /// [`decrypt_file`](crate::decrypt_file) detects the format of its
+    /// input files regardless of this setting.
+    pub format: ShareFormat,
+
+    /// Flag that cancels [`encrypt_file`](crate::encrypt_file),
+    /// [`decrypt_file`](crate::decrypt_file) or
+    /// [`verify_file`](crate::verify_file) once it is set, e.g. by a Ctrl-C
+    /// handler. They then remove their partial output files, unless
+    /// [`resume`](Config::resume) is set, and return [`Error::Cancelled`].
+    pub cancel: Option<Arc<AtomicBool>>,
+}
+
+/// Ways to deal with damaged input data. See [`Config::on_damage`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OnDamage {
+    /// Stop decrypting and return an error
+    #[default]
+    Fail,
+
+    /// Leave the damaged chunks out of the decrypted file
+    Skip,
+
+    /// Fill the damaged chunks of the decrypted file with zeros
+    Zero,
+}
+
+/// Formats of the encrypted files. See [`Config::format`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ShareFormat {
+    /// Binary files with header and trailer
+    #[default]
+    Binary,
+
+    /// Binary files encoded as ASCII armor: base64 between BEGIN and END
+    /// lines. Needs the cargo feature `armor`.
+    #[cfg(feature = "armor")]
+    Armor,
+
+    /// The encrypted data encoded as words of a fixed list of 2048 words,
+    /// for writing it down by hand. Holds at most 1024 bytes. No metadata
+    /// is stored, so the decrypted data can't be checked against a digest
+    /// and the file name and attributes are not restored. Can't be
+    /// combined with error correction.
+    Words,
+}
+
+/// Largest supported block size of the error correction in bytes
+const MAX_FEC_BLOCK_SIZE: u32 = 1 << 24;
+
+/// Largest number of data and parity blocks per group of the error
+/// correction, the size of the field of the Reed-Solomon code
+const MAX_FEC_BLOCKS: usize = 256;
+
+/// Parameters of the error correction applied to shares. See
+/// [`Config::fec`](crate::Config::fec).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FecParams {
+    /// Number of data blocks per group
+    pub data_blocks: u8,
+
+    /// Number of parity blocks per group. Up to this many damaged blocks
+    /// per group can be repaired.
+    pub parity_blocks: u8,
+
+    /// Size of a block in bytes
+    pub block_size: u32,
+}
+
+impl FecParams {
+    /// Constructs `FecParams` with the given number of parity blocks per
+    /// 32 data blocks of 4096 bytes each.
+    pub fn new(parity_blocks: u8) -> Self {
+        Self {
+            data_blocks: 32,
+            parity_blocks,
+            block_size: 4096,
+        }
+    }
+
+    pub(crate) fn check(&self) -> Result<(), String> {
+        if self.block_size == 0 || self.block_size > MAX_FEC_BLOCK_SIZE {
+            return Err(format!(
+                "block size must be between 1 and {MAX_FEC_BLOCK_SIZE} bytes"
+            ));
+        }
+
+        let blocks = self.data_blocks as usize + self.parity_blocks as usize;
+        if self.data_blocks == 0 || self.parity_blocks == 0 || blocks > MAX_FEC_BLOCKS {
+            return Err(format!(
+                "invalid number of data/parity blocks ({} and {}, at most {MAX_FEC_BLOCKS} in total)",
+                self.data_blocks, self.parity_blocks
+            ));
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn to_bytes(self) -> [u8; 6] {
+        let mut buf = [0u8; 6];
+        buf[0] = self.data_blocks;
+        buf[1] = self.parity_blocks;
+        buf[2..].copy_from_slice(&self.block_size.to_le_bytes());
+
+        buf
+    }
+
+    pub(crate) fn from_bytes(buf: &[u8]) -> Option<Self> {
+        if buf.len() != 6 {
+            return None;
+        }
+
+        Some(Self {
+            data_blocks: buf[0],
+            parity_blocks: buf[1],
+            block_size: u32::from_le_bytes(buf[2..].try_into().unwrap()),
+        })
+    }
 }
 
 impl Config {
@@ -44,14 +248,726 @@ impl Config {
         }
     }
 
+    /// Constructs a new `Config` for decrypting the given encrypted files.
+    /// Other fields are filled with default values.
+    pub fn new_with_shares(share1: &str, share2: &str) -> Self {
+        Self {
+            shares: Some([PathBuf::from(share1), PathBuf::from(share2)]),
+            ..Config::default()
+        }
+    }
+
+    /// Returns the paths of the two existing encrypted files.
+    ///
+    /// If the [name template](Config::name_template) contains `{set}` or
+    /// `{date}`, their directories are searched for matching files.
+    pub fn share_paths(&self) -> Result<[PathBuf; 2], Error> {
+        if let Some(shares) = &self.shares {
+            return Ok(shares.clone());
+        }
+
+        match &self.name_template {
+            Some(template) => {
+                let template = NameTemplate::parse(template)?;
+
+                Ok([
+                    template.find(&self.template_values(0, None, None))?,
+                    template.find(&self.template_values(1, None, None))?,
+                ])
+            }
+            None => Ok(self.suffixed_share_paths()),
+        }
+    }
+
+    /// Like [`share_paths`](Config::share_paths), but returns `Ok(None)` if
+    /// the name template matches no file for the first encrypted file.
+    pub(crate) fn existing_share_paths(&self) -> Result<Option<[PathBuf; 2]>, Error> {
+        let (None, Some(template)) = (&self.shares, &self.name_template) else {
+            return self.share_paths().map(Some);
+        };
+
+        let template = NameTemplate::parse(template)?;
+
+        match template.find_existing(&self.template_values(0, None, None))? {
+            Some(path1) => Ok(Some([
+                path1,
+                template.find(&self.template_values(1, None, None))?,
+            ])),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns the paths of the two encrypted files to be created.
+    pub(crate) fn new_share_paths(
+        &self,
+        set_id: &[u8; 16],
+        date: SystemTime,
+    ) -> Result<[PathBuf; 2], Error> {
+        let paths = match &self.name_template {
+            Some(template) => {
+                let template = NameTemplate::parse(template)?;
+                let path =
+                    |index| template.expand(&self.template_values(index, Some(set_id), Some(date)));
+
+                [path(0), path(1)]
+            }
+            None => self.suffixed_share_paths(),
+        };
+
+        if paths[0] == paths[1] {
+            return Err(Error::InvalidInput(format!(
+                "Both output files would be named {:?}",
+                paths[0]
+            )));
+        }
+
+        Ok(paths)
+    }
+
+    fn template_values<'a>(
+        &'a self,
+        index: u8,
+        set_id: Option<&'a [u8; 16]>,
+        date: Option<SystemTime>,
+    ) -> Values<'a> {
+        let dir = match index {
+            0 => self.dir1.as_deref(),
+            _ => self.dir2.as_deref(),
+        };
+
+        Values {
+            file: &self.file,
+            dir,
+            index,
+            set_id,
+            date,
+        }
+    }
+
+    fn suffixed_share_paths(&self) -> [PathBuf; 2] {
+        let path = |dir: &Option<PathBuf>, suffix: &str| {
+            let path = extend_extension(&self.file, suffix);
+
+            match (dir, path.file_name()) {
+                (Some(dir), Some(name)) => dir.join(name),
+                _ => path,
+            }
+        };
+
+        [
+            path(&self.dir1, &self.suffix1),
+            path(&self.dir2, &self.suffix2),
+        ]
+    }
+
+    /// Returns whether the directories of the two encrypted files are on
+    /// the same filesystem device, or `None` if that can't be determined.
+    pub fn shares_on_same_device(&self) -> Option<bool> {
+        let [share1, share2] = self.new_share_paths(&[0; 16], SystemTime::now()).ok()?;
+
+        same_device(&parent_dir(&share1), &parent_dir(&share2))
+    }
+
+    /// Returns whether the flag in [`cancel`](Config::cancel) is set.
+    pub(crate) fn is_cancelled(&self) -> bool {
+        self.cancel
+            .as_ref()
+            .is_some_and(|cancel| cancel.load(Ordering::Relaxed))
+    }
+
+    /// Checks the configuration for values that can't work.
+    ///
+    /// [`encrypt_file`](crate::encrypt_file), [`decrypt_file`](crate::decrypt_file)
+    /// and [`verify_file`](crate::verify_file) call this first.
+    ///
+    /// ## Error
+    /// Will return an [`Error::InvalidConfig`] if:
+    ///   * a suffix is empty or starts with a dot.
+    ///   * both suffixes are the same.
+    ///   * the buffer size is 0 or larger than [`MAX_BUFFER`].
+    ///   * the block size of [`Padding::Block`] is 0.
+    ///   * the volume size is not larger than the header of a volume.
+    ///   * the error correction parameters are out of range.
+    ///   * the word list format is combined with error correction,
+    ///     compression, padding, volumes or resuming.
+    ///   * resuming is combined with another format than
+    ///     [`ShareFormat::Binary`], error correction, compression or volumes.
+    ///   * error correction or extended attributes are requested, but the
+    ///     cargo feature `fec` or `xattr` is turned off.
+    pub fn validate(&self) -> Result<(), Error> {
+        check_suffix(&self.suffix1)?;
+        check_suffix(&self.suffix2)?;
+
+        if self.suffix1 == self.suffix2 {
+            return Err(Error::InvalidConfig(ConfigError::IdenticalSuffixes));
+        }
+
+        check_buffer(self.buffer)?;
+
+        if let Some(padding) = self.padding {
+            check_padding(padding)?;
+        }
+
+        if let Some(volume_size) = self.volume_size {
+            check_volume_size(volume_size)?;
+        }
+
+        if let Some(fec) = self.fec {
+            check_fec(fec)?;
+        }
+
+        #[cfg(not(feature = "fec"))]
+        if self.fec.is_some() {
+            return Err(Error::InvalidConfig(ConfigError::FeatureDisabled("fec")));
+        }
+
+        #[cfg(not(feature = "xattr"))]
+        if self.preserve_xattrs {
+            return Err(Error::InvalidConfig(ConfigError::FeatureDisabled("xattr")));
+        }
+
+        if self.format == ShareFormat::Words
+            && (self.fec.is_some()
+                || self.compression.is_some()
+                || self.padding.is_some()
+                || self.volume_size.is_some()
+                || self.resume)
+        {
+            return Err(Error::InvalidConfig(ConfigError::UnsupportedWithWords));
+        }
+
+        // The state of the encoders can't be restored
+        if self.resume
+            && (self.format != ShareFormat::Binary
+                || self.fec.is_some()
+                || self.compression.is_some()
+                || self.volume_size.is_some())
+        {
+            return Err(Error::InvalidConfig(ConfigError::UnsupportedWithResume));
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for Config {
     fn default() -> Self {
         Self {
             file: PathBuf::new(),
+            shares: None,
+            dir1: None,
+            dir2: None,
+            output: None,
             suffix1: "otp.0".to_string(),
             suffix2: "otp.1".to_string(),
+            name_template: None,
             buffer: 1048576,
             rm: false,
             quiet: true,
+            fec: None,
+            compression: None,
+            padding: None,
+            volume_size: None,
+            resume: false,
+            on_damage: OnDamage::Fail,
+            preserve: true,
+            preserve_owner: false,
+            preserve_xattrs: false,
+            format: ShareFormat::Binary,
+            cancel: None,
+        }
+    }
+}
+
+/// Ways in which a [`Config`] can be invalid. See [`Config::validate`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ConfigError {
+    /// A suffix is empty
+    EmptySuffix,
+
+    /// A suffix starts with a dot, which would give names like `file..otp.0`
+    SuffixStartsWithDot,
+
+    /// Both suffixes are the same, so both encrypted files would have the same name
+    IdenticalSuffixes,
+
+    /// The buffer size is 0
+    ZeroBuffer,
+
+    /// The buffer size is larger than [`MAX_BUFFER`]
+    BufferTooLarge,
+
+    /// The buffer size passed to `decrypt_async` is smaller than 8 bytes,
+    /// which it needs to recognize files written by
+    /// [`encrypt_file`](crate::encrypt_file)
+    BufferTooSmall,
+
+    /// The block size of [`Padding::Block`] is 0
+    ZeroPaddingBlock,
+
+    /// The volume size leaves no room for data after the header of a volume
+    VolumeTooSmall,
+
+    /// The error correction has no data or parity blocks, more than 256
+    /// blocks per group or a block size outside of 1 byte to 16 MiB
+    InvalidFec,
+
+    /// The word list format is combined with error correction, compression,
+    /// padding, volumes or resuming
+    UnsupportedWithWords,
+
+    /// Resuming is combined with a format other than binary, error
+    /// correction, compression or volumes
+    UnsupportedWithResume,
+
+    /// A setting needs the cargo feature with the given name, which is
+    /// turned off
+    FeatureDisabled(&'static str),
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigError::EmptySuffix => f.write_str("the suffixes must not be empty"),
+            ConfigError::SuffixStartsWithDot => {
+                f.write_str("the suffixes must not start with a dot")
+            }
+            ConfigError::IdenticalSuffixes => f.write_str("the two suffixes must be different"),
+            ConfigError::ZeroBuffer => f.write_str("the buffer size must not be 0"),
+            ConfigError::BufferTooSmall => {
+                f.write_str("the buffer size must be at least 8 bytes to decrypt streams")
+            }
+            ConfigError::BufferTooLarge => {
+                write!(
+                    f,
+                    "the buffer size must not be larger than {MAX_BUFFER} bytes"
+                )
+            }
+            ConfigError::ZeroPaddingBlock => f.write_str("the padding block size must not be 0"),
+            ConfigError::VolumeTooSmall => write!(
+                f,
+                "the volume size must be larger than {VOLUME_HEADER_LEN} bytes"
+            ),
+            ConfigError::InvalidFec => f.write_str(
+                "the error correction needs 1 to 255 data and parity blocks, at most 256 \
+                blocks per group and a block size of 1 byte to 16 MiB",
+            ),
+            ConfigError::UnsupportedWithWords => f.write_str(
+                "error correction, compression, padding, volumes and resuming can't be \
+                combined with the word list format",
+            ),
+            ConfigError::UnsupportedWithResume => f.write_str(
+                "resuming is only supported for binary output files without error \
+                correction, compression or volumes",
+            ),
+            ConfigError::FeatureDisabled(feature) => {
+                write!(f, "the cargo feature `{feature}` is turned off")
+            }
+        }
+    }
+}
+
+/// Builder for a [`Config`], which checks the values as they are set
+///
+/// ## Example
+/// ```
+/// use onetime_cli::ConfigBuilder;
+///
+/// # fn main() -> Result<(), onetime_cli::Error> {
+/// let c = ConfigBuilder::new("secret.txt")
+///     .suffixes("a", "b")?
+///     .buffer(4096)?
+///     .quiet(false)
+///     .build()?;
+///
+/// assert_eq!(c.suffix1, "a");
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct ConfigBuilder {
+    config: Config,
+}
+
+impl ConfigBuilder {
+    /// Starts a [`Config`] for the given file, with default values otherwise.
+    pub fn new(file: impl Into<PathBuf>) -> Self {
+        Self {
+            config: Config {
+                file: file.into(),
+                ..Config::default()
+            },
         }
     }
+
+    /// Sets [`Config::shares`].
+    pub fn shares(mut self, share1: impl Into<PathBuf>, share2: impl Into<PathBuf>) -> Self {
+        self.config.shares = Some([share1.into(), share2.into()]);
+        self
+    }
+
+    /// Sets [`Config::dir1`] and [`Config::dir2`].
+    pub fn dirs(mut self, dir1: impl Into<PathBuf>, dir2: impl Into<PathBuf>) -> Self {
+        self.config.dir1 = Some(dir1.into());
+        self.config.dir2 = Some(dir2.into());
+        self
+    }
+
+    /// Sets [`Config::output`].
+    pub fn output(mut self, output: impl Into<PathBuf>) -> Self {
+        self.config.output = Some(output.into());
+        self
+    }
+
+    /// Sets [`Config::suffix1`] and [`Config::suffix2`].
+    ///
+    /// ## Error
+    /// Will return an [`Error::InvalidConfig`] if a suffix is empty or
+    /// starts with a dot, or if both are the same.
+    pub fn suffixes(mut self, suffix1: &str, suffix2: &str) -> Result<Self, Error> {
+        check_suffix(suffix1)?;
+        check_suffix(suffix2)?;
+
+        if suffix1 == suffix2 {
+            return Err(Error::InvalidConfig(ConfigError::IdenticalSuffixes));
+        }
+
+        self.config.suffix1 = suffix1.to_string();
+        self.config.suffix2 = suffix2.to_string();
+        Ok(self)
+    }
+
+    /// Sets [`Config::name_template`].
+    pub fn name_template(mut self, template: &str) -> Self {
+        self.config.name_template = Some(template.to_string());
+        self
+    }
+
+    /// Sets [`Config::buffer`].
+    ///
+    /// ## Error
+    /// Will return an [`Error::InvalidConfig`] if `buffer` is 0 or larger
+    /// than [`MAX_BUFFER`].
+    pub fn buffer(mut self, buffer: u32) -> Result<Self, Error> {
+        check_buffer(buffer)?;
+
+        self.config.buffer = buffer;
+        Ok(self)
+    }
+
+    /// Sets [`Config::rm`].
+    pub fn rm(mut self, rm: bool) -> Self {
+        self.config.rm = rm;
+        self
+    }
+
+    /// Sets [`Config::quiet`].
+    pub fn quiet(mut self, quiet: bool) -> Self {
+        self.config.quiet = quiet;
+        self
+    }
+
+    /// Sets [`Config::fec`].
+    ///
+    /// ## Error
+    /// Will return an [`Error::InvalidConfig`] if the numbers of blocks or
+    /// the block size are out of range.
+    pub fn fec(mut self, fec: FecParams) -> Result<Self, Error> {
+        check_fec(fec)?;
+
+        self.config.fec = Some(fec);
+        Ok(self)
+    }
+
+    /// Sets [`Config::compression`].
+    pub fn compression(mut self, compression: Compression) -> Self {
+        self.config.compression = Some(compression);
+        self
+    }
+
+    /// Sets [`Config::padding`].
+    ///
+    /// ## Error
+    /// Will return an [`Error::InvalidConfig`] if the block size of
+    /// [`Padding::Block`] is 0.
+    pub fn padding(mut self, padding: Padding) -> Result<Self, Error> {
+        check_padding(padding)?;
+
+        self.config.padding = Some(padding);
+        Ok(self)
+    }
+
+    /// Sets [`Config::volume_size`].
+    ///
+    /// ## Error
+    /// Will return an [`Error::InvalidConfig`] if `volume_size` is not
+    /// larger than the header of a volume.
+    pub fn volume_size(mut self, volume_size: u64) -> Result<Self, Error> {
+        check_volume_size(volume_size)?;
+
+        self.config.volume_size = Some(volume_size);
+        Ok(self)
+    }
+
+    /// Sets [`Config::resume`].
+    pub fn resume(mut self, resume: bool) -> Self {
+        self.config.resume = resume;
+        self
+    }
+
+    /// Sets [`Config::on_damage`].
+    pub fn on_damage(mut self, on_damage: OnDamage) -> Self {
+        self.config.on_damage = on_damage;
+        self
+    }
+
+    /// Sets [`Config::preserve`], [`Config::preserve_owner`] and
+    /// [`Config::preserve_xattrs`].
+    pub fn preserve(mut self, preserve: bool, owner: bool, xattrs: bool) -> Self {
+        self.config.preserve = preserve;
+        self.config.preserve_owner = owner;
+        self.config.preserve_xattrs = xattrs;
+        self
+    }
+
+    /// Sets [`Config::format`].
+    pub fn format(mut self, format: ShareFormat) -> Self {
+        self.config.format = format;
+        self
+    }
+
+    /// Sets [`Config::cancel`].
+    pub fn cancel(mut self, cancel: Arc<AtomicBool>) -> Self {
+        self.config.cancel = Some(cancel);
+        self
+    }
+
+    /// Returns the [`Config`], after checking it with [`Config::validate`],
+    /// which also rejects combinations of settings that don't work together.
+    pub fn build(self) -> Result<Config, Error> {
+        self.config.validate()?;
+
+        Ok(self.config)
+    }
+}
+
+fn check_suffix(suffix: &str) -> Result<(), Error> {
+    if suffix.is_empty() {
+        return Err(Error::InvalidConfig(ConfigError::EmptySuffix));
+    }
+
+    if suffix.starts_with('.') {
+        return Err(Error::InvalidConfig(ConfigError::SuffixStartsWithDot));
+    }
+
+    Ok(())
+}
+
+pub(crate) fn check_buffer(buffer: u32) -> Result<(), Error> {
+    match buffer {
+        0 => Err(Error::InvalidConfig(ConfigError::ZeroBuffer)),
+        buffer if buffer > MAX_BUFFER => Err(Error::InvalidConfig(ConfigError::BufferTooLarge)),
+        _ => Ok(()),
+    }
+}
+
+fn check_padding(padding: Padding) -> Result<(), Error> {
+    padding
+        .check()
+        .map_err(|_| Error::InvalidConfig(ConfigError::ZeroPaddingBlock))
+}
+
+fn check_volume_size(volume_size: u64) -> Result<(), Error> {
+    match volume_size <= VOLUME_HEADER_LEN as u64 {
+        true => Err(Error::InvalidConfig(ConfigError::VolumeTooSmall)),
+        false => Ok(()),
+    }
+}
+
+fn check_fec(fec: FecParams) -> Result<(), Error> {
+    fec.check()
+        .map_err(|_| Error::InvalidConfig(ConfigError::InvalidFec))
+}
+
+fn parent_dir(path: &Path) -> PathBuf {
+    match path.parent() {
+        Some(p) if !p.as_os_str().is_empty() => p.to_owned(),
+        _ => PathBuf::from("."),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_validate() {
+        assert!(Config::default().validate().is_ok());
+
+        let cases = [
+            ("", "otp.1", 1, ConfigError::EmptySuffix),
+            ("otp.0", ".otp.1", 1, ConfigError::SuffixStartsWithDot),
+            ("otp", "otp", 1, ConfigError::IdenticalSuffixes),
+            ("otp.0", "otp.1", 0, ConfigError::ZeroBuffer),
+            (
+                "otp.0",
+                "otp.1",
+                MAX_BUFFER + 1,
+                ConfigError::BufferTooLarge,
+            ),
+        ];
+
+        for (suffix1, suffix2, buffer, expected) in cases {
+            let c = Config {
+                buffer,
+                ..Config::new_with_suffixes("file", suffix1, suffix2)
+            };
+
+            assert!(matches!(c.validate(), Err(Error::InvalidConfig(e)) if e == expected));
+        }
+    }
+
+    #[test]
+    fn test_validate_options() {
+        let fec = |data_blocks, parity_blocks, block_size| FecParams {
+            data_blocks,
+            parity_blocks,
+            block_size,
+        };
+        let words = |c: Config| Config {
+            format: ShareFormat::Words,
+            ..c
+        };
+        let resume = |c: Config| Config { resume: true, ..c };
+
+        let cases = [
+            (
+                Config {
+                    padding: Some(Padding::Block(0)),
+                    ..Config::default()
+                },
+                ConfigError::ZeroPaddingBlock,
+            ),
+            (
+                Config {
+                    volume_size: Some(VOLUME_HEADER_LEN as u64),
+                    ..Config::default()
+                },
+                ConfigError::VolumeTooSmall,
+            ),
+            (
+                Config {
+                    fec: Some(fec(0, 4, 4096)),
+                    ..Config::default()
+                },
+                ConfigError::InvalidFec,
+            ),
+            (
+                Config {
+                    fec: Some(fec(32, 0, 4096)),
+                    ..Config::default()
+                },
+                ConfigError::InvalidFec,
+            ),
+            (
+                Config {
+                    fec: Some(fec(200, 57, 4096)),
+                    ..Config::default()
+                },
+                ConfigError::InvalidFec,
+            ),
+            (
+                Config {
+                    fec: Some(fec(32, 4, 0)),
+                    ..Config::default()
+                },
+                ConfigError::InvalidFec,
+            ),
+            (
+                words(Config {
+                    volume_size: Some(1000),
+                    ..Config::default()
+                }),
+                ConfigError::UnsupportedWithWords,
+            ),
+            (
+                words(resume(Config::default())),
+                ConfigError::UnsupportedWithWords,
+            ),
+            (
+                resume(Config {
+                    volume_size: Some(1000),
+                    ..Config::default()
+                }),
+                ConfigError::UnsupportedWithResume,
+            ),
+        ];
+
+        for (c, expected) in cases {
+            assert!(matches!(c.validate(), Err(Error::InvalidConfig(e)) if e == expected));
+        }
+
+        #[cfg(feature = "armor")]
+        assert!(matches!(
+            resume(Config {
+                format: ShareFormat::Armor,
+                ..Config::default()
+            })
+            .validate(),
+            Err(Error::InvalidConfig(ConfigError::UnsupportedWithResume))
+        ));
+
+        let c = Config {
+            padding: Some(Padding::Block(1)),
+            volume_size: Some(VOLUME_HEADER_LEN as u64 + 1),
+            ..Config::default()
+        };
+        assert!(c.validate().is_ok());
+
+        let c = Config {
+            fec: Some(fec(200, 56, 1)),
+            ..Config::default()
+        };
+        #[cfg(feature = "fec")]
+        assert!(c.validate().is_ok());
+        #[cfg(not(feature = "fec"))]
+        assert!(matches!(
+            c.validate(),
+            Err(Error::InvalidConfig(ConfigError::FeatureDisabled("fec")))
+        ));
+    }
+
+    #[test]
+    fn test_builder() {
+        let c = ConfigBuilder::new("file")
+            .suffixes("a", "b")
+            .unwrap()
+            .buffer(10)
+            .unwrap()
+            .build()
+            .unwrap();
+
+        assert_eq!(c.file, PathBuf::from("file"));
+        assert_eq!((c.suffix1.as_str(), c.suffix2.as_str()), ("a", "b"));
+        assert_eq!(c.buffer, 10);
+
+        assert!(ConfigBuilder::new("file").buffer(0).is_err());
+        assert!(ConfigBuilder::new("file").suffixes("a", "a").is_err());
+        assert!(ConfigBuilder::new("file").suffixes(".a", "b").is_err());
+        assert!(ConfigBuilder::new("file")
+            .padding(Padding::Block(0))
+            .is_err());
+        assert!(ConfigBuilder::new("file").volume_size(10).is_err());
+        assert!(ConfigBuilder::new("file").fec(FecParams::new(0)).is_err());
+
+        // Combinations are checked when building
+        let builder = ConfigBuilder::new("file")
+            .format(ShareFormat::Words)
+            .resume(true);
+        assert!(matches!(
+            builder.build(),
+            Err(Error::InvalidConfig(ConfigError::UnsupportedWithWords))
+        ));
+    }
 }
@@ -1,78 +1,235 @@
-use std::{
-    fmt::{Debug, Display},
-    io,
-};
+use core::fmt::{Debug, Display};
+#[cfg(feature = "std")]
+use std::io;
+#[cfg(feature = "std")]
+use std::ops::Range;
+#[cfg(feature = "std")]
+use std::path::PathBuf;
+
+#[cfg(feature = "std")]
+use crate::ConfigError;
 
 /// Crate specific error type
+///
+/// Most variants only exist with the `std` feature. The enum is
+/// `#[non_exhaustive]`, so that a `match` written against a build without
+/// `std` doesn't break when another crate in the same build turns it on.
+#[non_exhaustive]
 pub enum Error {
     /// An error that occurred during an I/O operation. See [`IoError`](IoError)
+    #[cfg(feature = "std")]
     IoError(IoError),
 
     /// An error that is related to input data being invalid. Each function
     /// that possibly returns this error has detailed information about when
     /// it returns this error type.
+    #[cfg(feature = "std")]
     InvalidInput(String),
 
     /// At least one passed buffer does not fulfill its size requirements
     InvalidBufferSizes,
+
+    /// The decrypted data does not match the digest stored in the input files
+    DigestMismatch,
+
+    /// The [`Config`](crate::Config) can't be used. See
+    /// [`Config::validate`](crate::Config::validate).
+    #[cfg(feature = "std")]
+    InvalidConfig(ConfigError),
+
+    /// The two encrypted files don't belong to the same pair
+    #[cfg(feature = "std")]
+    ShareMismatch([PathBuf; 2]),
+
+    /// The output file already exists as one of the input files, which
+    /// would be destroyed by writing to it
+    #[cfg(feature = "std")]
+    OutputExists(PathBuf),
+
+    /// An encrypted file contains damaged data in the given byte range of
+    /// its encrypted data, according to its hash tree. A damaged hash tree
+    /// is reported with the range of the trailer, which follows the data.
+    #[cfg(feature = "std")]
+    IntegrityFailure {
+        /// The damaged file
+        path: PathBuf,
+
+        /// The damaged byte range
+        range: Range<u64>,
+    },
+
+    /// The operation was cancelled through [`Config::cancel`](crate::Config::cancel)
+    #[cfg(feature = "std")]
+    Cancelled,
 }
 
 impl Display for Error {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         match &self {
+            #[cfg(feature = "std")]
             Error::IoError(io_e) => f.write_fmt(format_args!("{io_e}")),
+            #[cfg(feature = "std")]
             Error::InvalidInput(e) => f.write_fmt(format_args!("Invalid input: {e}")),
             Error::InvalidBufferSizes => f.write_str("Invalid buffer sizes"),
+            Error::DigestMismatch => f.write_str(
+                "Integrity check failed: the decrypted data does not match the original",
+            ),
+            #[cfg(feature = "std")]
+            Error::InvalidConfig(e) => f.write_fmt(format_args!("Invalid configuration: {e}")),
+            #[cfg(feature = "std")]
+            Error::ShareMismatch([share1, share2]) => f.write_fmt(format_args!(
+                "The encrypted files {share1:?} and {share2:?} don't belong together"
+            )),
+            #[cfg(feature = "std")]
+            Error::OutputExists(path) => f.write_fmt(format_args!(
+                "The output file {path:?} is one of the input files"
+            )),
+            #[cfg(feature = "std")]
+            Error::IntegrityFailure { path, range } => f.write_fmt(format_args!(
+                "Damaged data in {path:?} at bytes {}..{} of the encrypted data",
+                range.start, range.end
+            )),
+            #[cfg(feature = "std")]
+            Error::Cancelled => f.write_str("The operation was cancelled"),
         }
     }
 }
 
 impl Debug for Error {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         match &self {
+            #[cfg(feature = "std")]
             Error::IoError(io_e) => f.write_fmt(format_args!("IoError ({io_e:?})")),
+            #[cfg(feature = "std")]
             Error::InvalidInput(e) => f.write_fmt(format_args!("InvalidInput ({e:?})")),
             Error::InvalidBufferSizes => f.write_str("Invalid buffer sizes"),
+            Error::DigestMismatch => f.write_str("DigestMismatch"),
+            #[cfg(feature = "std")]
+            Error::InvalidConfig(e) => f.write_fmt(format_args!("InvalidConfig ({e:?})")),
+            #[cfg(feature = "std")]
+            Error::ShareMismatch(paths) => f.write_fmt(format_args!("ShareMismatch ({paths:?})")),
+            #[cfg(feature = "std")]
+            Error::OutputExists(path) => f.write_fmt(format_args!("OutputExists ({path:?})")),
+            #[cfg(feature = "std")]
+            Error::IntegrityFailure { path, range } => f.write_fmt(format_args!(
+                "IntegrityFailure {{ path: {path:?}, range: {range:?} }}"
+            )),
+            #[cfg(feature = "std")]
+            Error::Cancelled => f.write_str("Cancelled"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::IoError(io_e) => Some(&io_e.error),
+            _ => None,
         }
     }
 }
 
 /// Variant of [`Error`] representing an error that occurred during file I/O
+#[cfg(feature = "std")]
+#[derive(Debug)]
 pub struct IoError {
-    /// The file that the operation was working on or related to
-    pub file: String,
+    /// The file that the operation was working on or related to, or `None`
+    /// for streams that are not files
+    pub path: Option<PathBuf>,
+
+    /// The operation that failed
+    pub operation: IoOperation,
 
     /// The actual [`std::io::Error`] that occurred
     pub error: io::Error,
 }
 
+#[cfg(feature = "std")]
+impl IoError {
+    /// Wraps `error`, which occurred while doing `operation` on the file at `path`.
+    pub fn new(path: impl Into<PathBuf>, operation: IoOperation, error: io::Error) -> Self {
+        Self {
+            path: Some(path.into()),
+            operation,
+            error,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
 impl Display for IoError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.write_fmt(format_args!("File: \"{}\"; {}", self.file, self.error))
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match &self.path {
+            Some(path) => f.write_fmt(format_args!(
+                "Failed to {} {path:?}: {}",
+                self.operation, self.error
+            )),
+            None => f.write_fmt(format_args!("Failed to {}: {}", self.operation, self.error)),
+        }
     }
 }
 
-impl Debug for IoError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.write_fmt(format_args!(
-            "IoError {{ file: \"{}\", error: {:?} }}",
-            self.file, self.error
-        ))
+#[cfg(feature = "std")]
+impl std::error::Error for IoError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
     }
 }
 
-#[cfg(test)]
+#[cfg(feature = "std")]
+impl From<IoError> for Error {
+    fn from(e: IoError) -> Self {
+        Error::IoError(e)
+    }
+}
+
+/// The kind of operation an [`IoError`] occurred in
+#[cfg(feature = "std")]
+#[non_exhaustive]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IoOperation {
+    /// Opening or creating a file, or listing a directory
+    Open,
+
+    /// Reading from a file or its metadata
+    Read,
+
+    /// Writing to a file or changing its metadata
+    Write,
+
+    /// Removing or renaming a file
+    Remove,
+}
+
+#[cfg(feature = "std")]
+impl Display for IoOperation {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(match self {
+            IoOperation::Open => "open",
+            IoOperation::Read => "read",
+            IoOperation::Write => "write",
+            IoOperation::Remove => "remove",
+        })
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
 mod test {
     use super::*;
 
     #[test]
     fn test_io_error_display() {
-        let e = Error::IoError(IoError {
-            file: "picture.png".to_string(),
-            error: io::Error::from(io::ErrorKind::NotFound),
-        });
+        let e = Error::IoError(IoError::new(
+            "picture.png",
+            IoOperation::Open,
+            io::Error::from(io::ErrorKind::NotFound),
+        ));
 
-        assert_eq!(&format!("{e}"), "File: \"picture.png\"; entity not found")
+        assert_eq!(
+            &format!("{e}"),
+            "Failed to open \"picture.png\": entity not found"
+        )
     }
 
     #[test]
@@ -84,21 +241,57 @@ mod test {
 
     #[test]
     fn test_io_error_debug() {
-        let e = Error::IoError(IoError {
-            file: "picture.png".to_string(),
-            error: io::Error::from(io::ErrorKind::NotFound),
-        });
+        let e = Error::IoError(IoError::new(
+            "picture.png",
+            IoOperation::Open,
+            io::Error::from(io::ErrorKind::NotFound),
+        ));
 
         assert_eq!(
             &format!("{e:?}"),
-            "IoError (IoError { file: \"picture.png\", error: Kind(NotFound) })"
+            "IoError (IoError { path: Some(\"picture.png\"), operation: Open, error: Kind(NotFound) })"
         )
     }
 
+    #[test]
+    fn test_io_error_source() {
+        let e: Box<dyn std::error::Error> = Box::new(Error::IoError(IoError::new(
+            "picture.png",
+            IoOperation::Read,
+            io::Error::from(io::ErrorKind::NotFound),
+        )));
+
+        assert_eq!(e.source().unwrap().to_string(), "entity not found");
+        assert!(std::error::Error::source(&Error::DigestMismatch).is_none());
+    }
+
     #[test]
     fn test_invalid_input_error_debug() {
         let e = Error::InvalidInput("param1 is None".to_string());
 
         assert_eq!(&format!("{e:?}"), "InvalidInput (\"param1 is None\")")
     }
+
+    #[test]
+    fn test_digest_mismatch_error_display() {
+        let e = Error::DigestMismatch;
+
+        assert_eq!(
+            &format!("{e}"),
+            "Integrity check failed: the decrypted data does not match the original"
+        )
+    }
+
+    #[test]
+    fn test_integrity_failure_display() {
+        let e = Error::IntegrityFailure {
+            path: PathBuf::from("file.otp.0"),
+            range: 1024..2048,
+        };
+
+        assert_eq!(
+            &format!("{e}"),
+            "Damaged data in \"file.otp.0\" at bytes 1024..2048 of the encrypted data"
+        )
+    }
 }
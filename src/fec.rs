@@ -0,0 +1,394 @@
+//! Optional forward error correction for shares.
+//!
+//! The protected data is cut into groups of `data_blocks * block_size`
+//! bytes. For every group, `parity_blocks` Reed-Solomon parity blocks are
+//! computed. Each block is stored as
+//!
+//! ```text
+//! group length (u32) | block_size bytes | CRC-32 (u32)
+//! ```
+//!
+//! The checksum tells which blocks are damaged. As long as no more than
+//! `parity_blocks` blocks of a group are damaged, the group can be repaired.
+
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::ops::Range;
+
+use reed_solomon_erasure::galois_8::ReedSolomon;
+
+use crate::stream::{resolve_seek, Sink, Source};
+use crate::FecParams;
+
+impl FecParams {
+    fn codec(&self) -> Result<ReedSolomon, String> {
+        ReedSolomon::new(self.data_blocks as usize, self.parity_blocks as usize)
+            .map_err(|e| format!("invalid number of data/parity blocks ({e:?})"))
+    }
+
+    fn blocks(&self) -> usize {
+        self.data_blocks as usize + self.parity_blocks as usize
+    }
+
+    /// Length of the data protected by one group
+    fn group_len(&self) -> u64 {
+        self.data_blocks as u64 * self.block_size as u64
+    }
+
+    /// Length of one stored block, including group length and checksum
+    fn stored_block_len(&self) -> usize {
+        self.block_size as usize + 8
+    }
+
+    /// Length of one stored group
+    fn stored_group_len(&self) -> u64 {
+        self.blocks() as u64 * self.stored_block_len() as u64
+    }
+}
+
+/// Adds parity blocks to everything written to it.
+pub struct FecWriter<W: Sink> {
+    inner: W,
+    params: FecParams,
+    codec: ReedSolomon,
+    buf: Vec<u8>,
+}
+
+impl<W: Sink> FecWriter<W> {
+    pub fn new(inner: W, params: FecParams) -> io::Result<Self> {
+        let codec = params
+            .codec()
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+
+        Ok(Self {
+            inner,
+            params,
+            codec,
+            buf: Vec::with_capacity(params.group_len() as usize),
+        })
+    }
+
+    fn write_group(&mut self) -> io::Result<()> {
+        let block_size = self.params.block_size as usize;
+        let group_len = (self.buf.len() as u32).to_le_bytes();
+
+        let mut blocks = vec![vec![0u8; block_size]; self.params.blocks()];
+        for (block, data) in blocks.iter_mut().zip(self.buf.chunks(block_size)) {
+            block[..data.len()].copy_from_slice(data);
+        }
+
+        self.codec
+            .encode(&mut blocks)
+            .map_err(|e| io::Error::other(format!("{e:?}")))?;
+
+        let mut stored = Vec::with_capacity(self.params.stored_group_len() as usize);
+        for block in &blocks {
+            let start = stored.len();
+            stored.extend_from_slice(&group_len);
+            stored.extend_from_slice(block);
+
+            let crc = crc32fast::hash(&stored[start..]);
+            stored.extend_from_slice(&crc.to_le_bytes());
+        }
+
+        self.inner.write_all(&stored)?;
+        self.buf.clear();
+
+        Ok(())
+    }
+}
+
+impl<W: Sink> Write for FecWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let free = self.params.group_len() as usize - self.buf.len();
+        let bytes = buf.len().min(free);
+
+        self.buf.extend_from_slice(&buf[..bytes]);
+
+        if self.buf.len() == self.params.group_len() as usize {
+            self.write_group()?;
+        }
+
+        Ok(bytes)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl<W: Sink> Sink for FecWriter<W> {
+    fn finish(mut self: Box<Self>) -> io::Result<()> {
+        if !self.buf.is_empty() {
+            self.write_group()?;
+        }
+
+        Box::new(self.inner).finish()
+    }
+}
+
+/// A block group with more damaged blocks than can be repaired. It is
+/// wrapped in the `io::Error` returned by [`FecReader`], so that
+/// [`io_error`](crate::fs::io_error) can report it as an
+/// [`IntegrityFailure`](crate::Error::IntegrityFailure).
+#[derive(Debug)]
+pub struct Unrepairable {
+    /// Byte range of the group within the protected data
+    pub range: Range<u64>,
+
+    index: u64,
+    damaged: usize,
+    parity_blocks: u8,
+}
+
+impl Display for Unrepairable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "too many damaged blocks in block group {} ({} damaged, at most {} can be repaired)",
+            self.index, self.damaged, self.parity_blocks
+        )
+    }
+}
+
+impl std::error::Error for Unrepairable {}
+
+/// Reads data written by [`FecWriter`], repairing damaged blocks.
+pub struct FecReader<S: Source> {
+    inner: S,
+    params: FecParams,
+    codec: ReedSolomon,
+    len: u64,
+    pos: u64,
+    group: Option<(u64, Vec<u8>)>,
+
+    /// Number of damaged blocks per group
+    damaged: BTreeMap<u64, u64>,
+}
+
+impl<S: Source> FecReader<S> {
+    pub fn new(mut inner: S, params: FecParams) -> io::Result<Self> {
+        let codec = params
+            .codec()
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+
+        let stored_len = inner.seek(SeekFrom::End(0))?;
+        if stored_len % params.stored_group_len() != 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "error correction data is truncated",
+            ));
+        }
+
+        let mut reader = Self {
+            inner,
+            params,
+            codec,
+            len: 0,
+            pos: 0,
+            group: None,
+            damaged: BTreeMap::new(),
+        };
+
+        let groups = stored_len / params.stored_group_len();
+        if groups > 0 {
+            reader.load_group(groups - 1)?;
+
+            let last_len = reader.group.as_ref().map(|(_, g)| g.len()).unwrap_or(0);
+            reader.len = (groups - 1) * params.group_len() + last_len as u64;
+        }
+
+        Ok(reader)
+    }
+
+    fn load_group(&mut self, index: u64) -> io::Result<()> {
+        if matches!(self.group, Some((i, _)) if i == index) {
+            return Ok(());
+        }
+
+        let block_size = self.params.block_size as usize;
+        let stored_block_len = self.params.stored_block_len();
+
+        let mut stored = vec![0u8; self.params.stored_group_len() as usize];
+        self.inner
+            .seek(SeekFrom::Start(index * self.params.stored_group_len()))?;
+        self.inner.read_exact(&mut stored)?;
+
+        let mut group_len = None;
+        let mut blocks: Vec<Option<Vec<u8>>> = Vec::with_capacity(self.params.blocks());
+
+        for stored_block in stored.chunks(stored_block_len) {
+            let (content, crc) = stored_block.split_at(block_size + 4);
+            let len = u32::from_le_bytes(content[..4].try_into().unwrap());
+
+            let valid = crc32fast::hash(content).to_le_bytes() == crc
+                && *group_len.get_or_insert(len) == len;
+
+            blocks.push(valid.then(|| content[4..].to_vec()));
+        }
+
+        let damaged = blocks.iter().filter(|b| b.is_none()).count();
+        if damaged > self.params.parity_blocks as usize {
+            let start = index * self.params.group_len();
+
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                Unrepairable {
+                    range: start..start + self.params.group_len(),
+                    index,
+                    damaged,
+                    parity_blocks: self.params.parity_blocks,
+                },
+            ));
+        }
+
+        let group_len = group_len.unwrap_or(0) as usize;
+        if group_len as u64 > self.params.group_len() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("invalid length of block group {index}"),
+            ));
+        }
+
+        if damaged > 0 {
+            self.codec
+                .reconstruct_data(&mut blocks)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("{e:?}")))?;
+        }
+
+        let mut data = Vec::with_capacity(self.params.group_len() as usize);
+        for block in blocks.into_iter().take(self.params.data_blocks as usize) {
+            data.extend_from_slice(&block.unwrap_or_default());
+        }
+        data.truncate(group_len);
+
+        self.damaged.insert(index, damaged as u64);
+        self.group = Some((index, data));
+
+        Ok(())
+    }
+}
+
+impl<S: Source> Read for FecReader<S> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.pos >= self.len || buf.is_empty() {
+            return Ok(0);
+        }
+
+        let index = self.pos / self.params.group_len();
+        self.load_group(index)?;
+
+        let group = &self.group.as_ref().unwrap().1;
+        let offset = (self.pos - index * self.params.group_len()) as usize;
+        let bytes = buf.len().min(group.len() - offset);
+
+        buf[..bytes].copy_from_slice(&group[offset..offset + bytes]);
+        self.pos += bytes as u64;
+
+        Ok(bytes)
+    }
+}
+
+impl<S: Source> Seek for FecReader<S> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.pos = resolve_seek(pos, self.pos, self.len)?;
+
+        Ok(self.pos)
+    }
+}
+
+impl<S: Source> Source for FecReader<S> {
+    fn repaired_blocks(&self) -> u64 {
+        self.damaged.values().sum::<u64>() + self.inner.repaired_blocks()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::Cursor;
+
+    impl Sink for &mut Vec<u8> {
+        fn finish(self: Box<Self>) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Source for Cursor<Vec<u8>> {}
+
+    const PARAMS: FecParams = FecParams {
+        data_blocks: 4,
+        parity_blocks: 2,
+        block_size: 16,
+    };
+
+    fn encode(data: &[u8]) -> Vec<u8> {
+        let mut stored = Vec::new();
+
+        let mut writer = Box::new(FecWriter::new(&mut stored, PARAMS).unwrap());
+        writer.write_all(data).unwrap();
+        writer.finish().unwrap();
+
+        stored
+    }
+
+    fn decode(stored: Vec<u8>) -> io::Result<(Vec<u8>, u64)> {
+        let mut reader = FecReader::new(Cursor::new(stored), PARAMS)?;
+
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+
+        Ok((data, reader.repaired_blocks()))
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let data: Vec<u8> = (0..200).collect();
+        let stored = encode(&data);
+
+        assert_eq!(stored.len() as u64, 4 * PARAMS.stored_group_len());
+        assert_eq!(decode(stored).unwrap(), (data, 0));
+    }
+
+    #[test]
+    fn test_repair() {
+        let data: Vec<u8> = (0..200).collect();
+        let mut stored = encode(&data);
+
+        // Two damaged blocks in the first group, one in the last
+        stored[3] ^= 0xff;
+        stored[30] ^= 0x01;
+        let last = stored.len() - 1;
+        stored[last] ^= 0x10;
+
+        assert_eq!(decode(stored).unwrap(), (data, 3));
+    }
+
+    #[test]
+    fn test_too_much_damage() {
+        let data: Vec<u8> = (0..64).collect();
+        let mut stored = encode(&data);
+
+        for block in 0..3 {
+            stored[block * PARAMS.stored_block_len() + 10] ^= 0xff;
+        }
+
+        assert_eq!(
+            decode(stored).unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+    }
+
+    #[test]
+    fn test_seek() {
+        let data: Vec<u8> = (0..200).collect();
+        let mut reader = FecReader::new(Cursor::new(encode(&data)), PARAMS).unwrap();
+
+        let mut buf = [0u8; 10];
+        reader.seek(SeekFrom::End(-10)).unwrap();
+        reader.read_exact(&mut buf).unwrap();
+
+        assert_eq!(buf[..], data[190..]);
+    }
+}
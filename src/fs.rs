@@ -1,61 +1,97 @@
-use std::ffi::OsString;
+use std::ffi::{OsStr, OsString};
 use std::fs::File;
-use std::io::{Read, Write};
+use std::io::{self, Read, Write};
 use std::path::{Path, PathBuf};
 
-use crate::{Error, IoError};
+use log::debug;
 
+#[cfg(feature = "fec")]
+use crate::fec::Unrepairable;
+use crate::{Error, IoError, IoOperation};
+
+/// How to open a file
 #[derive(Clone, Copy)]
 pub enum Mode {
+    /// Open an existing file for reading
     Open,
+
+    /// Create or truncate a file for writing
     Create,
 }
 
 pub fn open_file(path: &Path, mode: Mode) -> Result<File, Error> {
     let res = match mode {
-        Mode::Open => File::open(path),
-        Mode::Create => File::create(path),
+        Mode::Open => {
+            debug!("Opening {path:?} for reading");
+            File::open(path)
+        }
+        Mode::Create => {
+            debug!("Creating {path:?}");
+            File::create(path)
+        }
     };
 
     match res {
         Ok(f) => Ok(f),
-        Err(e) => Err(Error::IoError(IoError {
-            file: format!("{path:?}"),
-            error: e,
-        })),
+        Err(e) => Err(io_error(path, IoOperation::Open, e)),
     }
 }
 
-pub fn read(file: &mut File, buf: &mut [u8]) -> Result<usize, Error> {
+pub fn read(file: &mut File, path: &Path, buf: &mut [u8]) -> Result<usize, Error> {
     match file.read(buf) {
         Ok(bytes) => Ok(bytes),
-        Err(e) => Err(Error::IoError(IoError {
-            file: format!("{file:?}"),
-            error: e,
-        })),
+        Err(e) => Err(io_error(path, IoOperation::Read, e)),
     }
 }
 
-pub fn write(file: &mut File, buf: &[u8]) -> Result<usize, Error> {
-    match file.write(buf) {
-        Ok(bytes) => Ok(bytes),
-        Err(e) => Err(Error::IoError(IoError {
-            file: format!("{file:?}"),
-            error: e,
-        })),
+pub fn write(file: &mut File, path: &Path, buf: &[u8]) -> Result<usize, Error> {
+    match file.write_all(buf) {
+        Ok(_) => Ok(buf.len()),
+        Err(e) => Err(io_error(path, IoOperation::Write, e)),
+    }
+}
+
+/// Reads until `buf` is full or the end of `r` is reached.
+pub fn read_full<R: Read>(r: &mut R, buf: &mut [u8], path: &Path) -> Result<usize, Error> {
+    let mut total = 0;
+
+    while total < buf.len() {
+        match r.read(&mut buf[total..]) {
+            Ok(0) => break,
+            Ok(bytes) => total += bytes,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => (),
+            Err(e) => return Err(io_error(path, IoOperation::Read, e)),
+        }
     }
+
+    Ok(total)
 }
 
 pub fn remove_file(path: &Path) -> Result<(), Error> {
+    debug!("Removing {path:?}");
+
     match std::fs::remove_file(path) {
         Ok(_) => Ok(()),
-        Err(e) => Err(Error::IoError(IoError {
-            file: format!("{path:?}"),
-            error: e,
-        })),
+        Err(e) => Err(io_error(path, IoOperation::Remove, e)),
     }
 }
 
+pub fn io_error(path: &Path, operation: IoOperation, error: std::io::Error) -> Error {
+    // Damage that the error correction can't repair is not an I/O failure
+    #[cfg(feature = "fec")]
+    if let Some(e) = error
+        .get_ref()
+        .and_then(|e| e.downcast_ref::<Unrepairable>())
+    {
+        return Error::IntegrityFailure {
+            path: path.to_owned(),
+            range: e.range.clone(),
+        };
+    }
+
+    Error::IoError(IoError::new(path, operation, error))
+}
+
 pub fn extend_extension(path: &Path, extension: &str) -> PathBuf {
     let mut path = path.to_owned();
 
@@ -75,3 +111,57 @@ pub fn extend_extension(path: &Path, extension: &str) -> PathBuf {
 
     path
 }
+
+/// Returns whether both paths point to the same existing file.
+pub fn same_file(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+/// Returns whether both paths are on the same filesystem device, or `None`
+/// if that can't be determined.
+pub fn same_device(a: &Path, b: &Path) -> Option<bool> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+
+        let dev_a = std::fs::metadata(a).ok()?.dev();
+        let dev_b = std::fs::metadata(b).ok()?.dev();
+
+        Some(dev_a == dev_b)
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = (a, b);
+        None
+    }
+}
+
+pub fn os_str_to_bytes(s: &OsStr) -> Vec<u8> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStrExt;
+        s.as_bytes().to_vec()
+    }
+
+    #[cfg(not(unix))]
+    {
+        s.to_string_lossy().into_owned().into_bytes()
+    }
+}
+
+pub fn os_string_from_bytes(buf: &[u8]) -> OsString {
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStrExt;
+        OsStr::from_bytes(buf).to_owned()
+    }
+
+    #[cfg(not(unix))]
+    {
+        OsString::from(String::from_utf8_lossy(buf).into_owned())
+    }
+}
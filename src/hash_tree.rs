@@ -0,0 +1,287 @@
+//! Hash trees over the encrypted data of a share.
+//!
+//! The data is cut into chunks of a fixed size. The leaves of the tree are
+//! the SHA-256 digests of these chunks; every inner node is the digest of
+//! its two children. Comparing a stored tree with one computed from the
+//! current data tells exactly which chunks were damaged.
+
+use std::ops::Range;
+
+use sha2::{Digest, Sha256};
+
+use crate::config::MAX_BUFFER;
+use crate::share::DIGEST_LEN;
+
+type Hash = [u8; DIGEST_LEN];
+
+/// Prefixes that keep leaves, inner nodes and the root apart
+const LEAF_PREFIX: u8 = 0;
+const NODE_PREFIX: u8 = 1;
+const ROOT_PREFIX: u8 = 2;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HashTree {
+    pub chunk_size: u32,
+    pub leaves: Vec<Hash>,
+}
+
+impl HashTree {
+    /// Digest of the root node and the chunk size, so that a damaged chunk
+    /// size is detected as well
+    pub fn root(&self) -> Hash {
+        let top: Hash = self
+            .levels()
+            .last()
+            .and_then(|l| l.first().copied())
+            .unwrap_or_else(|| Sha256::digest([NODE_PREFIX]).into());
+
+        let mut hasher = Sha256::new();
+        hasher.update([ROOT_PREFIX]);
+        hasher.update(self.chunk_size.to_le_bytes());
+        hasher.update(top);
+
+        hasher.finalize().into()
+    }
+
+    /// Returns whether the tree has one leaf per chunk of data of length
+    /// `data_len`.
+    pub fn covers(&self, data_len: u64) -> bool {
+        self.leaves.len() as u64 == data_len.div_ceil(self.chunk_size as u64)
+    }
+
+    /// Returns the digest a leaf must have for the given chunk.
+    pub fn leaf_hash(chunk: &[u8]) -> Hash {
+        let mut hasher = Sha256::new();
+        hasher.update([LEAF_PREFIX]);
+        hasher.update(chunk);
+
+        hasher.finalize().into()
+    }
+
+    /// Byte range of the given chunk within data of length `data_len`
+    pub fn chunk_range(&self, index: u64, data_len: u64) -> Range<u64> {
+        let start = index * self.chunk_size as u64;
+
+        start..(start + self.chunk_size as u64).min(data_len)
+    }
+
+    /// Returns the indices of the chunks whose leaves differ from the ones
+    /// of `other`, in ascending order.
+    pub fn damaged_chunks(&self, other: &HashTree) -> Vec<u64> {
+        if self.chunk_size != other.chunk_size || self.leaves.len() != other.leaves.len() {
+            return (0..self.leaves.len().max(other.leaves.len()) as u64).collect();
+        }
+
+        let (a, b) = (self.levels(), other.levels());
+        let mut damaged = Vec::new();
+
+        // Only descend into subtrees whose roots differ
+        let mut stack = vec![(a.len().saturating_sub(1), 0)];
+        while let Some((level, i)) = stack.pop() {
+            if a.is_empty() || a[level][i] == b[level][i] {
+                continue;
+            }
+
+            if level == 0 {
+                damaged.push(i as u64);
+                continue;
+            }
+
+            if 2 * i + 1 < a[level - 1].len() {
+                stack.push((level - 1, 2 * i + 1));
+            }
+            stack.push((level - 1, 2 * i));
+        }
+
+        damaged
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(4 + DIGEST_LEN * (self.leaves.len() + 1));
+        buf.extend_from_slice(&self.chunk_size.to_le_bytes());
+        buf.extend_from_slice(&self.root());
+
+        for leaf in &self.leaves {
+            buf.extend_from_slice(leaf);
+        }
+
+        buf
+    }
+
+    /// Parses a tree written by [`to_bytes`](HashTree::to_bytes) and checks
+    /// it against its stored root. Chunk sizes of 0 or above [`MAX_BUFFER`]
+    /// are rejected, as they can't have been written.
+    pub fn from_bytes(buf: &[u8]) -> Result<HashTree, String> {
+        if buf.len() < 4 + DIGEST_LEN || (buf.len() - 4) % DIGEST_LEN != 0 {
+            return Err("invalid hash tree length".to_string());
+        }
+
+        let chunk_size = u32::from_le_bytes(buf[..4].try_into().unwrap());
+        let mut hashes = buf[4..]
+            .chunks(DIGEST_LEN)
+            .map(|h| Hash::try_from(h).unwrap());
+        let root = hashes.next().unwrap();
+
+        let tree = HashTree {
+            chunk_size,
+            leaves: hashes.collect(),
+        };
+
+        if chunk_size == 0 || chunk_size > MAX_BUFFER || tree.root() != root {
+            return Err("damaged hash tree".to_string());
+        }
+
+        Ok(tree)
+    }
+
+    /// All levels of the tree, starting with the leaves
+    fn levels(&self) -> Vec<Vec<Hash>> {
+        if self.leaves.is_empty() {
+            return Vec::new();
+        }
+
+        let mut levels = vec![self.leaves.clone()];
+
+        while levels.last().unwrap().len() > 1 {
+            let level = levels.last().unwrap().chunks(2).map(|pair| match pair {
+                [left, right] => {
+                    let mut hasher = Sha256::new();
+                    hasher.update([NODE_PREFIX]);
+                    hasher.update(left);
+                    hasher.update(right);
+
+                    hasher.finalize().into()
+                }
+                [single] => *single,
+                _ => unreachable!("chunks(2) yields one or two elements"),
+            });
+
+            levels.push(level.collect());
+        }
+
+        levels
+    }
+}
+
+/// Builds a [`HashTree`] from data passed in pieces of any size.
+pub struct TreeBuilder {
+    tree: HashTree,
+    hasher: Sha256,
+    filled: u64,
+}
+
+impl TreeBuilder {
+    pub fn new(chunk_size: u32) -> Self {
+        Self {
+            tree: HashTree {
+                chunk_size,
+                leaves: Vec::new(),
+            },
+            hasher: Self::leaf_hasher(),
+            filled: 0,
+        }
+    }
+
+    pub fn update(&mut self, mut data: &[u8]) {
+        while !data.is_empty() {
+            let free = self.tree.chunk_size as u64 - self.filled;
+            let bytes = data.len().min(free.try_into().unwrap_or(usize::MAX));
+
+            self.hasher.update(&data[..bytes]);
+            self.filled += bytes as u64;
+            data = &data[bytes..];
+
+            if self.filled == self.tree.chunk_size as u64 {
+                self.push_leaf();
+            }
+        }
+    }
+
+    pub fn finish(mut self) -> HashTree {
+        if self.filled > 0 {
+            self.push_leaf();
+        }
+
+        self.tree
+    }
+
+    fn push_leaf(&mut self) {
+        let hasher = std::mem::replace(&mut self.hasher, Self::leaf_hasher());
+
+        self.tree.leaves.push(hasher.finalize().into());
+        self.filled = 0;
+    }
+
+    fn leaf_hasher() -> Sha256 {
+        let mut hasher = Sha256::new();
+        hasher.update([LEAF_PREFIX]);
+
+        hasher
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn build(data: &[u8], chunk_size: u32) -> HashTree {
+        let mut builder = TreeBuilder::new(chunk_size);
+
+        for piece in data.chunks(7) {
+            builder.update(piece);
+        }
+
+        builder.finish()
+    }
+
+    #[test]
+    fn test_builder_matches_leaf_hash() {
+        let data: Vec<u8> = (0..100).collect();
+        let tree = build(&data, 32);
+
+        assert_eq!(tree.leaves.len(), 4);
+        assert_eq!(tree.leaves[1], HashTree::leaf_hash(&data[32..64]));
+        assert_eq!(tree.leaves[3], HashTree::leaf_hash(&data[96..]));
+        assert_eq!(tree.chunk_range(3, data.len() as u64), 96..100);
+    }
+
+    #[test]
+    fn test_damaged_chunks() {
+        let mut data: Vec<u8> = (0..200).collect();
+        let stored = build(&data, 16);
+
+        data[0] ^= 1;
+        data[100] ^= 1;
+        data[199] ^= 1;
+
+        assert_eq!(stored.damaged_chunks(&build(&data, 16)), vec![0, 6, 12]);
+        assert_eq!(stored.damaged_chunks(&stored), Vec::<u64>::new());
+    }
+
+    #[test]
+    fn test_bytes_roundtrip() {
+        let tree = build(&[1, 2, 3, 4, 5], 2);
+        let mut bytes = tree.to_bytes();
+
+        assert_eq!(HashTree::from_bytes(&bytes), Ok(tree.clone()));
+
+        bytes[40] ^= 1;
+        assert!(HashTree::from_bytes(&bytes).is_err());
+
+        // The chunk size is covered by the root
+        let mut bytes = tree.to_bytes();
+        bytes[0] ^= 1;
+        assert!(HashTree::from_bytes(&bytes).is_err());
+    }
+
+    #[test]
+    fn test_covers() {
+        let tree = build(&[1, 2, 3, 4, 5], 2);
+
+        assert!(tree.covers(5));
+        assert!(tree.covers(6));
+        assert!(!tree.covers(4));
+        assert!(!tree.covers(7));
+        assert!(build(&[], 2).covers(0));
+    }
+}
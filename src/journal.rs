@@ -0,0 +1,146 @@
+//! Progress journals for resuming interrupted runs.
+//!
+//! While [`Config::resume`](crate::Config::resume) is set, the progress of
+//! [`encrypt_file`](crate::encrypt_file) and
+//! [`decrypt_file`](crate::decrypt_file) is recorded at regular checkpoints
+//! in a journal next to the first output file, named `<output>.journal`:
+//!
+//! ```text
+//! magic | set id | offset (u64) | number of digests (u8) | SHA-256 digests ...
+//! ```
+//!
+//! The offset is the number of bytes of encrypted data that were written or
+//! read. The digests cover the prefixes of the encrypted files up to that
+//! offset, so changes to them can be detected before resuming. There is no
+//! digest of the plaintext, which would allow to check guesses of it with
+//! only one encrypted file; the plaintext is checked against the decrypted
+//! prefixes instead.
+
+use std::fs::{rename, File};
+use std::io::{ErrorKind, Read, Write};
+use std::path::{Path, PathBuf};
+
+use crate::fs::{extend_extension, io_error, remove_file};
+use crate::share::DIGEST_LEN;
+use crate::{Error, IoOperation};
+
+const MAGIC: &[u8; 8] = b"OTPJOURN";
+
+/// Number of bytes between two checkpoints
+pub const CHECKPOINT_INTERVAL: u64 = 64 << 20;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Journal {
+    /// Set id of the shares
+    pub set_id: [u8; 16],
+
+    /// Number of bytes of encrypted data processed up to the checkpoint
+    pub offset: u64,
+
+    /// Digests of the prefixes of the encrypted files written, up to the
+    /// checkpoint. Empty when decrypting.
+    pub digests: Vec<[u8; DIGEST_LEN]>,
+}
+
+impl Journal {
+    /// Path of the journal for the output file at `path`
+    pub fn path(path: &Path) -> PathBuf {
+        extend_extension(path, "journal")
+    }
+
+    /// Reads the journal for the output file at `path`. Returns `Ok(None)`
+    /// if there is none.
+    pub fn read(path: &Path) -> Result<Option<Journal>, Error> {
+        let journal_path = Self::path(path);
+
+        let mut buf = Vec::new();
+        match File::open(&journal_path).and_then(|mut f| f.read_to_end(&mut buf)) {
+            Ok(_) => (),
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(io_error(&journal_path, IoOperation::Open, e)),
+        }
+
+        Self::from_bytes(&buf).map(Some).ok_or_else(|| {
+            Error::InvalidInput(format!("{journal_path:?} is not a valid progress journal"))
+        })
+    }
+
+    /// Replaces the journal for the output file at `path`. The journal is
+    /// written to a temporary file first, so a valid journal always exists.
+    pub fn write(&self, path: &Path) -> Result<(), Error> {
+        let journal_path = Self::path(path);
+        let tmp_path = extend_extension(&journal_path, "tmp");
+
+        File::create(&tmp_path)
+            .and_then(|mut f| {
+                f.write_all(&self.to_bytes())?;
+                f.sync_all()
+            })
+            .map_err(|e| io_error(&tmp_path, IoOperation::Write, e))?;
+
+        rename(&tmp_path, &journal_path)
+            .map_err(|e| io_error(&journal_path, IoOperation::Remove, e))
+    }
+
+    /// Removes the journal for the output file at `path`, if there is one.
+    pub fn remove(path: &Path) -> Result<(), Error> {
+        let journal_path = Self::path(path);
+
+        match journal_path.exists() {
+            true => remove_file(&journal_path),
+            false => Ok(()),
+        }
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut buf = MAGIC.to_vec();
+        buf.extend_from_slice(&self.set_id);
+        buf.extend_from_slice(&self.offset.to_le_bytes());
+        buf.push(self.digests.len() as u8);
+
+        for digest in &self.digests {
+            buf.extend_from_slice(digest);
+        }
+
+        buf
+    }
+
+    fn from_bytes(buf: &[u8]) -> Option<Journal> {
+        let rest = buf.strip_prefix(MAGIC)?;
+        let (set_id, rest) = rest.split_first_chunk::<16>()?;
+        let (offset, rest) = rest.split_first_chunk::<8>()?;
+        let (count, rest) = rest.split_first()?;
+
+        if rest.len() != *count as usize * DIGEST_LEN {
+            return None;
+        }
+
+        Some(Journal {
+            set_id: *set_id,
+            offset: u64::from_le_bytes(*offset),
+            digests: rest
+                .chunks(DIGEST_LEN)
+                .map(|d| d.try_into().unwrap())
+                .collect(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_roundtrip() {
+        let journal = Journal {
+            set_id: [3; 16],
+            offset: 1 << 40,
+            digests: vec![[1; DIGEST_LEN], [2; DIGEST_LEN]],
+        };
+        let buf = journal.to_bytes();
+
+        assert_eq!(Journal::from_bytes(&buf), Some(journal));
+        assert_eq!(Journal::from_bytes(&buf[..buf.len() - 1]), None);
+        assert_eq!(Journal::from_bytes(b"OTPSHARE"), None);
+    }
+}
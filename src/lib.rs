@@ -29,14 +29,98 @@
 //! ```bash
 //! onetime-cli --help
 //! ```
+//!
+//! ## Cargo features
+//!
+//! * `std` (default): everything that works on files. Without it, only
+//!   [`encrypt_with_rng`], [`decrypt`] and [`Error`] are available, and the
+//!   crate is `no_std`.
+//! * `fec`: error correction of the encrypted files, see [`Config::fec`]
+//! * `armor`: encrypted files as ASCII armor ([`ShareFormat::Armor`]) and as
+//!   the texts of QR codes ([`qr_payloads`])
+//! * `compress`: compression of the data before encrypting it, see
+//!   [`Config::compression`]
+//! * `xattr`: extended attributes, see [`Config::preserve_xattrs`]
+//! * `cli` (default): the dependencies of the `onetime-cli` binary, including
+//!   all of the above. Turn off the default features and enable `std` and
+//!   the features you need to use this crate as a library without them.
+//! * `async`: [`encrypt_async`], [`decrypt_async`] and the helpers in
+//!   [`async_fs`], for use with tokio
+//!
+//! ## Logging
+//!
+//! With `std`, [`encrypt_file`], [`decrypt_file`] and [`verify_file`] log
+//! their progress through the [`log`](https://docs.rs/log) crate: the files
+//! opened, buffer sizes, chunks and timings. The data itself and the pad are
+//! never logged.
 
+#![cfg_attr(not(feature = "std"), no_std)]
 #![warn(missing_docs)]
 
+#[cfg(feature = "armor")]
+mod armor;
+#[cfg(feature = "async")]
+pub mod async_fs;
+#[cfg(feature = "async")]
+mod async_otp;
+#[cfg(feature = "std")]
+mod attrs;
+#[cfg(feature = "std")]
+mod compress;
+#[cfg(feature = "std")]
 mod config;
 mod error;
+#[cfg(feature = "fec")]
+mod fec;
+#[cfg(feature = "std")]
 mod fs;
+#[cfg(feature = "std")]
+mod hash_tree;
+#[cfg(feature = "std")]
+mod journal;
+#[cfg(feature = "std")]
 mod otp;
+#[cfg(feature = "std")]
+mod pad;
+#[cfg(feature = "std")]
+mod paper;
+#[cfg(feature = "armor")]
+mod qr;
+#[cfg(feature = "std")]
+mod share;
+#[cfg(feature = "std")]
+mod stream;
+#[cfg(feature = "std")]
+mod template;
+#[cfg(feature = "std")]
+mod volume;
+#[cfg(feature = "std")]
+mod words;
+mod xor;
 
-pub use config::Config;
-pub use error::{Error, IoError};
-pub use otp::{decrypt, decrypt_file, encrypt, encrypt_file};
+#[cfg(feature = "async")]
+pub use async_otp::{decrypt_async, encrypt_async};
+#[cfg(feature = "std")]
+pub use compress::Compression;
+#[cfg(feature = "std")]
+pub use config::{
+    Config, ConfigBuilder, ConfigError, FecParams, OnDamage, ShareFormat, MAX_BUFFER,
+};
+pub use error::Error;
+#[cfg(feature = "std")]
+pub use error::{IoError, IoOperation};
+#[cfg(feature = "std")]
+pub use otp::{
+    decrypt_file, decrypt_range, encrypt_file, verify_file, DecryptSummary, EncryptSummary,
+    VerifySummary,
+};
+#[cfg(feature = "std")]
+pub use pad::Padding;
+#[cfg(feature = "std")]
+pub use paper::{export_paper, import_paper};
+#[cfg(feature = "armor")]
+pub use qr::qr_payloads;
+pub use rand::{CryptoRng, RngCore};
+#[cfg(feature = "std")]
+pub use xor::encrypt;
+pub use xor::{decrypt, encrypt_with_rng};
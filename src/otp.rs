@@ -1,202 +1,1334 @@
-use crate::fs::extend_extension;
+use crate::attrs::FileAttributes;
+use crate::compress::{Compressor, Decompressor};
 use crate::fs::Mode;
-use crate::fs::{open_file, read, remove_file, write};
-use crate::Config;
-use crate::Error;
+use crate::fs::{io_error, open_file, read, read_full, remove_file, same_file, write};
+use crate::hash_tree::{HashTree, TreeBuilder};
+use crate::journal::{Journal, CHECKPOINT_INTERVAL};
+use crate::share::{check_pair, Header, Metadata, ShareReader, ShareWriter};
+use crate::volume::remove_share;
+use crate::xor::{decrypt, encrypt};
+use crate::{Config, OnDamage};
+use crate::{Error, IoError, IoOperation};
 
-use rand::Rng;
+use std::fs::{File, OpenOptions};
+use std::io::{Seek, SeekFrom, Write};
+use std::ops::Range;
+use std::path::{Component, Path, PathBuf};
+use std::time::{Instant, SystemTime};
 
-/// Encrypts data using the one-time pad.
+use log::{debug, info, trace, warn};
+use sha2::{Digest, Sha256};
+
+/// Size of the pieces [`decrypt_range`] reads from input files without hash trees
+const RANGE_BUFFER: u32 = 1 << 20;
+
+/// Information about a completed [`encrypt_file`] call
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct EncryptSummary {
+    /// Paths of the two encrypted files
+    pub shares: [PathBuf; 2],
+
+    /// Number of bytes of the file that were encrypted
+    pub length: u64,
+}
+
+/// Information about a completed [`decrypt_file`] call
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DecryptSummary {
+    /// Path of the decrypted file
+    pub output: PathBuf,
+
+    /// Length of the decrypted file in bytes
+    pub length: u64,
+
+    /// Number of damaged blocks in the input files that were repaired
+    /// using their error correction data
+    pub repaired_blocks: u64,
+
+    /// Byte ranges of damaged data that were skipped or zero-filled
+    /// according to [`Config::on_damage`]
+    pub damaged: Vec<Range<u64>>,
+}
+
+/// Information about a completed [`verify_file`] call
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct VerifySummary {
+    /// Damaged byte ranges of the encrypted data in the first input file,
+    /// or `None` if the file contains no hash tree to check against
+    pub damaged1: Option<Vec<Range<u64>>>,
+
+    /// The same as `damaged1`, but for the second input file
+    pub damaged2: Option<Vec<Range<u64>>>,
+
+    /// Whether the decrypted data matches the stored digest, or `None` if
+    /// the input files contain no digest
+    pub digest_matches: Option<bool>,
+
+    /// Number of damaged blocks that can be repaired using the error
+    /// correction data of the input files
+    pub repaired_blocks: u64,
+
+    /// Number of bytes of encrypted data checked in each input file
+    pub length: u64,
+}
+
+impl VerifySummary {
+    /// Returns `true` if no problems were found.
+    pub fn is_ok(&self) -> bool {
+        let intact = |d: &Option<Vec<Range<u64>>>| d.as_ref().map_or(true, |d| d.is_empty());
+
+        intact(&self.damaged1) && intact(&self.damaged2) && self.digest_matches != Some(false)
+    }
+}
+
+/// Encrypts a file using the options wrapped in a [`Config`].
+///
+/// A SHA-256 digest of the file is stored in the two output files, split
+/// between them so that neither reveals it on its own. [`decrypt_file`] uses
+/// it to check the decrypted file.
 ///
-/// ## Error
-/// Will return an [`Error::InvalidBufferSizes`] if either
-/// of `buf_out1` or `buf_out2` is smaller than `buf_in`.
+/// If [`Config::fec`] is set, error correction data is added to the output
+/// files, which allows [`decrypt_file`] to repair damaged blocks.
+///
+/// Unless [`Config::preserve`] is unset, the permissions and timestamps of
+/// the file are stored in the same way as the digest.
+///
+/// ## Errors
+///
+/// Returns an [`Error`] if:
+///  - any of the I/O operations fail ([`IoError`](Error::IoError))
+///  - the configuration is invalid ([`InvalidConfig`](Error::InvalidConfig)), see
+///    [`Config::validate`]
+///  - an output file would overwrite the input file ([`OutputExists`](Error::OutputExists))
+///  - the run is cancelled through [`Config::cancel`] ([`Cancelled`](Error::Cancelled))
 ///
 /// ## Example
-/// ```
-/// use onetime_cli::encrypt;
+/// ```no_run
+/// use onetime_cli::Config;
+/// use onetime_cli::encrypt_file;
+/// use onetime_cli::Error;
 ///
 /// # fn main() -> Result<(), onetime_cli::Error> {
-/// let data: [u8; 10] = [1,2,3,4,5,6,7,8,9,10];
-/// let mut out1 = [0u8; 10];
-/// let mut out2 = [0u8; 10];
-///
-/// encrypt(&data, &mut out1, &mut out2)?;
-/// // The encrypted parts are stored in `out1` and `out2`.
+/// let c = Config::new("secret.txt");
 ///
-/// println!("{:?}", out1);
-/// println!("{:?}", out2);
+/// let summary = encrypt_file(&c)?;
+/// assert_eq!(summary.shares[0].to_str(), Some("secret.txt.otp.0"));
 /// # Ok(())
 /// # }
 /// ```
-pub fn encrypt(buf_in: &[u8], buf_out1: &mut [u8], buf_out2: &mut [u8]) -> Result<(), Error> {
-    if (buf_out1.len() < buf_in.len()) || (buf_out2.len() < buf_in.len()) {
-        return Err(Error::InvalidBufferSizes);
+pub fn encrypt_file(c: &Config) -> Result<EncryptSummary, Error> {
+    c.validate()?;
+
+    let start = Instant::now();
+    let mut f_in = open_file(&c.file, Mode::Open)?;
+
+    // Before reading the file, which updates its access time
+    let attributes = match c.preserve {
+        true => FileAttributes::read(&c.file, &f_in, c.preserve_owner, c.preserve_xattrs)?,
+        false => FileAttributes::default(),
+    };
+
+    debug!(
+        "Encrypting {:?} with a buffer of {} bytes, format {:?}, error correction {:?}, \
+        compression {:?}, padding {:?}, volume size {:?}",
+        c.file, c.buffer, c.format, c.fec, c.compression, c.padding, c.volume_size
+    );
+
+    let mut buf_in = vec![0u8; c.buffer as usize];
+    let mut bufs_out = [vec![0u8; c.buffer as usize], vec![0u8; c.buffer as usize]];
+
+    let mut hasher = Sha256::new();
+    let (mut header1, mut header2) = Header::new_pair(c);
+
+    let journal = match c.resume {
+        // The names of the output files may contain the set id and date of
+        // the interrupted run, so they are looked up
+        true => match c.existing_share_paths()? {
+            Some(paths) => Journal::read(&paths[0])?.map(|j| (paths, j)),
+            None => None,
+        },
+        false => None,
+    };
+
+    let ([f_out1_name, f_out2_name], mut f_out1, mut f_out2) = match journal {
+        Some((paths, journal)) => {
+            info!("Resuming at byte {} of {:?}", journal.offset, c.file);
+
+            header1.set_id = journal.set_id;
+            header2.set_id = journal.set_id;
+
+            let mut f_out1 = ShareWriter::resume(&paths[0], &header1, c.buffer, journal.offset)?;
+            let mut f_out2 = ShareWriter::resume(&paths[1], &header2, c.buffer, journal.offset)?;
+
+            let changed = || {
+                Error::InvalidInput(
+                    "The input file or the output files changed since the interrupted run"
+                        .to_string(),
+                )
+            };
+
+            let digests = [f_out1.checkpoint()?, f_out2.checkpoint()?];
+            if journal.digests[..] != digests[..] {
+                return Err(changed());
+            }
+
+            let mut share1 = open_data(&paths[0], &header1)?;
+            let mut share2 = open_data(&paths[1], &header2)?;
+
+            let intact = check_prefix(
+                [
+                    &mut |buf| read_full(&mut f_in, buf, &c.file),
+                    &mut |buf| read_full(&mut share1, buf, &paths[0]),
+                    &mut |buf| read_full(&mut share2, buf, &paths[1]),
+                ],
+                journal.offset,
+                c.buffer,
+                &mut hasher,
+            )?;
+
+            if !intact {
+                return Err(changed());
+            }
+
+            (paths, f_out1, f_out2)
+        }
+        None => {
+            let paths = c.new_share_paths(&header1.set_id, SystemTime::now())?;
+
+            if let Some(path) = paths.iter().find(|p| same_file(p, &c.file)) {
+                return Err(Error::OutputExists(path.clone()));
+            }
+
+            let mut f_out1 =
+                ShareWriter::create(&paths[0], &header1, c.buffer, c.format, c.volume_size)?;
+            let mut f_out2 =
+                ShareWriter::create(&paths[1], &header2, c.buffer, c.format, c.volume_size)?;
+
+            if c.resume {
+                f_out1.keep_digest();
+                f_out2.keep_digest();
+            }
+
+            (paths, f_out1, f_out2)
+        }
+    };
+
+    let mut checkpoint = f_out1.data_len();
+    let mut compressor = c.compression.map(Compressor::new);
+    let compress_err = |error| io_error(&c.file, IoOperation::Read, error);
+
+    // When resuming, the data already written is the part of the file already read
+    let mut input_len = f_out1.data_len();
+    let mut chunks = 0u64;
+
+    loop {
+        if c.is_cancelled() {
+            return Err(cancel_encrypt(
+                c,
+                [f_out1, f_out2],
+                [&f_out1_name, &f_out2_name],
+            ));
+        }
+
+        let bytes = read(&mut f_in, &c.file, &mut buf_in)?;
+
+        if bytes == 0 {
+            break;
+        }
+
+        hasher.update(&buf_in[..bytes]);
+        input_len += bytes as u64;
+        chunks += 1;
+        trace!("Encrypting chunk {chunks} of {bytes} bytes");
+
+        match &mut compressor {
+            Some(compressor) => {
+                let data = compressor
+                    .compress(&buf_in[..bytes])
+                    .map_err(compress_err)?;
+                write_encrypted(&data, [&mut f_out1, &mut f_out2], &mut bufs_out)?;
+            }
+            None => write_encrypted(&buf_in[..bytes], [&mut f_out1, &mut f_out2], &mut bufs_out)?,
+        }
+
+        if c.resume && f_out1.data_len() - checkpoint >= CHECKPOINT_INTERVAL {
+            checkpoint = f_out1.data_len();
+
+            let journal = Journal {
+                set_id: header1.set_id,
+                offset: checkpoint,
+                digests: vec![f_out1.checkpoint()?, f_out2.checkpoint()?],
+            };
+            journal.write(&f_out1_name)?;
+            debug!("Saved progress at byte {checkpoint}");
+        }
+    }
+
+    if let Some(compressor) = compressor {
+        let data = compressor.finish().map_err(compress_err)?;
+        write_encrypted(&data, [&mut f_out1, &mut f_out2], &mut bufs_out)?;
     }
 
-    let mut rng = rand::thread_rng();
+    let length = f_out1.data_len();
 
-    for i in 0..buf_in.len() {
-        buf_out1[i] = rng.gen_range(0..=255);
-        buf_out2[i] = buf_in[i] ^ buf_out1[i];
+    if let Some(padding) = c.padding {
+        let mut rest = padding.padded_len(length) - length;
+        buf_in.fill(0);
+        debug!("Padding {length} bytes of data with {rest} bytes");
+
+        while rest > 0 {
+            if c.is_cancelled() {
+                return Err(cancel_encrypt(
+                    c,
+                    [f_out1, f_out2],
+                    [&f_out1_name, &f_out2_name],
+                ));
+            }
+
+            let bytes = rest.min(buf_in.len() as u64) as usize;
+            write_encrypted(&buf_in[..bytes], [&mut f_out1, &mut f_out2], &mut bufs_out)?;
+            rest -= bytes as u64;
+        }
     }
 
-    Ok(())
+    let meta = Metadata {
+        digest: Some(hasher.finalize().into()),
+        name: c.file.file_name().map(|n| n.to_owned()),
+        length: c.padding.map(|_| length),
+        attributes,
+    };
+    let (meta1, meta2) = meta.split()?;
+
+    f_out1.finish(meta1)?;
+    f_out2.finish(meta2)?;
+
+    if c.resume {
+        Journal::remove(&f_out1_name)?;
+    }
+
+    if c.rm {
+        remove_file(&c.file)?;
+    }
+
+    info!(
+        "Encrypted {input_len} bytes in {chunks} chunks to {f_out1_name:?} and {f_out2_name:?} \
+        in {:?}",
+        start.elapsed()
+    );
+
+    Ok(EncryptSummary {
+        shares: [f_out1_name, f_out2_name],
+        length: input_len,
+    })
 }
 
-/// Decrypts data using the one-time pad.
+/// Decrypts a file using the options wrapped in a [`Config`].
+///
+/// If the input files contain a digest of the original file, the decrypted
+/// file is checked against it. Damaged blocks of input files with error
+/// correction data are repaired; their number is returned in the
+/// [`DecryptSummary`].
+///
+/// Chunks of the input files that don't match their hash trees are handled
+/// according to [`Config::on_damage`]. If any chunks are skipped or
+/// zero-filled, the digest is not checked and the input files are kept even
+/// if [`Config::rm`] is set.
+///
+/// Input files with compressed data, see [`Config::compression`], are
+/// decompressed after decrypting. Their damaged chunks always fail.
 ///
-/// `buf_in1` and `buf_in2` must have the same size.
+/// Unless [`Config::preserve`] is unset, the file attributes stored by
+/// [`encrypt_file`] are restored. If that fails, a warning is logged and
+/// the decrypted file is kept.
+///
+/// The decrypted file is written to [`Config::output`]. If that is unset,
+/// it is written to [`Config::file`] or, if the encrypted files are passed
+/// in [`Config::shares`], to the original file name stored in them.
+///
+/// ## Errors
+///
+/// Returns an [`Error`] if:
+///  - any of the I/O operations fail ([`IoError`](Error::IoError))
+///  - the two input files differ in length or don't belong together
+///    ([`ShareMismatch`](Error::ShareMismatch))
+///  - the output file is one of the input files ([`OutputExists`](Error::OutputExists))
+///  - an input file contains damaged data and [`Config::on_damage`] is
+///    [`OnDamage::Fail`], more damaged blocks than the error correction
+///    can repair or a damaged hash tree ([`IntegrityFailure`](Error::IntegrityFailure))
+///  - the decrypted file doesn't match the stored digest ([`DigestMismatch`](Error::DigestMismatch))
+///  - the run is cancelled through [`Config::cancel`] ([`Cancelled`](Error::Cancelled)).
 ///
-/// ## Error
-/// Will return an [`Error::InvalidBufferSizes`] if:
-///   * `buf_in1` and `buf_in2` don't have the same size.
-///   * `buf_out` is smaller than the `buf_in`s.
+/// The decrypted file is removed if the input files are damaged or don't
+/// match the digest.
 ///
 /// ## Example
-/// ```
-/// use onetime_cli::decrypt;
+/// ```no_run
+/// use onetime_cli::Config;
+/// use onetime_cli::decrypt_file;
+/// use onetime_cli::Error;
 ///
 /// # fn main() -> Result<(), onetime_cli::Error> {
-/// let in1: [u8; 10] = [1, 2, 3, 4, 5, 6, 7, 8, 9, 10];
-/// let in2: [u8; 10] = [26, 89, 3, 93, 78, 12, 60, 23, 4, 71];
-/// let mut data = [0u8; 10];
-///
-/// decrypt(&in1, &in2, &mut data)?;
-/// // The decrypted data is stored in `data`.
+/// let c = Config::new("secret.txt");
+/// decrypt_file(&c)?;
 ///
-/// assert_eq!(data, [27, 91, 0, 89, 75, 10, 59, 31, 13, 77]);
+/// // Restores secret.txt under its original name
+/// let c = Config::new_with_shares("secret.txt.otp.0", "secret.txt.otp.1");
+/// let summary = decrypt_file(&c)?;
+/// assert_eq!(summary.output.to_str(), Some("secret.txt"));
 /// # Ok(())
 /// # }
 /// ```
-pub fn decrypt(buf_in1: &[u8], buf_in2: &[u8], buf_out: &mut [u8]) -> Result<(), Error> {
-    if (buf_in1.len() != buf_in2.len()) || (buf_out.len() < buf_in1.len()) {
-        return Err(Error::InvalidBufferSizes);
+pub fn decrypt_file(c: &Config) -> Result<DecryptSummary, Error> {
+    c.validate()?;
+
+    let start = Instant::now();
+    let [f_in1_name, f_in2_name] = c.share_paths()?;
+
+    let mut f_in1 = ShareReader::open(&f_in1_name)?;
+    let mut f_in2 = ShareReader::open(&f_in2_name)?;
+    let meta = check_pair(&f_in1, &f_in2)?;
+
+    let output = output_path(c, &meta)?;
+
+    if same_file(&output, &f_in1_name) || same_file(&output, &f_in2_name) {
+        return Err(Error::OutputExists(output));
     }
 
-    for i in 0..buf_in1.len() {
-        buf_out[i] = buf_in1[i] ^ buf_in2[i];
+    // Read whole chunks of the hash trees, so they can be checked
+    let chunk_size = f_in1.chunk_size().unwrap_or(c.buffer) as usize;
+
+    debug!(
+        "Decrypting {f_in1_name:?} and {f_in2_name:?} to {output:?} in chunks of {chunk_size} \
+        bytes, hash tree {}, digest {}",
+        f_in1.chunk_size().is_some(),
+        meta.digest.is_some()
+    );
+
+    let mut buf_in1 = vec![0u8; chunk_size];
+    let mut buf_in2 = vec![0u8; chunk_size];
+    let mut buf_out = vec![0u8; chunk_size];
+
+    let mut hasher = Sha256::new();
+    let mut damaged: Vec<Range<u64>> = Vec::new();
+    let mut offset = 0u64;
+
+    // Damaged chunks of compressed data can't be skipped or zero-filled
+    let compression = f_in1.header.as_ref().and_then(|h| h.compression);
+    let mut decompressor = compression.map(Decompressor::new);
+    let on_damage = match compression {
+        Some(_) => OnDamage::Fail,
+        None => c.on_damage,
+    };
+
+    if c.resume && (compression.is_some() || c.on_damage != OnDamage::Fail) {
+        return Err(Error::InvalidInput(
+            "Resuming is not supported for compressed data or when damaged data is skipped \
+            or zero-filled"
+                .to_string(),
+        ));
     }
 
-    Ok(())
+    let set_id = f_in1.header.as_ref().map_or([0; 16], |h| h.set_id);
+    let journal = match c.resume {
+        true => Journal::read(&output)?,
+        false => None,
+    };
+
+    let mut f_out = match journal {
+        Some(journal) => {
+            if journal.set_id != set_id {
+                return Err(Error::InvalidInput(format!(
+                    "The progress journal of {output:?} belongs to different input files"
+                )));
+            }
+
+            let len = meta
+                .length
+                .map_or(journal.offset, |l| l.min(journal.offset));
+            let f_out = resume_output(&output, len, [&mut f_in1, &mut f_in2], &mut hasher)?;
+
+            info!("Resuming at byte {} of {f_in1_name:?}", journal.offset);
+
+            f_in1.skip(journal.offset)?;
+            f_in2.skip(journal.offset)?;
+            offset = journal.offset;
+
+            f_out
+        }
+        None => open_file(&output, Mode::Create)?,
+    };
+    let mut checkpoint = offset;
+
+    loop {
+        if c.is_cancelled() {
+            info!("Cancelled decrypting to {output:?} at byte {offset}");
+            drop(f_out);
+
+            // The journal of the last checkpoint allows to continue later
+            if !c.resume {
+                remove_file(&output)?;
+            }
+
+            return Err(Error::Cancelled);
+        }
+
+        let bytes_1 = f_in1.read(&mut buf_in1)?;
+        let bytes_2 = f_in2.read(&mut buf_in2)?;
+
+        if bytes_1 != bytes_2 {
+            drop(f_out);
+            remove_file(&output)?;
+
+            return Err(Error::ShareMismatch([f_in1_name, f_in2_name]));
+        }
+
+        if bytes_1 == 0 {
+            break;
+        }
+
+        let chunk = offset / chunk_size as u64;
+        let range = offset..offset + bytes_1 as u64;
+        offset += bytes_1 as u64;
+        trace!("Decrypting chunk {chunk} at bytes {range:?}");
+
+        let len = unpadded_len(&meta, range.start, bytes_1);
+
+        if len == 0 {
+            continue;
+        }
+
+        let broken = [(&f_in1, &buf_in1), (&f_in2, &buf_in2)]
+            .into_iter()
+            .find(|(f, buf)| !f.chunk_intact(chunk, &buf[..bytes_1]));
+
+        if let Some((f, _)) = broken {
+            debug!("Chunk {chunk} of {:?} is damaged", f.path());
+
+            match on_damage {
+                OnDamage::Fail => {
+                    drop(f_out);
+                    remove_file(&output)?;
+
+                    return Err(Error::IntegrityFailure {
+                        path: f.path().to_owned(),
+                        range,
+                    });
+                }
+                OnDamage::Skip => {
+                    push_range(&mut damaged, range);
+                    continue;
+                }
+                OnDamage::Zero => {
+                    push_range(&mut damaged, range);
+                    buf_out[..len].fill(0);
+                    write(&mut f_out, &output, &buf_out[..len])?;
+                    continue;
+                }
+            }
+        }
+
+        decrypt(&buf_in1[..len], &buf_in2[..len], &mut buf_out[..len])?;
+
+        let plain = match &mut decompressor {
+            Some(d) => d.decompress(&buf_out[..len]).ok(),
+            None => Some(buf_out[..len].to_vec()),
+        };
+
+        // Data that can't be decompressed doesn't match the digest either
+        let Some(plain) = plain else {
+            drop(f_out);
+            remove_file(&output)?;
+
+            return Err(Error::DigestMismatch);
+        };
+
+        hasher.update(&plain);
+        write(&mut f_out, &output, &plain)?;
+
+        if c.resume && offset - checkpoint >= CHECKPOINT_INTERVAL {
+            checkpoint = offset;
+
+            f_out
+                .sync_data()
+                .map_err(|error| io_error(&output, IoOperation::Write, error))?;
+
+            let journal = Journal {
+                set_id,
+                offset,
+                // The output file is checked against the input files instead
+                digests: Vec::new(),
+            };
+            journal.write(&output)?;
+            debug!("Saved progress at byte {offset}");
+        }
+    }
+
+    if let Some(d) = decompressor {
+        let Ok(plain) = d.finish() else {
+            drop(f_out);
+            remove_file(&output)?;
+
+            return Err(Error::DigestMismatch);
+        };
+
+        hasher.update(&plain);
+        write(&mut f_out, &output, &plain)?;
+    }
+
+    if let (Some(digest), true) = (meta.digest, damaged.is_empty()) {
+        if hasher.finalize()[..] != digest {
+            drop(f_out);
+            remove_file(&output)?;
+
+            return Err(Error::DigestMismatch);
+        }
+    }
+
+    // The decrypted data is fine, so the file is kept without its attributes
+    if c.preserve {
+        if let Err(e) = meta.attributes.apply(&output, &f_out) {
+            warn!("Could not restore the file attributes, keeping the decrypted file: {e}");
+        }
+    }
+
+    let length = f_out
+        .stream_position()
+        .map_err(|error| io_error(&output, IoOperation::Write, error))?;
+
+    let summary = DecryptSummary {
+        output,
+        length,
+        repaired_blocks: f_in1.repaired_blocks() + f_in2.repaired_blocks(),
+        damaged,
+    };
+
+    if c.resume {
+        Journal::remove(&summary.output)?;
+    }
+
+    if c.rm && summary.damaged.is_empty() {
+        remove_share(&f_in1_name)?;
+        remove_share(&f_in2_name)?;
+    }
+
+    info!(
+        "Decrypted {} bytes to {:?} in {:?}, {} repaired block(s), {} damaged range(s)",
+        summary.length,
+        summary.output,
+        start.elapsed(),
+        summary.repaired_blocks,
+        summary.damaged.len()
+    );
+
+    Ok(summary)
 }
 
-/// Encrypts a file using the options wrapped in a [`Config`].
+/// Decrypts `len` bytes of the original file, starting at `offset`, and
+/// writes them to `writer`. Returns the number of bytes written, which is
+/// less than `len` if the range extends beyond the end of the file, and 0 if
+/// it starts at or after the end.
+///
+/// Only the chunks of the input files that contain the range are read and
+/// checked against the hash trees. The whole file can't be checked against
+/// the stored digest, though.
 ///
 /// ## Errors
 ///
-/// Returns an [`Error::IoError`](Error::IoError) if any of the I/O operations fail.
+/// Returns an [`Error`] if:
+///  - any of the I/O operations fail ([`IoError`](Error::IoError))
+///  - the two input files differ in length or don't belong together
+///    ([`ShareMismatch`](Error::ShareMismatch))
+///  - the input files contain compressed data, which can't be decrypted in
+///    parts ([`InvalidInput`](Error::InvalidInput))
+///  - the range contains damaged data, an input file contains more damaged
+///    blocks than the error correction can repair or a damaged hash tree
+///    ([`IntegrityFailure`](Error::IntegrityFailure))
 ///
 /// ## Example
 /// ```no_run
-/// use onetime_cli::Config;
-/// use onetime_cli::encrypt_file;
-/// use onetime_cli::Error;
+/// use std::path::Path;
+///
+/// use onetime_cli::decrypt_range;
 ///
 /// # fn main() -> Result<(), onetime_cli::Error> {
-/// let c = Config::new("secret.txt");
+/// let share1 = Path::new("archive.tar.otp.0");
+/// let share2 = Path::new("archive.tar.otp.1");
 ///
-/// let res = encrypt_file(&c)?;
+/// // The first 512 bytes, the header of the first file in the archive
+/// let mut header = Vec::new();
+/// decrypt_range(share1, share2, 0, 512, &mut header)?;
 /// # Ok(())
 /// # }
 /// ```
-pub fn encrypt_file(c: &Config) -> Result<(), Error> {
-    let mut f_in = open_file(&c.file, Mode::Open)?;
+pub fn decrypt_range<W: Write>(
+    share1: &Path,
+    share2: &Path,
+    offset: u64,
+    len: u64,
+    writer: &mut W,
+) -> Result<u64, Error> {
+    let mut f_in1 = ShareReader::open(share1)?;
+    let mut f_in2 = ShareReader::open(share2)?;
+    let meta = check_pair(&f_in1, &f_in2)?;
 
-    let f_out1_name = extend_extension(&c.file, &c.suffix1);
-    let f_out2_name = extend_extension(&c.file, &c.suffix2);
+    if f_in1
+        .header
+        .as_ref()
+        .is_some_and(|h| h.compression.is_some())
+    {
+        return Err(Error::InvalidInput(
+            "Byte ranges of compressed data can't be decrypted on their own".to_string(),
+        ));
+    }
 
-    let mut f_out1 = open_file(&f_out1_name, Mode::Create)?;
-    let mut f_out2 = open_file(&f_out2_name, Mode::Create)?;
+    let end = offset
+        .saturating_add(len)
+        .min(meta.length.unwrap_or(f_in1.data_len()));
 
-    let mut buf_in = vec![0u8; c.buffer as usize];
-    let mut buf_out1 = vec![0u8; c.buffer as usize];
-    let mut buf_out2 = vec![0u8; c.buffer as usize];
+    if offset >= end {
+        return Ok(0);
+    }
 
-    loop {
-        let bytes = read(&mut f_in, &mut buf_in)?;
+    // Read whole chunks of the hash trees, so they can be checked
+    let chunk_size = f_in1.chunk_size().unwrap_or(RANGE_BUFFER) as u64;
+    let mut pos = offset - offset % chunk_size;
+
+    f_in1.skip(pos)?;
+    f_in2.skip(pos)?;
+
+    let mut buf_in1 = vec![0u8; chunk_size as usize];
+    let mut buf_in2 = vec![0u8; chunk_size as usize];
+    let mut buf_out = vec![0u8; chunk_size as usize];
+    let mut written = 0;
+
+    while pos < end {
+        let bytes = f_in1.read(&mut buf_in1)?;
+
+        if bytes != f_in2.read(&mut buf_in2)? {
+            return Err(Error::ShareMismatch([share1.to_owned(), share2.to_owned()]));
+        }
 
         if bytes == 0 {
             break;
         }
 
-        encrypt(
-            &buf_in[..bytes],
-            &mut buf_out1[..bytes],
-            &mut buf_out2[..bytes],
+        let chunk = pos / chunk_size;
+
+        for (f, buf) in [(&f_in1, &buf_in1), (&f_in2, &buf_in2)] {
+            if !f.chunk_intact(chunk, &buf[..bytes]) {
+                return Err(Error::IntegrityFailure {
+                    path: f.path().to_owned(),
+                    range: pos..pos + bytes as u64,
+                });
+            }
+        }
+
+        // Part of the chunk within the range
+        let from = (offset.max(pos) - pos) as usize;
+        let to = (end - pos).min(bytes as u64) as usize;
+
+        decrypt(
+            &buf_in1[from..to],
+            &buf_in2[from..to],
+            &mut buf_out[from..to],
         )?;
 
-        write(&mut f_out1, &buf_out1[..bytes])?;
-        write(&mut f_out2, &buf_out2[..bytes])?;
-    }
+        writer.write_all(&buf_out[from..to]).map_err(|error| {
+            Error::IoError(IoError {
+                path: None,
+                operation: IoOperation::Write,
+                error,
+            })
+        })?;
 
-    if c.rm {
-        remove_file(&c.file)?;
+        written += (to - from) as u64;
+        pos += bytes as u64;
     }
 
-    Ok(())
+    Ok(written)
 }
 
-/// Decrypts a file using the options wrapped in a [`Config`].
+/// Checks the input files of [`decrypt_file`] without writing the
+/// decrypted file.
+///
+/// Each input file is checked against its hash tree, which tells exactly
+/// which byte ranges are damaged. If the input files contain a digest of
+/// the original file, the decrypted data is checked against it as well.
 ///
 /// ## Errors
 ///
 /// Returns an [`Error`] if:
 ///  - any of the I/O operations fail ([`IoError`](Error::IoError))
-///  - the two input files differ in length ([`InvalidInput`](Error::InvalidInput))
+///  - the two input files differ in length or don't belong together
+///    ([`ShareMismatch`](Error::ShareMismatch))
+///  - an input file contains more damaged blocks than the error correction
+///    can repair or a damaged hash tree ([`IntegrityFailure`](Error::IntegrityFailure))
+///  - the run is cancelled through [`Config::cancel`] ([`Cancelled`](Error::Cancelled))
+///
+/// Other damaged data is not an error, but reported in the [`VerifySummary`].
 ///
 /// ## Example
 /// ```no_run
 /// use onetime_cli::Config;
-/// use onetime_cli::decrypt_file;
-/// use onetime_cli::Error;
+/// use onetime_cli::verify_file;
 ///
 /// # fn main() -> Result<(), onetime_cli::Error> {
 /// let c = Config::new("secret.txt");
 ///
-/// let res = decrypt_file(&c)?;
+/// let summary = verify_file(&c)?;
+/// assert!(summary.is_ok());
 /// # Ok(())
 /// # }
 /// ```
-pub fn decrypt_file(c: &Config) -> Result<(), Error> {
-    let f_in1_name = extend_extension(&c.file, &c.suffix1);
-    let f_in2_name = extend_extension(&c.file, &c.suffix2);
+pub fn verify_file(c: &Config) -> Result<VerifySummary, Error> {
+    c.validate()?;
+
+    let [f_in1_name, f_in2_name] = c.share_paths()?;
 
-    let mut f_in1 = open_file(&f_in1_name, Mode::Open)?;
-    let mut f_in2 = open_file(&f_in2_name, Mode::Open)?;
-    let mut f_out = open_file(&c.file, Mode::Create)?;
+    let mut f_in1 = ShareReader::open(&f_in1_name)?;
+    let mut f_in2 = ShareReader::open(&f_in2_name)?;
+    let meta = check_pair(&f_in1, &f_in2)?;
+
+    let start = Instant::now();
+    let chunk_size = f_in1.chunk_size().unwrap_or(c.buffer);
+
+    debug!(
+        "Verifying {f_in1_name:?} and {f_in2_name:?} with a buffer of {} bytes",
+        c.buffer
+    );
 
     let mut buf_in1 = vec![0u8; c.buffer as usize];
     let mut buf_in2 = vec![0u8; c.buffer as usize];
     let mut buf_out = vec![0u8; c.buffer as usize];
 
+    let mut hasher = Sha256::new();
+    let mut tree1 = TreeBuilder::new(chunk_size);
+    let mut tree2 = TreeBuilder::new(chunk_size);
+
+    let compression = f_in1.header.as_ref().and_then(|h| h.compression);
+    let mut decompressor = compression.map(Decompressor::new);
+
+    // Set if the decrypted data can't be decompressed
+    let mut broken = false;
+    let mut offset = 0u64;
+
     loop {
-        let bytes_1 = read(&mut f_in1, &mut buf_in1)?;
-        let bytes_2 = read(&mut f_in2, &mut buf_in2)?;
+        if c.is_cancelled() {
+            return Err(Error::Cancelled);
+        }
 
-        if bytes_1 != bytes_2 {
-            return Err(Error::InvalidInput(
-                "The two input files differ in size!".to_string(),
-            ));
+        let bytes = f_in1.read(&mut buf_in1)?;
+
+        if bytes != f_in2.read(&mut buf_in2)? {
+            return Err(Error::ShareMismatch([f_in1_name, f_in2_name]));
         }
 
-        if bytes_1 == 0 {
+        if bytes == 0 {
             break;
         }
 
-        decrypt(
-            &buf_in1[..bytes_1],
-            &buf_in2[..bytes_1],
-            &mut buf_out[..bytes_1],
-        )?;
+        tree1.update(&buf_in1[..bytes]);
+        tree2.update(&buf_in2[..bytes]);
+
+        let len = unpadded_len(&meta, offset, bytes);
+        offset += bytes as u64;
 
-        write(&mut f_out, &buf_out[..bytes_1])?;
+        decrypt(&buf_in1[..len], &buf_in2[..len], &mut buf_out[..len])?;
+
+        match &mut decompressor {
+            Some(d) => match d.decompress(&buf_out[..len]) {
+                Ok(plain) => hasher.update(&plain),
+                Err(_) => broken = true,
+            },
+            None => hasher.update(&buf_out[..len]),
+        }
     }
 
-    if c.rm {
-        remove_file(&f_in1_name)?;
-        remove_file(&f_in2_name)?;
+    if let Some(d) = decompressor {
+        match d.finish() {
+            Ok(plain) => hasher.update(&plain),
+            Err(_) => broken = true,
+        }
+    }
+
+    let damaged = |f: &ShareReader, actual: HashTree| {
+        f.trailer.hash_tree.as_ref().map(|stored| {
+            let mut ranges = Vec::new();
+
+            for chunk in stored.damaged_chunks(&actual) {
+                push_range(&mut ranges, stored.chunk_range(chunk, f.data_len()));
+            }
+
+            ranges
+        })
+    };
+
+    let summary = VerifySummary {
+        damaged1: damaged(&f_in1, tree1.finish()),
+        damaged2: damaged(&f_in2, tree2.finish()),
+        digest_matches: meta.digest.map(|d| !broken && hasher.finalize()[..] == d),
+        repaired_blocks: f_in1.repaired_blocks() + f_in2.repaired_blocks(),
+        length: offset,
+    };
+
+    info!("Verified {offset} bytes in {:?}", start.elapsed());
+
+    Ok(summary)
+}
+
+/// Stops a cancelled [`encrypt_file`]. The partial output files are removed,
+/// unless they can be resumed from the journal of the last checkpoint.
+fn cancel_encrypt(c: &Config, shares: [ShareWriter; 2], paths: [&Path; 2]) -> Error {
+    info!("Cancelled encrypting {:?}", c.file);
+    drop(shares);
+
+    if !c.resume {
+        for path in paths {
+            if let Err(e) = remove_share(path) {
+                return e;
+            }
+        }
+    }
+
+    Error::Cancelled
+}
+
+/// Encrypts `data` into both shares, in pieces of the length of the buffers.
+fn write_encrypted(
+    data: &[u8],
+    shares: [&mut ShareWriter; 2],
+    bufs: &mut [Vec<u8>; 2],
+) -> Result<(), Error> {
+    let [f_out1, f_out2] = shares;
+    let [buf_out1, buf_out2] = bufs;
+
+    for piece in data.chunks(buf_out1.len()) {
+        let len = piece.len();
+        encrypt(piece, &mut buf_out1[..len], &mut buf_out2[..len])?;
+
+        f_out1.write(&buf_out1[..len])?;
+        f_out2.write(&buf_out2[..len])?;
     }
 
     Ok(())
 }
+
+/// Number of the `bytes` bytes at `offset` of the encrypted data that come
+/// before the padding.
+fn unpadded_len(meta: &Metadata, offset: u64, bytes: usize) -> usize {
+    match meta.length {
+        Some(length) => length.saturating_sub(offset).min(bytes as u64) as usize,
+        None => bytes,
+    }
+}
+
+/// Reader of the plaintext and readers of the encrypted data of both shares
+type PrefixReaders<'a> = [&'a mut dyn FnMut(&mut [u8]) -> Result<usize, Error>; 3];
+
+/// Checks that the first `len` bytes of the plaintext are the decryption of
+/// the first `len` bytes of the encrypted data, and adds them to `hasher`.
+/// Returns `false` if they differ.
+///
+/// This is how resuming detects files changed since the interrupted run.
+/// A journal with a digest of the plaintext would allow to check guesses of
+/// it with just one share.
+fn check_prefix(
+    readers: PrefixReaders,
+    len: u64,
+    buffer: u32,
+    hasher: &mut Sha256,
+) -> Result<bool, Error> {
+    let [read_plain, read1, read2] = readers;
+    let mut bufs = [(); 4].map(|_| vec![0u8; buffer as usize]);
+    let [plain, buf1, buf2, decrypted] = &mut bufs;
+    let mut rest = len;
+
+    while rest > 0 {
+        let max = plain.len().min(rest.try_into().unwrap_or(usize::MAX));
+
+        let bytes = read_plain(&mut plain[..max])?;
+        if bytes < max || read1(&mut buf1[..max])? < max || read2(&mut buf2[..max])? < max {
+            return Ok(false);
+        }
+
+        decrypt(&buf1[..max], &buf2[..max], &mut decrypted[..max])?;
+        if plain[..max] != decrypted[..max] {
+            return Ok(false);
+        }
+
+        hasher.update(&plain[..max]);
+        rest -= max as u64;
+    }
+
+    Ok(true)
+}
+
+/// Opens the share at `path`, which starts with `header`, and seeks to its
+/// encrypted data.
+fn open_data(path: &Path, header: &Header) -> Result<File, Error> {
+    let mut file = open_file(path, Mode::Open)?;
+
+    file.seek(SeekFrom::Start(header.to_bytes().len() as u64))
+        .map_err(|error| io_error(path, IoOperation::Read, error))?;
+
+    Ok(file)
+}
+
+/// Opens the partially decrypted file at `path` and checks its first `len`
+/// bytes against the input files, see [`check_prefix`]. The rest of the file
+/// is cut off.
+fn resume_output(
+    path: &Path,
+    len: u64,
+    shares: [&mut ShareReader; 2],
+    hasher: &mut Sha256,
+) -> Result<File, Error> {
+    let io_err = |operation| move |error| io_error(path, operation, error);
+
+    let mut file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(path)
+        .map_err(io_err(IoOperation::Open))?;
+
+    let [share1, share2] = shares;
+    let intact = check_prefix(
+        [
+            &mut |buf| read_full(&mut file, buf, path),
+            &mut |buf| share1.read(buf),
+            &mut |buf| share2.read(buf),
+        ],
+        len,
+        RANGE_BUFFER,
+        hasher,
+    )?;
+
+    if !intact {
+        return Err(Error::InvalidInput(format!(
+            "{path:?} changed since the interrupted run, run again without resuming"
+        )));
+    }
+
+    file.set_len(len)
+        .and_then(|_| file.seek(SeekFrom::End(0)))
+        .map_err(io_err(IoOperation::Write))?;
+
+    Ok(file)
+}
+
+/// Returns the path [`decrypt_file`] writes to. See [`Config::output`].
+fn output_path(c: &Config, meta: &Metadata) -> Result<PathBuf, Error> {
+    if let Some(output) = &c.output {
+        return Ok(output.clone());
+    }
+
+    if c.shares.is_none() {
+        return Ok(c.file.clone());
+    }
+
+    let name = meta.name.as_ref().ok_or_else(|| {
+        Error::InvalidInput(
+            "The input files don't contain the original file name, the output file must be specified"
+                .to_string(),
+        )
+    })?;
+
+    // Don't allow the stored name to point anywhere but the current directory
+    let path = Path::new(name);
+    match path.components().collect::<Vec<_>>()[..] {
+        [Component::Normal(_)] => Ok(path.to_owned()),
+        _ => Err(Error::InvalidInput(format!(
+            "The stored file name {name:?} is not a valid file name"
+        ))),
+    }
+}
+
+/// Appends `range` to `ranges`, merging it with the last range if they are adjacent.
+fn push_range(ranges: &mut Vec<Range<u64>>, range: Range<u64>) {
+    match ranges.last_mut() {
+        Some(last) if last.end == range.start => last.end = range.end,
+        _ => ranges.push(range),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::fs;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    use crate::share::DIGEST_LEN;
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("onetime-cli-test-{name}"));
+        fs::create_dir_all(&dir).unwrap();
+
+        dir
+    }
+
+    fn test_config(file: &Path) -> Config {
+        let mut c = Config::new(file.to_str().unwrap());
+        c.buffer = 256;
+        c.resume = true;
+
+        c
+    }
+
+    fn digest(data: &[u8]) -> [u8; DIGEST_LEN] {
+        Sha256::digest(data).into()
+    }
+
+    #[test]
+    fn test_resume_encrypt() {
+        let dir = test_dir("resume-encrypt");
+        let file = dir.join("file");
+        let data: Vec<u8> = (0..10_000).map(|i| (i % 251) as u8).collect();
+        fs::write(&file, &data).unwrap();
+
+        let c = test_config(&file);
+        let [share1, share2] = encrypt_file(&c).unwrap().shares;
+        assert!(!Journal::path(&share1).exists());
+
+        // Interrupt the run after 4000 bytes, in the middle of a write
+        let offset = 4000;
+        let mut digests = Vec::new();
+        let mut set_id = [0; 16];
+
+        for share in [&share1, &share2] {
+            let bytes = fs::read(share).unwrap();
+            let (header, start) = Header::read_from(&mut &bytes[..], share).unwrap().unwrap();
+            let start = start as usize;
+
+            set_id = header.set_id;
+            digests.push(digest(&bytes[start..start + offset]));
+            fs::write(share, &bytes[..start + offset + 100]).unwrap();
+        }
+
+        let journal = Journal {
+            set_id,
+            offset: offset as u64,
+            digests,
+        };
+        journal.write(&share1).unwrap();
+
+        // The journal doesn't reveal the digest of the data
+        let stored = fs::read(Journal::path(&share1)).unwrap();
+        assert!(!stored
+            .windows(DIGEST_LEN)
+            .any(|w| w == digest(&data[..offset])));
+
+        let shares = encrypt_file(&c).unwrap().shares;
+        assert_eq!(shares, [share1.clone(), share2.clone()]);
+        assert!(!Journal::path(&share1).exists());
+
+        fs::remove_file(&file).unwrap();
+        decrypt_file(&c).unwrap();
+        assert_eq!(fs::read(&file).unwrap(), data);
+
+        // The input file changed since the interrupted run
+        journal.write(&share1).unwrap();
+        for share in [&share1, &share2] {
+            let bytes = fs::read(share).unwrap();
+            fs::write(share, &bytes[..bytes.len() - 1000]).unwrap();
+        }
+        fs::write(&file, [&data[..100], &[0; 9900]].concat()).unwrap();
+
+        assert!(encrypt_file(&c).is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_resume_decrypt() {
+        let dir = test_dir("resume-decrypt");
+        let file = dir.join("file");
+        let data: Vec<u8> = (0..10_000).map(|i| (i % 251) as u8).collect();
+        fs::write(&file, &data).unwrap();
+
+        let c = test_config(&file);
+        let [share1, _] = encrypt_file(&c).unwrap().shares;
+        let bytes = fs::read(&share1).unwrap();
+        let (header, _) = Header::read_from(&mut &bytes[..], &share1)
+            .unwrap()
+            .unwrap();
+
+        // Interrupt the run after 16 chunks, with a partial chunk written
+        let offset = 16 * 256;
+        fs::write(&file, [&data[..offset], &[0; 100]].concat()).unwrap();
+
+        let journal = Journal {
+            set_id: header.set_id,
+            offset: offset as u64,
+            digests: Vec::new(),
+        };
+        journal.write(&file).unwrap();
+
+        decrypt_file(&c).unwrap();
+        assert_eq!(fs::read(&file).unwrap(), data);
+        assert!(!Journal::path(&file).exists());
+
+        // The output file changed since the interrupted run
+        fs::write(&file, [1; 5000]).unwrap();
+        journal.write(&file).unwrap();
+
+        assert!(decrypt_file(&c).is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    #[cfg(all(unix, feature = "xattr"))]
+    fn test_decrypt_keeps_file_without_attributes() {
+        let dir = test_dir("attributes");
+        let file = dir.join("file");
+        let data = b"secret data";
+
+        let c = Config::new(file.to_str().unwrap());
+        let [path1, path2] = c.share_paths().unwrap();
+        let (header1, header2) = Header::new_pair(&c);
+        let mut share1 = ShareWriter::create(&path1, &header1, c.buffer, c.format, None).unwrap();
+        let mut share2 = ShareWriter::create(&path2, &header2, c.buffer, c.format, None).unwrap();
+
+        let (mut out1, mut out2) = ([0; 11], [0; 11]);
+        encrypt(data, &mut out1, &mut out2).unwrap();
+        share1.write(&out1).unwrap();
+        share2.write(&out2).unwrap();
+
+        // Extended attributes need a namespace like `user.`, so this can't be restored
+        let meta = Metadata {
+            digest: Some(digest(data)),
+            attributes: FileAttributes {
+                xattrs: vec![(b"invalid".to_vec(), b"value".to_vec())],
+                ..FileAttributes::default()
+            },
+            ..Metadata::default()
+        };
+        let (meta1, meta2) = meta.split().unwrap();
+        share1.finish(meta1).unwrap();
+        share2.finish(meta2).unwrap();
+
+        let summary = decrypt_file(&c).unwrap();
+        assert_eq!(fs::read(&summary.output).unwrap(), data);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_cancel() {
+        let dir = test_dir("cancel");
+        let file = dir.join("file");
+        fs::write(&file, [7u8; 1000]).unwrap();
+
+        let cancel = Arc::new(AtomicBool::new(false));
+        let c = Config {
+            cancel: Some(Arc::clone(&cancel)),
+            ..Config::new(file.to_str().unwrap())
+        };
+        let [share1, share2] = c.share_paths().unwrap();
+
+        cancel.store(true, Ordering::Relaxed);
+        assert!(matches!(encrypt_file(&c), Err(Error::Cancelled)));
+        assert!(!share1.exists() && !share2.exists());
+
+        cancel.store(false, Ordering::Relaxed);
+        encrypt_file(&c).unwrap();
+        fs::remove_file(&file).unwrap();
+
+        cancel.store(true, Ordering::Relaxed);
+        assert!(matches!(verify_file(&c), Err(Error::Cancelled)));
+        assert!(matches!(decrypt_file(&c), Err(Error::Cancelled)));
+        assert!(!file.exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_shares_differ_in_length() {
+        let dir = test_dir("differ-in-length");
+        let [share1, share2] = [dir.join("file.otp.0"), dir.join("file.otp.1")];
+
+        // Shares without a header, which are read as raw data
+        fs::write(&share1, [1u8; 10]).unwrap();
+        fs::write(&share2, [2u8; 12]).unwrap();
+
+        let c = Config {
+            output: Some(dir.join("file")),
+            ..Config::new_with_shares(share1.to_str().unwrap(), share2.to_str().unwrap())
+        };
+
+        assert!(matches!(verify_file(&c), Err(Error::ShareMismatch(_))));
+        assert!(matches!(decrypt_file(&c), Err(Error::ShareMismatch(_))));
+        assert!(!dir.join("file").exists());
+        assert!(matches!(
+            decrypt_range(&share1, &share2, 0, 100, &mut Vec::new()),
+            Err(Error::ShareMismatch(_))
+        ));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_damaged_chunk_size() {
+        let dir = test_dir("damaged-chunk-size");
+        let file = dir.join("file");
+        fs::write(&file, [7u8; 1000]).unwrap();
+
+        let c = Config {
+            buffer: 256,
+            ..Config::new(file.to_str().unwrap())
+        };
+        let [_, share2] = encrypt_file(&c).unwrap().shares;
+
+        // The hash tree is the last trailer field, before the fields length
+        // and the trailer magic: chunk size, root and 4 leaves
+        let mut bytes = fs::read(&share2).unwrap();
+        let chunk_size = bytes.len() - 16 - (4 + DIGEST_LEN * 5);
+        assert_eq!(bytes[chunk_size..chunk_size + 4], 256u32.to_le_bytes());
+
+        bytes[chunk_size + 3] = 0x7f;
+        fs::write(&share2, bytes).unwrap();
+
+        assert!(matches!(
+            decrypt_file(&c),
+            Err(Error::IntegrityFailure { path, .. }) if path == share2
+        ));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_decrypt_range_at_end() {
+        let dir = test_dir("decrypt-range-at-end");
+        let file = dir.join("file");
+        fs::write(&file, [7u8; 36]).unwrap();
+
+        let [share1, share2] = encrypt_file(&test_config(&file)).unwrap().shares;
+
+        let mut out = Vec::new();
+        assert_eq!(
+            decrypt_range(&share1, &share2, 30, 100, &mut out).unwrap(),
+            6
+        );
+        assert_eq!(out, [7; 6]);
+
+        // Starting exactly at the end of the file
+        let mut out = Vec::new();
+        assert_eq!(
+            decrypt_range(&share1, &share2, 36, 10, &mut out).unwrap(),
+            0
+        );
+        assert!(out.is_empty());
+
+        // Starting past the end of the file
+        let mut out = Vec::new();
+        assert_eq!(
+            decrypt_range(&share1, &share2, 100, 100, &mut out).unwrap(),
+            0
+        );
+        assert!(out.is_empty());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
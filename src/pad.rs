@@ -0,0 +1,78 @@
+//! Padding that hides the length of the encrypted data.
+//!
+//! The padding is appended to the data before it is encrypted, so the
+//! encrypted files are longer than the data. The true length is stored in
+//! the split [`Metadata`](crate::share::Metadata), and
+//! [`decrypt_file`](crate::decrypt_file) cuts the padding off again.
+
+use rand::Rng;
+
+/// Smallest range of random padding, so short files get a useful amount
+const RANDOM_MIN_RANGE: u64 = 4096;
+
+/// Ways to pad the data. See [`Config::padding`](crate::Config::padding).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Padding {
+    /// Add a random number of bytes, up to an eighth of the length of the
+    /// data, but at least up to 4 KiB
+    Random,
+
+    /// Round the length up to the next power of two
+    PowerOfTwo,
+
+    /// Round the length up to a multiple of the given number of bytes
+    Block(u64),
+}
+
+impl Padding {
+    /// Returns the length of `len` bytes of data after padding.
+    pub fn padded_len(self, len: u64) -> u64 {
+        match self {
+            Padding::Random => {
+                let range = (len / 8).max(RANDOM_MIN_RANGE);
+                len.saturating_add(rand::thread_rng().gen_range(0..=range))
+            }
+            Padding::PowerOfTwo => len.checked_next_power_of_two().unwrap_or(len),
+            Padding::Block(size) => match len % size.max(1) {
+                0 => len,
+                rest => len.saturating_add(size - rest),
+            },
+        }
+    }
+
+    /// Checks if the padding can be applied, see [`Padding::Block`].
+    pub fn check(self) -> Result<(), String> {
+        match self {
+            Padding::Block(0) => Err("the block size must not be 0".to_string()),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_padded_len() {
+        assert_eq!(Padding::PowerOfTwo.padded_len(1000), 1024);
+        assert_eq!(Padding::PowerOfTwo.padded_len(1024), 1024);
+        assert_eq!(Padding::PowerOfTwo.padded_len(0), 1);
+
+        assert_eq!(Padding::Block(4096).padded_len(1), 4096);
+        assert_eq!(Padding::Block(4096).padded_len(8192), 8192);
+        assert_eq!(Padding::Block(4096).padded_len(0), 0);
+
+        for len in [0, 100, 1 << 20] {
+            let padded = Padding::Random.padded_len(len);
+            assert!(padded >= len);
+            assert!(padded <= len + (len / 8).max(RANDOM_MIN_RANGE));
+        }
+    }
+
+    #[test]
+    fn test_check() {
+        assert!(Padding::Block(0).check().is_err());
+        assert!(Padding::Block(1).check().is_ok());
+    }
+}
@@ -0,0 +1,413 @@
+//! Printable paper backups of shares.
+//!
+//! The binary share is encoded as base32 ([RFC 4648], without padding) in
+//! numbered lines of 40 characters. Every line ends with a checksum over its
+//! number and its data, so a typo can be traced to its line when the backup
+//! is typed in again. Line 0 holds the size of the share in bytes, so
+//! missing lines are noticed even at the end of the backup:
+//!
+//! ```text
+//!   0: 60  W6RP
+//!   1: MFRGG ZDFMZ TWQ2L KNRWY 3LON5 XXA4T TON2X  K7QA
+//! ```
+//!
+//! [RFC 4648]: https://www.rfc-editor.org/rfc/rfc4648
+
+use std::path::Path;
+
+use sha2::{Digest, Sha256};
+
+use crate::share::{read_binary, Header};
+use crate::Error;
+
+const ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+/// Number of share bytes per line, 40 base32 characters
+const BYTES_PER_LINE: usize = 25;
+
+const GROUP_LEN: usize = 5;
+const CHECKSUM_LEN: usize = 4;
+
+const INSTRUCTIONS: &str = "\
+This is one of the two encrypted files (shares) of a file encrypted with
+onetime-cli. On its own, it reveals nothing about the file. Both shares
+are needed to recover it.
+
+To recover the file:
+  1. Type the numbered lines below, starting with line 0, into a text
+     file, e.g. share.txt. Upper or lower case and extra spaces don't
+     matter.
+  2. Run: onetime-cli import-paper share.txt <share file>
+     Lines with typos are reported by their numbers.
+  3. Do the same with the backup of the other share, then run:
+     onetime-cli decrypt <share file 1> <share file 2>";
+
+fn encode_base32(data: &[u8]) -> String {
+    let mut out = String::new();
+    let mut buffer = 0u32;
+    let mut bits = 0;
+
+    for &byte in data {
+        buffer = buffer << 8 | byte as u32;
+        bits += 8;
+
+        while bits >= 5 {
+            bits -= 5;
+            out.push(ALPHABET[(buffer >> bits) as usize & 31] as char);
+        }
+    }
+
+    if bits > 0 {
+        out.push(ALPHABET[(buffer << (5 - bits)) as usize & 31] as char);
+    }
+
+    out
+}
+
+/// Decodes base32 without padding. Characters that are easily confused
+/// with letters of the alphabet are accepted in their place.
+fn decode_base32(text: &str) -> Option<Vec<u8>> {
+    let mut out = Vec::new();
+    let mut buffer = 0u32;
+    let mut bits = 0;
+
+    for c in text.chars() {
+        let c = match c.to_ascii_uppercase() {
+            '0' => 'O',
+            '1' => 'I',
+            '8' => 'B',
+            c => c,
+        };
+        let value = ALPHABET.iter().position(|&a| a as char == c)?;
+
+        buffer = buffer << 5 | value as u32;
+        bits += 5;
+
+        if bits >= 8 {
+            bits -= 8;
+            out.push((buffer >> bits) as u8);
+        }
+    }
+
+    // Leftover bits must be padding
+    match buffer & ((1 << bits) - 1) {
+        0 => Some(out),
+        _ => None,
+    }
+}
+
+fn line_checksum(number: usize, data: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update((number as u64).to_le_bytes());
+    hasher.update(data);
+
+    encode_base32(&hasher.finalize())[..CHECKSUM_LEN].to_string()
+}
+
+/// Creates the paper backup of the share at `path` as plain text.
+///
+/// Shares in text formats are decoded first, so the backup always
+/// contains the binary share.
+pub fn export_paper(path: &Path) -> Result<String, Error> {
+    let share = read_binary(path)?;
+
+    let (index, set_id) = match Header::read_from(&mut &share[..], path)? {
+        Some((header, _)) => (
+            header.index.to_string(),
+            header.set_id.iter().map(|b| format!("{b:02x}")).collect(),
+        ),
+        None => ("unknown".to_string(), "unknown".to_string()),
+    };
+
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let lines = share.chunks(BYTES_PER_LINE).count();
+
+    let mut doc = format!(
+        "ONETIME-CLI PAPER BACKUP\n\
+        ========================\n\n\
+        Share:  {name}\n\
+        Index:  {index}\n\
+        Set ID: {set_id}\n\
+        Size:   {} bytes in {lines} lines\n\n\
+        {INSTRUCTIONS}\n\n",
+        share.len()
+    );
+
+    let width = lines.to_string().len().max(3);
+    let size = share.len() as u64;
+
+    doc.push_str(&format!(
+        "{:>width$}: {size}  {}\n",
+        0,
+        line_checksum(0, &size.to_le_bytes())
+    ));
+
+    for (i, data) in share.chunks(BYTES_PER_LINE).enumerate() {
+        let encoded = encode_base32(data);
+        let groups: Vec<&str> = encoded
+            .as_bytes()
+            .chunks(GROUP_LEN)
+            .map(|g| std::str::from_utf8(g).unwrap())
+            .collect();
+
+        doc.push_str(&format!(
+            "{:>width$}: {}  {}\n",
+            i + 1,
+            groups.join(" "),
+            line_checksum(i + 1, data)
+        ));
+    }
+
+    Ok(doc)
+}
+
+/// Rebuilds the binary share from the typed-in lines of a paper backup.
+///
+/// Only lines starting with a line number are read, and lines typed in twice
+/// only once. Returns an [`InvalidInput`](Error::InvalidInput) error naming the
+/// lines that don't match their checksums, occur twice with different data or
+/// are missing, according to the share size in line 0.
+pub fn import_paper(text: &str) -> Result<Vec<u8>, Error> {
+    let mut lines: Vec<(usize, Vec<u8>)> = Vec::new();
+    let mut sizes = Vec::new();
+    let mut typos = Vec::new();
+
+    for line in text.lines() {
+        let Some((number, rest)) = line.split_once(':') else {
+            continue;
+        };
+        let Ok(number) = number.trim().parse::<usize>() else {
+            continue;
+        };
+
+        let mut groups: Vec<&str> = rest.split_whitespace().collect();
+        let checksum = groups.pop().unwrap_or_default();
+
+        if number == 0 {
+            match groups.concat().parse::<u64>() {
+                Ok(size)
+                    if line_checksum(0, &size.to_le_bytes()).eq_ignore_ascii_case(checksum) =>
+                {
+                    sizes.push(size)
+                }
+                _ => typos.push(0),
+            }
+            continue;
+        }
+
+        match decode_base32(&groups.concat()) {
+            Some(data) if line_checksum(number, &data).eq_ignore_ascii_case(checksum) => {
+                lines.push((number, data))
+            }
+            _ => typos.push(number),
+        }
+    }
+
+    lines.sort_by_key(|(number, _)| *number);
+
+    // A line typed in twice is only read once, unless the copies differ
+    for pair in lines.windows(2) {
+        if pair[0].0 == pair[1].0 && pair[0].1 != pair[1].1 {
+            typos.push(pair[0].0);
+        }
+    }
+    lines.dedup();
+
+    sizes.sort_unstable();
+    sizes.dedup();
+    if sizes.len() > 1 {
+        typos.push(0);
+    }
+
+    if !typos.is_empty() {
+        typos.sort_unstable();
+        typos.dedup();
+        return Err(Error::InvalidInput(format!(
+            "Typo in line(s) {} of the paper backup",
+            join_numbers(&typos)
+        )));
+    }
+
+    let Some(&size) = sizes.first() else {
+        return Err(Error::InvalidInput(match lines.is_empty() {
+            true => "The paper backup doesn't contain any numbered lines".to_string(),
+            false => {
+                "Line 0 of the paper backup, with the size of the share, is missing".to_string()
+            }
+        }));
+    };
+
+    let count = (size as usize).div_ceil(BYTES_PER_LINE);
+
+    // Lines beyond the size belong to another backup
+    let extra: Vec<usize> = lines
+        .iter()
+        .map(|(number, _)| *number)
+        .filter(|number| *number > count)
+        .collect();
+
+    if !extra.is_empty() {
+        return Err(Error::InvalidInput(format!(
+            "Line(s) {} don't belong to a paper backup of {size} bytes",
+            join_numbers(&extra)
+        )));
+    }
+
+    let missing: Vec<usize> = (1..=count)
+        .filter(|n| {
+            lines
+                .binary_search_by_key(n, |(number, _)| *number)
+                .is_err()
+        })
+        .collect();
+
+    if !missing.is_empty() {
+        return Err(Error::InvalidInput(format!(
+            "Line(s) {} of the paper backup are missing",
+            join_numbers(&missing)
+        )));
+    }
+
+    let share: Vec<u8> = lines.into_iter().flat_map(|(_, data)| data).collect();
+
+    match share.len() as u64 == size {
+        true => Ok(share),
+        false => Err(Error::InvalidInput(format!(
+            "The lines of the paper backup don't add up to {size} bytes"
+        ))),
+    }
+}
+
+fn join_numbers(numbers: &[usize]) -> String {
+    numbers
+        .iter()
+        .map(|n| n.to_string())
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_base32() {
+        // Test vectors of RFC 4648, without padding
+        let vectors = [
+            ("", ""),
+            ("f", "MY"),
+            ("fo", "MZXQ"),
+            ("foo", "MZXW6"),
+            ("foob", "MZXW6YQ"),
+            ("fooba", "MZXW6YTB"),
+            ("foobar", "MZXW6YTBOI"),
+        ];
+
+        for (data, encoded) in vectors {
+            assert_eq!(encode_base32(data.as_bytes()), encoded);
+            assert_eq!(decode_base32(encoded).unwrap(), data.as_bytes());
+        }
+
+        assert_eq!(decode_base32("mzxw6ytb0i").unwrap(), b"foobar");
+        assert_eq!(decode_base32("MZXW6YTBOJ"), None);
+    }
+
+    #[test]
+    fn test_import_lines() {
+        let data: Vec<u8> = (0..60).collect();
+        let line =
+            |n: usize, d: &[u8]| format!("{n}: {}  {}", encode_base32(d), line_checksum(n, d));
+        let size = |s: u64| format!("0: {s}  {}", line_checksum(0, &s.to_le_bytes()));
+
+        let text = [
+            "Some header",
+            &size(60),
+            &line(3, &data[50..]),
+            &line(1, &data[..25]),
+            &line(2, &data[25..50]).to_lowercase(),
+        ]
+        .join("\n");
+        assert_eq!(import_paper(&text).unwrap(), data);
+
+        // Swapped lines don't match their checksums
+        let text = [
+            line(2, &data[25..50]).replacen("2:", "1:", 1),
+            line(1, &data[..25]).replacen("1:", "2:", 1),
+        ]
+        .join("\n");
+        assert_eq!(
+            import_paper(&text).unwrap_err().to_string(),
+            "Invalid input: Typo in line(s) 1, 2 of the paper backup"
+        );
+
+        // Lines typed in twice are read once, unless they differ
+        let text = [
+            size(50),
+            line(1, &data[..25]),
+            line(2, &data[25..50]),
+            line(1, &data[..25]),
+            size(50),
+        ]
+        .join("\n");
+        assert_eq!(import_paper(&text).unwrap(), data[..50]);
+
+        let text = [
+            line(1, &data[..25]),
+            line(2, &data[25..50]),
+            line(1, &data[50..]),
+        ]
+        .join("\n");
+        assert_eq!(
+            import_paper(&text).unwrap_err().to_string(),
+            "Invalid input: Typo in line(s) 1 of the paper backup"
+        );
+
+        let text = [size(60), line(1, &data[..25]), line(3, &data[50..])].join("\n");
+        assert_eq!(
+            import_paper(&text).unwrap_err().to_string(),
+            "Invalid input: Line(s) 2 of the paper backup are missing"
+        );
+
+        let text = [size(60), size(50), line(1, &data[..25])].join("\n");
+        assert_eq!(
+            import_paper(&text).unwrap_err().to_string(),
+            "Invalid input: Typo in line(s) 0 of the paper backup"
+        );
+    }
+
+    #[test]
+    fn test_import_missing_last_line() {
+        let path = std::env::temp_dir().join("onetime-cli-test-paper-missing-last-line");
+        let share: Vec<u8> = (0..=255).collect();
+        std::fs::write(&path, &share).unwrap();
+        let paper = export_paper(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let numbered: Vec<&str> = paper
+            .lines()
+            .filter(|l| {
+                l.split_once(':')
+                    .is_some_and(|(n, _)| n.trim().parse::<usize>().is_ok())
+            })
+            .collect();
+        assert_eq!(numbered.len(), 12);
+        assert_eq!(import_paper(&numbered.join("\n")).unwrap(), share);
+
+        assert_eq!(
+            import_paper(&numbered[..11].join("\n"))
+                .unwrap_err()
+                .to_string(),
+            "Invalid input: Line(s) 11 of the paper backup are missing"
+        );
+
+        assert_eq!(
+            import_paper(&numbered[1..].join("\n"))
+                .unwrap_err()
+                .to_string(),
+            "Invalid input: Line 0 of the paper backup, with the size of the share, is missing"
+        );
+    }
+}
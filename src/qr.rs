@@ -0,0 +1,209 @@
+//! Text payloads for printing shares as QR codes.
+//!
+//! A share file is split into parts of at most [`MAX_PART_LEN`] bytes. Each
+//! part becomes the text of one QR code:
+//!
+//! ```text
+//! OTPQR:<part>/<count>:<crc32 of the whole share, hex>:<base64 of the part>
+//! ```
+//!
+//! A file containing the texts of all codes, one per line and in any order,
+//! is accepted by [`decrypt_file`](crate::decrypt_file) like a share.
+
+use std::path::Path;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+
+use crate::fs::{open_file, read, Mode};
+use crate::Error;
+
+const PREFIX: &str = "OTPQR:";
+
+/// Maximum number of share bytes stored in a single QR code
+pub const MAX_PART_LEN: usize = 750;
+
+/// Maximum number of QR codes of a share, which limits the memory reserved
+/// for the parts announced by the first code
+pub const MAX_PARTS: usize = 10_000;
+
+/// Returns whether `start` looks like the beginning of a list of QR code texts.
+pub fn is_qr_text(start: &[u8]) -> bool {
+    start.starts_with(PREFIX.as_bytes())
+}
+
+/// Splits `share` into the texts of numbered QR codes.
+pub fn split(share: &[u8]) -> Vec<String> {
+    let crc = crc32fast::hash(share);
+
+    let mut parts: Vec<&[u8]> = share.chunks(MAX_PART_LEN).collect();
+    if parts.is_empty() {
+        parts.push(&[]);
+    }
+
+    let count = parts.len();
+    parts
+        .iter()
+        .enumerate()
+        .map(|(i, part)| {
+            format!(
+                "{PREFIX}{}/{count}:{crc:08x}:{}",
+                i + 1,
+                STANDARD.encode(part)
+            )
+        })
+        .collect()
+}
+
+/// Reassembles a share from the texts of its QR codes, one per line.
+pub fn join(text: &str) -> Result<Vec<u8>, String> {
+    let mut parts: Vec<Option<Vec<u8>>> = Vec::new();
+    let mut share_crc = None;
+
+    for line in text.lines().map(str::trim).filter(|l| !l.is_empty()) {
+        let invalid = || format!("invalid QR code text {line:?}");
+
+        let fields: Vec<&str> = line
+            .strip_prefix(PREFIX)
+            .ok_or_else(invalid)?
+            .split(':')
+            .collect();
+        let [number, crc, data] = fields[..] else {
+            return Err(invalid());
+        };
+
+        let (part, count) = number.split_once('/').ok_or_else(invalid)?;
+        let part: usize = part.parse().map_err(|_| invalid())?;
+        let count: usize = count.parse().map_err(|_| invalid())?;
+        let crc = u32::from_str_radix(crc, 16).map_err(|_| invalid())?;
+        let data = STANDARD.decode(data).map_err(|_| invalid())?;
+
+        if part == 0 || part > count {
+            return Err(invalid());
+        }
+
+        if count > MAX_PARTS {
+            return Err(format!(
+                "invalid QR code text {line:?}: a share has at most {MAX_PARTS} QR codes"
+            ));
+        }
+
+        if parts.is_empty() {
+            parts.resize(count, None);
+        }
+
+        if parts.len() != count || share_crc.is_some_and(|c| c != crc) {
+            return Err("the QR codes belong to different shares".to_string());
+        }
+        share_crc = Some(crc);
+
+        parts[part - 1] = Some(data);
+    }
+
+    let missing: Vec<String> = (0..parts.len())
+        .filter(|&i| parts[i].is_none())
+        .map(|i| (i + 1).to_string())
+        .collect();
+    if !missing.is_empty() {
+        return Err(format!("QR code(s) {} missing", missing.join(", ")));
+    }
+
+    let share: Vec<u8> = parts.into_iter().flatten().flatten().collect();
+
+    match share_crc {
+        Some(crc) if crc == crc32fast::hash(&share) => Ok(share),
+        Some(_) => Err("checksum mismatch of the QR codes".to_string()),
+        None => Err("no QR codes found".to_string()),
+    }
+}
+
+/// Returns the texts of the QR codes for the share at `path`.
+///
+/// The codes contain the share file exactly as it is stored, in any
+/// [format](crate::ShareFormat). Shares larger than 750 bytes are split
+/// across several codes, up to 10000 codes.
+///
+/// ## Error
+/// Will return an [`Error::InvalidInput`] if the share already contains
+/// the texts of QR codes or doesn't fit into 10000 codes.
+pub fn qr_payloads(path: &Path) -> Result<Vec<String>, Error> {
+    let mut file = open_file(path, Mode::Open)?;
+    let mut share = Vec::new();
+    let mut buf = [0u8; 8192];
+
+    loop {
+        match read(&mut file, path, &mut buf)? {
+            0 => break,
+            bytes => share.extend_from_slice(&buf[..bytes]),
+        }
+    }
+
+    if is_qr_text(&share) {
+        return Err(Error::InvalidInput(format!(
+            "{path:?} already contains the texts of QR codes"
+        )));
+    }
+
+    if share.len() > MAX_PARTS * MAX_PART_LEN {
+        return Err(Error::InvalidInput(format!(
+            "{path:?} is too large for QR codes, at most {} bytes fit into {MAX_PARTS} codes",
+            MAX_PARTS * MAX_PART_LEN
+        )));
+    }
+
+    Ok(split(&share))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_split_join() {
+        let share: Vec<u8> = (0..2000).map(|i| (i % 251) as u8).collect();
+        let mut texts = split(&share);
+
+        assert_eq!(texts.len(), 3);
+        assert!(texts[2].starts_with("OTPQR:3/3:"));
+
+        // Order doesn't matter
+        texts.reverse();
+        assert_eq!(join(&texts.join("\n")), Ok(share));
+
+        assert_eq!(
+            join(&texts[1..].join("\n")),
+            Err("QR code(s) 3 missing".to_string())
+        );
+    }
+
+    #[test]
+    fn test_empty_share() {
+        let texts = split(&[]);
+
+        assert_eq!(texts.len(), 1);
+        assert_eq!(join(&texts[0]), Ok(Vec::new()));
+    }
+
+    #[test]
+    fn test_too_many_parts() {
+        let text = format!("OTPQR:1/{}:00000000:AAAA", usize::MAX);
+
+        assert_eq!(
+            join(&text),
+            Err(format!(
+                "invalid QR code text {text:?}: a share has at most {MAX_PARTS} QR codes"
+            ))
+        );
+    }
+
+    #[test]
+    fn test_different_shares() {
+        let mut texts = split(&[1; 1000]);
+        texts[1] = split(&[2; 1000]).remove(1);
+
+        assert_eq!(
+            join(&texts.join("\n")),
+            Err("the QR codes belong to different shares".to_string())
+        );
+    }
+}
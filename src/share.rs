@@ -0,0 +1,1078 @@
+//! Layout of the files written by [`encrypt_file`](crate::encrypt_file).
+//!
+//! Every share starts with a [`Header`], followed by the encrypted data and
+//! a [`Trailer`]:
+//!
+//! ```text
+//! header | header copy | data ... | trailer fields | fields length | trailer magic
+//! header = magic | version | index | set id | fields length | header fields | checksum
+//! ```
+//!
+//! Everything after the header is protected by the error correction, if
+//! any. The header itself holds the error correction parameters, so it is
+//! stored twice instead, with a checksum that tells which copy is intact.
+//!
+//! Header and trailer fields are stored as `tag | length (u32) | value`
+//! records. Information about the original file that must not be revealed
+//! by a single share is stored as [`Metadata`], which is split between the
+//! two shares the same way the data is.
+//!
+//! Shares written by versions before the header was introduced are still
+//! accepted; they are treated as raw data without any metadata.
+
+use std::ffi::OsString;
+use std::fs::OpenOptions;
+use std::io::{self, Cursor, Read, Seek, SeekFrom, Take, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use log::warn;
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+
+#[cfg(feature = "armor")]
+use crate::armor::{self, is_armored, ArmorWriter};
+use crate::attrs::{time_from_bytes, time_to_bytes, FileAttributes};
+use crate::compress::Compression;
+#[cfg(feature = "fec")]
+use crate::fec::{FecReader, FecWriter};
+use crate::fs::{io_error, open_file, os_str_to_bytes, os_string_from_bytes, read_full, Mode};
+use crate::hash_tree::{HashTree, TreeBuilder};
+use crate::pad::Padding;
+#[cfg(feature = "armor")]
+use crate::qr::{self, is_qr_text};
+use crate::stream::{Section, Sink, Source};
+use crate::volume::{is_split, VolumeReader, VolumeWriter};
+use crate::words::{self, looks_like_words, WordsWriter};
+use crate::{decrypt, encrypt, Config, Error, FecParams, IoOperation, ShareFormat};
+
+/// Start of every share with a header
+pub const MAGIC: &[u8; 8] = b"OTPSHARE";
+const TRAILER_MAGIC: &[u8; 8] = b"OTPTRAIL";
+const VERSION: u8 = 1;
+
+/// Length of the fixed part of the header
+const HEADER_LEN: usize = 8 + 1 + 1 + 16 + 4;
+
+/// Length of the checksum at the end of each copy of the header, the first
+/// bytes of a SHA-256 digest
+const HEADER_CHECKSUM_LEN: usize = 4;
+
+/// Maximum length of the header fields, which limits how far the copy of a
+/// header with a damaged length is searched for
+const MAX_HEADER_FIELDS_LEN: usize = 1024;
+
+/// Length of the fixed part of the trailer
+const TRAILER_END_LEN: usize = 8 + 8;
+
+/// Number of bytes needed to tell text formats from binary shares
+const TEXT_PEEK_LEN: usize = 64;
+
+const HEADER_FEC: u8 = 1;
+const HEADER_COMPRESSION: u8 = 2;
+
+const TRAILER_METADATA: u8 = 1;
+const TRAILER_HASH_TREE: u8 = 2;
+
+const META_DIGEST: u8 = 1;
+const META_MODE: u8 = 2;
+const META_MODIFIED: u8 = 3;
+const META_ACCESSED: u8 = 4;
+const META_OWNER: u8 = 5;
+const META_XATTR: u8 = 6;
+const META_NAME: u8 = 7;
+const META_LENGTH: u8 = 8;
+const META_NAME_PADDING: u8 = 9;
+
+/// The file name is padded to a multiple of this length, so the length of
+/// the split metadata doesn't reveal the length of the name
+const NAME_PADDING: u64 = 256;
+
+/// Length of a SHA-256 digest
+pub const DIGEST_LEN: usize = 32;
+
+/// Public information stored at the beginning of each share
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Header {
+    /// Position of the share within its set (`0` or `1`)
+    pub index: u8,
+
+    /// Random identifier shared by both shares of a set
+    pub set_id: [u8; 16],
+
+    /// Error correction applied to everything after the header
+    pub fec: Option<FecParams>,
+
+    /// Compression applied to the data before it was encrypted
+    pub compression: Option<Compression>,
+}
+
+impl Header {
+    /// Creates the headers of a new pair of shares.
+    pub fn new_pair(c: &Config) -> (Header, Header) {
+        let mut set_id = [0u8; 16];
+        rand::thread_rng().fill_bytes(&mut set_id);
+
+        let header = Header {
+            index: 0,
+            set_id,
+            fec: c.fec,
+            compression: c.compression,
+        };
+
+        (header.clone(), Header { index: 1, ..header })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut fields = Vec::new();
+
+        if let Some(fec) = self.fec {
+            write_field(&mut fields, HEADER_FEC, &fec.to_bytes());
+        }
+
+        if let Some(compression) = self.compression {
+            write_field(&mut fields, HEADER_COMPRESSION, &[compression.to_byte()]);
+        }
+
+        let mut copy = Vec::with_capacity(HEADER_LEN + fields.len() + HEADER_CHECKSUM_LEN);
+        copy.extend_from_slice(MAGIC);
+        copy.push(VERSION);
+        copy.push(self.index);
+        copy.extend_from_slice(&self.set_id);
+        copy.extend_from_slice(&(fields.len() as u32).to_le_bytes());
+        copy.extend_from_slice(&fields);
+
+        let checksum = Sha256::digest(&copy);
+        copy.extend_from_slice(&checksum[..HEADER_CHECKSUM_LEN]);
+
+        [copy.as_slice(), copy.as_slice()].concat()
+    }
+
+    /// Reads a header from the current position of `r`, using its copy if
+    /// the first one is damaged.
+    ///
+    /// Returns `Ok(None)` if there is no header. Otherwise returns the header
+    /// and its length, including the copy. Either way, the position of `r`
+    /// is unspecified afterwards.
+    pub fn read_from<R: Read>(r: &mut R, path: &Path) -> Result<Option<(Header, u64)>, Error> {
+        let mut buf = vec![0u8; 2 * (HEADER_LEN + MAX_HEADER_FIELDS_LEN + HEADER_CHECKSUM_LEN)];
+        let bytes = read_full(r, &mut buf, path)?;
+        let buf = &buf[..bytes];
+
+        let copy = match checked_header(buf) {
+            Some(copy) => copy,
+            None => {
+                // The copy directly follows the first header, so it starts
+                // at an offset equal to its length
+                let copy = (HEADER_LEN + HEADER_CHECKSUM_LEN..=buf.len() / 2)
+                    .filter(|&start| buf[start..].starts_with(MAGIC))
+                    .find_map(|start| checked_header(&buf[start..]).filter(|c| c.len() == start));
+
+                match copy {
+                    Some(copy) => {
+                        warn!("The header of {path:?} is damaged, using its copy");
+                        copy
+                    }
+                    None if buf.starts_with(MAGIC) => {
+                        return Err(invalid_share(path, "damaged or truncated header"))
+                    }
+                    None => return Ok(None),
+                }
+            }
+        };
+
+        if copy[8] != VERSION {
+            return Err(invalid_share(
+                path,
+                &format!("unsupported version {}", copy[8]),
+            ));
+        }
+
+        let mut set_id = [0u8; 16];
+        set_id.copy_from_slice(&copy[10..26]);
+
+        let mut header = Header {
+            index: copy[9],
+            set_id,
+            fec: None,
+            compression: None,
+        };
+
+        let fields = &copy[HEADER_LEN..copy.len() - HEADER_CHECKSUM_LEN];
+
+        for field in Fields::new(fields) {
+            let (tag, value) = field.map_err(|e| invalid_share(path, &e))?;
+
+            match tag {
+                HEADER_FEC => {
+                    let fec = FecParams::from_bytes(value).ok_or_else(|| {
+                        invalid_share(path, "invalid error correction parameters")
+                    })?;
+                    fec.check().map_err(|e| invalid_share(path, &e))?;
+
+                    header.fec = Some(fec);
+                }
+                HEADER_COMPRESSION => {
+                    let compression = match value {
+                        [byte] => Compression::from_byte(*byte),
+                        _ => None,
+                    };
+
+                    header.compression = Some(
+                        compression.ok_or_else(|| invalid_share(path, "unknown compression"))?,
+                    );
+                }
+                _ => (),
+            }
+        }
+
+        Ok(Some((header, 2 * copy.len() as u64)))
+    }
+}
+
+/// Returns the copy of a header at the start of `buf`, including its
+/// checksum, if the checksum matches.
+fn checked_header(buf: &[u8]) -> Option<&[u8]> {
+    if buf.len() < HEADER_LEN || !buf.starts_with(MAGIC) {
+        return None;
+    }
+
+    let fields_len = u32::from_le_bytes(buf[26..30].try_into().unwrap()) as usize;
+    if fields_len > MAX_HEADER_FIELDS_LEN {
+        return None;
+    }
+
+    let len = HEADER_LEN + fields_len;
+    let checksum = buf.get(len..len + HEADER_CHECKSUM_LEN)?;
+
+    (Sha256::digest(&buf[..len])[..HEADER_CHECKSUM_LEN] == *checksum)
+        .then(|| &buf[..len + HEADER_CHECKSUM_LEN])
+}
+
+/// Information stored at the end of each share
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Trailer {
+    /// This share's part of the split [`Metadata`]
+    pub metadata: Vec<u8>,
+
+    /// Hash tree over this share's encrypted data
+    pub hash_tree: Option<HashTree>,
+}
+
+impl Trailer {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        write_field(&mut buf, TRAILER_METADATA, &self.metadata);
+
+        if let Some(tree) = &self.hash_tree {
+            write_field(&mut buf, TRAILER_HASH_TREE, &tree.to_bytes());
+        }
+
+        let fields_len = buf.len() as u64;
+        buf.extend_from_slice(&fields_len.to_le_bytes());
+        buf.extend_from_slice(TRAILER_MAGIC);
+
+        buf
+    }
+
+    /// Parses the trailer fields of the share at `path`, which are stored at
+    /// `range` after its `data_len` bytes of data.
+    ///
+    /// A hash tree that doesn't match its root or the data is reported as an
+    /// [`IntegrityFailure`](Error::IntegrityFailure) at `range`.
+    fn parse(buf: &[u8], path: &Path, range: Range<u64>, data_len: u64) -> Result<Trailer, Error> {
+        let mut trailer = Trailer::default();
+        let damaged = |e: String| {
+            warn!("The hash tree of {path:?} is damaged: {e}");
+
+            Error::IntegrityFailure {
+                path: path.to_owned(),
+                range: range.clone(),
+            }
+        };
+
+        for field in Fields::new(buf) {
+            let (tag, value) = field.map_err(|e| invalid_share(path, &e))?;
+
+            match tag {
+                TRAILER_METADATA => trailer.metadata = value.to_vec(),
+                TRAILER_HASH_TREE => {
+                    let tree = HashTree::from_bytes(value).map_err(damaged)?;
+                    if !tree.covers(data_len) {
+                        return Err(damaged("wrong number of chunks".to_string()));
+                    }
+
+                    trailer.hash_tree = Some(tree);
+                }
+                _ => (),
+            }
+        }
+
+        Ok(trailer)
+    }
+}
+
+/// Information about the original file, split between both shares
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Metadata {
+    /// SHA-256 digest of the original file
+    pub digest: Option<[u8; DIGEST_LEN]>,
+
+    /// Name of the original file, without any directories
+    pub name: Option<OsString>,
+
+    /// Length of the encrypted data without the padding added to hide it
+    pub length: Option<u64>,
+
+    pub attributes: FileAttributes,
+}
+
+impl Metadata {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+
+        if let Some(digest) = &self.digest {
+            write_field(&mut buf, META_DIGEST, digest);
+        }
+
+        if let Some(name) = &self.name {
+            let name = os_str_to_bytes(name);
+            let padded = Padding::Block(NAME_PADDING).padded_len(name.len() as u64);
+
+            write_field(&mut buf, META_NAME, &name);
+            write_field(
+                &mut buf,
+                META_NAME_PADDING,
+                &vec![0u8; padded as usize - name.len()],
+            );
+        }
+
+        if let Some(length) = self.length {
+            write_field(&mut buf, META_LENGTH, &length.to_le_bytes());
+        }
+
+        let attrs = &self.attributes;
+
+        if let Some(mode) = attrs.mode {
+            write_field(&mut buf, META_MODE, &mode.to_le_bytes());
+        }
+
+        if let Some(modified) = attrs.modified {
+            write_field(&mut buf, META_MODIFIED, &time_to_bytes(modified));
+        }
+
+        if let Some(accessed) = attrs.accessed {
+            write_field(&mut buf, META_ACCESSED, &time_to_bytes(accessed));
+        }
+
+        if let Some((uid, gid)) = attrs.owner {
+            let mut owner = uid.to_le_bytes().to_vec();
+            owner.extend_from_slice(&gid.to_le_bytes());
+
+            write_field(&mut buf, META_OWNER, &owner);
+        }
+
+        for (name, value) in &attrs.xattrs {
+            let mut xattr = (name.len() as u32).to_le_bytes().to_vec();
+            xattr.extend_from_slice(name);
+            xattr.extend_from_slice(value);
+
+            write_field(&mut buf, META_XATTR, &xattr);
+        }
+
+        buf
+    }
+
+    pub fn from_bytes(buf: &[u8]) -> Result<Metadata, String> {
+        let mut meta = Metadata::default();
+
+        for field in Fields::new(buf) {
+            let (tag, value) = field?;
+
+            let attrs = &mut meta.attributes;
+            let invalid = |field: &str| format!("invalid {field}");
+
+            match tag {
+                META_DIGEST => {
+                    meta.digest = Some(value.try_into().map_err(|_| invalid("digest"))?);
+                }
+                META_NAME => meta.name = Some(os_string_from_bytes(value)),
+                META_LENGTH => {
+                    let length = value.try_into().map_err(|_| invalid("length"))?;
+                    meta.length = Some(u64::from_le_bytes(length));
+                }
+                META_MODE => {
+                    let mode = value.try_into().map_err(|_| invalid("file mode"))?;
+                    attrs.mode = Some(u32::from_le_bytes(mode));
+                }
+                META_MODIFIED => {
+                    attrs.modified = Some(time_from_bytes(value).ok_or_else(|| invalid("time"))?);
+                }
+                META_ACCESSED => {
+                    attrs.accessed = Some(time_from_bytes(value).ok_or_else(|| invalid("time"))?);
+                }
+                META_OWNER => {
+                    if value.len() != 8 {
+                        return Err(invalid("owner"));
+                    }
+
+                    let uid = u32::from_le_bytes(value[..4].try_into().unwrap());
+                    let gid = u32::from_le_bytes(value[4..].try_into().unwrap());
+                    attrs.owner = Some((uid, gid));
+                }
+                META_XATTR => {
+                    let name_len = value
+                        .get(..4)
+                        .map(|l| u32::from_le_bytes(l.try_into().unwrap()) as usize)
+                        .filter(|l| *l <= value.len() - 4)
+                        .ok_or_else(|| invalid("extended attribute"))?;
+
+                    let (name, value) = value[4..].split_at(name_len);
+                    attrs.xattrs.push((name.to_vec(), value.to_vec()));
+                }
+                _ => (),
+            }
+        }
+
+        Ok(meta)
+    }
+
+    /// Serializes the metadata and splits it into two parts, neither of
+    /// which reveals anything on its own.
+    pub fn split(&self) -> Result<(Vec<u8>, Vec<u8>), Error> {
+        let buf = self.to_bytes();
+        let mut part1 = vec![0u8; buf.len()];
+        let mut part2 = vec![0u8; buf.len()];
+
+        encrypt(&buf, &mut part1, &mut part2)?;
+
+        Ok((part1, part2))
+    }
+
+    /// Inverse of [`split`](Metadata::split). `paths` are the shares the two
+    /// parts were read from.
+    pub fn join(part1: &[u8], part2: &[u8], paths: [&Path; 2]) -> Result<Metadata, Error> {
+        if part1.len() != part2.len() {
+            return Err(Error::ShareMismatch(paths.map(Path::to_owned)));
+        }
+
+        let mut buf = vec![0u8; part1.len()];
+        decrypt(part1, part2, &mut buf)?;
+
+        Metadata::from_bytes(&buf)
+            .map_err(|e| Error::InvalidInput(format!("Invalid metadata: {e}")))
+    }
+}
+
+/// Writes a share: header, data and trailer.
+pub struct ShareWriter {
+    path: PathBuf,
+    sink: Box<dyn Sink>,
+
+    /// `None` for shares without header and trailer
+    tree: Option<TreeBuilder>,
+
+    data_len: u64,
+
+    /// Digest of the data, only kept for [`ShareWriter::checkpoint`]
+    digest: Option<Sha256>,
+}
+
+impl ShareWriter {
+    /// Creates a share at `path` in the given format. A hash tree with
+    /// chunks of `chunk_size` bytes is built over the data. If `volume_size`
+    /// is set, the share is split into volumes of at most that size.
+    pub fn create(
+        path: &Path,
+        header: &Header,
+        chunk_size: u32,
+        format: ShareFormat,
+        volume_size: Option<u64>,
+    ) -> Result<Self, Error> {
+        let file: Box<dyn Sink> = match volume_size {
+            Some(size) => Box::new(
+                VolumeWriter::new(path, header, size)
+                    .map_err(|e| io_error(path, IoOperation::Open, e))?,
+            ),
+            None => Box::new(open_file(path, Mode::Create)?),
+        };
+
+        let mut sink: Box<dyn Sink> = match format {
+            ShareFormat::Binary => file,
+            #[cfg(feature = "armor")]
+            ShareFormat::Armor => Box::new(
+                ArmorWriter::new(file, header.index, &header.set_id)
+                    .map_err(|e| io_error(path, IoOperation::Write, e))?,
+            ),
+            // Only the data itself, without header and trailer
+            ShareFormat::Words => {
+                return Ok(Self {
+                    path: path.to_owned(),
+                    sink: Box::new(WordsWriter::new(file)),
+                    tree: None,
+                    data_len: 0,
+                    digest: None,
+                })
+            }
+        };
+
+        sink.write_all(&header.to_bytes())
+            .map_err(|e| io_error(path, IoOperation::Write, e))?;
+
+        // Without the feature, `Config::validate` rejects error correction
+        #[cfg(feature = "fec")]
+        if let Some(fec) = header.fec {
+            sink = Box::new(
+                FecWriter::new(sink, fec).map_err(|e| io_error(path, IoOperation::Write, e))?,
+            );
+        }
+
+        Ok(Self {
+            path: path.to_owned(),
+            sink,
+            tree: Some(TreeBuilder::new(chunk_size)),
+            data_len: 0,
+            digest: None,
+        })
+    }
+
+    /// Continues a binary share at `path` that was interrupted after
+    /// `data_len` bytes of data. Anything after them is discarded.
+    ///
+    /// The hash tree is rebuilt from the data that was written, and a
+    /// digest of it is kept, see [`ShareWriter::checkpoint`].
+    pub fn resume(
+        path: &Path,
+        header: &Header,
+        chunk_size: u32,
+        data_len: u64,
+    ) -> Result<Self, Error> {
+        let io_err = |operation| move |e: io::Error| io_error(path, operation, e);
+        let mismatch = || {
+            Error::InvalidInput(format!(
+                "{path:?} doesn't match the progress journal, run again without resuming"
+            ))
+        };
+
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(path)
+            .map_err(io_err(IoOperation::Open))?;
+
+        let header = header.to_bytes();
+        let mut stored = vec![0u8; header.len()];
+        if read_full(&mut file, &mut stored, path)? < header.len() || stored != header {
+            return Err(mismatch());
+        }
+
+        let mut tree = TreeBuilder::new(chunk_size);
+        let mut digest = Sha256::new();
+        let mut buf = vec![0u8; chunk_size as usize];
+        let mut rest = data_len;
+
+        while rest > 0 {
+            let max = buf.len().min(rest.try_into().unwrap_or(usize::MAX));
+            let bytes = read_full(&mut file, &mut buf[..max], path)?;
+
+            if bytes == 0 {
+                return Err(mismatch());
+            }
+
+            tree.update(&buf[..bytes]);
+            digest.update(&buf[..bytes]);
+            rest -= bytes as u64;
+        }
+
+        file.set_len(header.len() as u64 + data_len)
+            .and_then(|_| file.seek(SeekFrom::End(0)))
+            .map_err(io_err(IoOperation::Write))?;
+
+        Ok(Self {
+            path: path.to_owned(),
+            sink: Box::new(file),
+            tree: Some(tree),
+            data_len,
+            digest: Some(digest),
+        })
+    }
+
+    /// Keeps a digest of the data written from now on, see
+    /// [`ShareWriter::checkpoint`].
+    pub fn keep_digest(&mut self) {
+        self.digest = Some(Sha256::new());
+    }
+
+    /// Stores all data written so far durably and returns the digest of it.
+    pub fn checkpoint(&mut self) -> Result<[u8; DIGEST_LEN], Error> {
+        self.sink
+            .sync()
+            .map_err(|e| io_error(&self.path, IoOperation::Write, e))?;
+
+        Ok(self.digest.clone().unwrap_or_default().finalize().into())
+    }
+
+    pub fn write(&mut self, buf: &[u8]) -> Result<(), Error> {
+        if let Some(tree) = &mut self.tree {
+            tree.update(buf);
+        }
+
+        self.data_len += buf.len() as u64;
+
+        if let Some(digest) = &mut self.digest {
+            digest.update(buf);
+        }
+
+        self.sink
+            .write_all(buf)
+            .map_err(|e| io_error(&self.path, IoOperation::Write, e))
+    }
+
+    /// Number of data bytes written so far
+    pub fn data_len(&self) -> u64 {
+        self.data_len
+    }
+
+    /// Writes the trailer, which contains `metadata` and the hash tree.
+    pub fn finish(self, metadata: Vec<u8>) -> Result<(), Error> {
+        let mut sink = self.sink;
+
+        let Some(tree) = self.tree else {
+            return sink
+                .finish()
+                .map_err(|e| io_error(&self.path, IoOperation::Write, e));
+        };
+
+        let trailer = Trailer {
+            metadata,
+            hash_tree: Some(tree.finish()),
+        };
+
+        sink.write_all(&trailer.to_bytes())
+            .and_then(|_| sink.finish())
+            .map_err(|e| io_error(&self.path, IoOperation::Write, e))
+    }
+}
+
+/// Reads a share written by [`ShareWriter`] or a raw share of an older version.
+pub struct ShareReader {
+    path: PathBuf,
+    data: Take<Box<dyn Source>>,
+    data_len: u64,
+
+    /// `None` for shares without header
+    pub header: Option<Header>,
+    pub trailer: Trailer,
+}
+
+impl ShareReader {
+    /// Opens a share, which may be ASCII-armored, encoded as words or
+    /// given as the texts of its QR codes.
+    pub fn open(path: &Path) -> Result<Self, Error> {
+        if is_split(path) {
+            let volumes = VolumeReader::open(path)?;
+            let len = volumes.len();
+
+            return Self::open_source(path, volumes, len);
+        }
+
+        let file = open_file(path, Mode::Open)?;
+        let len = file
+            .metadata()
+            .map_err(|e| io_error(path, IoOperation::Read, e))?
+            .len();
+
+        Self::open_source(path, file, len)
+    }
+
+    fn open_source<R: Read + Seek + 'static>(
+        path: &Path,
+        mut file: R,
+        file_len: u64,
+    ) -> Result<Self, Error> {
+        let io_err = |e: io::Error| io_error(path, IoOperation::Read, e);
+
+        let mut start = [0u8; TEXT_PEEK_LEN];
+        let bytes = read_full(&mut file, &mut start, path)?;
+        file.rewind().map_err(io_err)?;
+
+        if is_text_format(&start[..bytes]) {
+            // Text formats are meant for small shares, so they are decoded in memory
+            let mut text = Vec::new();
+            file.read_to_end(&mut text).map_err(io_err)?;
+
+            let data = decode_text(&text).map_err(|e| invalid_share(path, &e))?;
+            let len = data.len() as u64;
+
+            return Self::from_source(path, Cursor::new(data), len);
+        }
+
+        Self::from_source(path, file, file_len)
+    }
+
+    fn from_source<R: Read + Seek + 'static>(
+        path: &Path,
+        mut file: R,
+        file_len: u64,
+    ) -> Result<Self, Error> {
+        let io_err = |e: io::Error| io_error(path, IoOperation::Read, e);
+
+        let (header, data_start) = match Header::read_from(&mut file, path)? {
+            Some(h) => h,
+            None => {
+                let stream: Box<dyn Source> =
+                    Box::new(Section::new(file, 0, file_len).map_err(io_err)?);
+
+                return Ok(Self {
+                    path: path.to_owned(),
+                    data: stream.take(file_len),
+                    data_len: file_len,
+                    header: None,
+                    trailer: Trailer::default(),
+                });
+            }
+        };
+
+        let section = Section::new(file, data_start, file_len - data_start).map_err(io_err)?;
+        let mut stream: Box<dyn Source> = match header.fec {
+            #[cfg(feature = "fec")]
+            Some(fec) => Box::new(FecReader::new(section, fec).map_err(io_err)?),
+            #[cfg(not(feature = "fec"))]
+            Some(_) => {
+                return Err(invalid_share(
+                    path,
+                    "error correction needs the cargo feature `fec`",
+                ))
+            }
+            None => Box::new(section),
+        };
+
+        let stream_len = stream.seek(SeekFrom::End(0)).map_err(io_err)?;
+        if stream_len < TRAILER_END_LEN as u64 {
+            return Err(invalid_share(path, "missing trailer"));
+        }
+
+        let mut end = [0u8; TRAILER_END_LEN];
+        stream
+            .seek(SeekFrom::End(-(TRAILER_END_LEN as i64)))
+            .map_err(io_err)?;
+        read_full(&mut stream, &mut end, path)?;
+
+        if &end[8..] != TRAILER_MAGIC {
+            return Err(invalid_share(path, "missing trailer"));
+        }
+
+        let fields_len = u64::from_le_bytes(end[..8].try_into().unwrap());
+        let data_len = (stream_len - TRAILER_END_LEN as u64)
+            .checked_sub(fields_len)
+            .ok_or_else(|| invalid_share(path, "truncated trailer"))?;
+
+        let mut fields = vec![0u8; fields_len as usize];
+        stream.seek(SeekFrom::Start(data_len)).map_err(io_err)?;
+        read_full(&mut stream, &mut fields, path)?;
+        let trailer = Trailer::parse(&fields, path, data_len..stream_len, data_len)?;
+
+        stream.rewind().map_err(io_err)?;
+
+        Ok(Self {
+            path: path.to_owned(),
+            data: stream.take(data_len),
+            data_len,
+            header: Some(header),
+            trailer,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Checks the chunk with the given index against the hash tree. Returns
+    /// `true` if the share has no hash tree.
+    pub fn chunk_intact(&self, index: u64, chunk: &[u8]) -> bool {
+        match &self.trailer.hash_tree {
+            Some(tree) => tree.leaves.get(index as usize) == Some(&HashTree::leaf_hash(chunk)),
+            None => true,
+        }
+    }
+
+    /// Number of damaged blocks that were repaired so far
+    pub fn repaired_blocks(&self) -> u64 {
+        self.data.get_ref().repaired_blocks()
+    }
+
+    /// Size of the chunks of the hash tree, if the share has one
+    pub fn chunk_size(&self) -> Option<u32> {
+        self.trailer.hash_tree.as_ref().map(|t| t.chunk_size)
+    }
+
+    /// Length of the encrypted data in bytes
+    pub fn data_len(&self) -> u64 {
+        self.data_len
+    }
+
+    /// Skips the first `offset` bytes of the encrypted data.
+    pub fn skip(&mut self, offset: u64) -> Result<(), Error> {
+        let offset = offset.min(self.data_len);
+
+        self.data
+            .get_mut()
+            .seek(SeekFrom::Start(offset))
+            .map_err(|e| io_error(&self.path, IoOperation::Read, e))?;
+        self.data.set_limit(self.data_len - offset);
+
+        Ok(())
+    }
+
+    /// Reads encrypted data into `buf`. Only returns less than `buf.len()`
+    /// bytes at the end of the data.
+    pub fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        read_full(&mut self.data, buf, &self.path)
+    }
+}
+
+/// Checks that two shares belong to the same set and have the same length,
+/// and returns the metadata split between them.
+pub fn check_pair(s1: &ShareReader, s2: &ShareReader) -> Result<Metadata, Error> {
+    match (&s1.header, &s2.header) {
+        (None, None) => Ok(Metadata::default()),
+        (Some(h1), Some(h2)) if h1.set_id == h2.set_id && h1.index != h2.index => {
+            if s1.data_len() != s2.data_len() || s1.chunk_size() != s2.chunk_size() {
+                return Err(Error::ShareMismatch([
+                    s1.path().to_owned(),
+                    s2.path().to_owned(),
+                ]));
+            }
+
+            Metadata::join(
+                &s1.trailer.metadata,
+                &s2.trailer.metadata,
+                [s1.path(), s2.path()],
+            )
+        }
+        _ => Err(Error::ShareMismatch([
+            s1.path().to_owned(),
+            s2.path().to_owned(),
+        ])),
+    }
+}
+
+/// Reads the share at `path` and decodes it if it is stored in a text format.
+pub fn read_binary(path: &Path) -> Result<Vec<u8>, Error> {
+    let mut share = Vec::new();
+
+    match is_split(path) {
+        true => VolumeReader::open(path)?.read_to_end(&mut share),
+        false => open_file(path, Mode::Open)?.read_to_end(&mut share),
+    }
+    .map_err(|e| io_error(path, IoOperation::Read, e))?;
+
+    match is_text_format(&share[..share.len().min(TEXT_PEEK_LEN)]) {
+        true => decode_text(&share).map_err(|e| invalid_share(path, &e)),
+        false => Ok(share),
+    }
+}
+
+#[cfg(feature = "armor")]
+fn is_text_format(start: &[u8]) -> bool {
+    is_armored(start) || is_qr_text(start) || looks_like_words(start)
+}
+
+/// Without the cargo feature `armor`, ASCII armor and QR code texts can't be
+/// decoded, but are still told apart from binary shares, which never
+/// consist of printable characters only.
+#[cfg(not(feature = "armor"))]
+fn is_text_format(start: &[u8]) -> bool {
+    !start.is_empty()
+        && start
+            .iter()
+            .all(|c| c.is_ascii_graphic() || c.is_ascii_whitespace())
+}
+
+/// Decodes a share stored in one of the text formats.
+#[cfg(feature = "armor")]
+fn decode_text(text: &[u8]) -> Result<Vec<u8>, String> {
+    if is_armored(text) {
+        return armor::decode(text);
+    }
+
+    if is_qr_text(text) {
+        let text = std::str::from_utf8(text).map_err(|_| "invalid QR code texts".to_string())?;
+        let share = qr::join(text)?;
+
+        // The QR codes may contain a share in any other format
+        return match is_text_format(&share[..share.len().min(TEXT_PEEK_LEN)]) {
+            true if !is_qr_text(&share) => decode_text(&share),
+            _ => Ok(share),
+        };
+    }
+
+    let text = std::str::from_utf8(text).map_err(|_| "invalid word list".to_string())?;
+    words::decode(text)
+}
+
+/// Decodes a share stored as a word list, the only text format without the
+/// cargo feature `armor`.
+#[cfg(not(feature = "armor"))]
+fn decode_text(text: &[u8]) -> Result<Vec<u8>, String> {
+    match std::str::from_utf8(text) {
+        Ok(text) if looks_like_words(text.as_bytes()) => words::decode(text),
+        _ => Err("ASCII armor and QR code texts need the cargo feature `armor`".to_string()),
+    }
+}
+
+fn invalid_share(path: &Path, reason: &str) -> Error {
+    Error::InvalidInput(format!("{path:?} is not a valid share: {reason}"))
+}
+
+fn write_field(buf: &mut Vec<u8>, tag: u8, value: &[u8]) {
+    buf.push(tag);
+    buf.extend_from_slice(&(value.len() as u32).to_le_bytes());
+    buf.extend_from_slice(value);
+}
+
+/// Iterator over the `tag | length | value` records in a buffer
+struct Fields<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> Fields<'a> {
+    fn new(buf: &'a [u8]) -> Self {
+        Self { buf }
+    }
+}
+
+impl<'a> Iterator for Fields<'a> {
+    type Item = Result<(u8, &'a [u8]), String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.buf.is_empty() {
+            return None;
+        }
+
+        if self.buf.len() < 5 {
+            self.buf = &[];
+            return Some(Err("truncated field".to_string()));
+        }
+
+        let tag = self.buf[0];
+        let len = u32::from_le_bytes(self.buf[1..5].try_into().unwrap()) as usize;
+
+        if self.buf.len() - 5 < len {
+            self.buf = &[];
+            return Some(Err("truncated field".to_string()));
+        }
+
+        let value = &self.buf[5..5 + len];
+        self.buf = &self.buf[5 + len..];
+
+        Some(Ok((tag, value)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    #[cfg(feature = "compress")]
+    fn test_header_roundtrip() {
+        let c = Config {
+            fec: Some(FecParams::new(4)),
+            compression: Some(Compression::Deflate),
+            ..Config::new("file")
+        };
+        let (h1, h2) = Header::new_pair(&c);
+        let bytes = h2.to_bytes();
+        let path = Path::new("share");
+
+        assert_eq!(h1.set_id, h2.set_id);
+        assert_eq!(
+            Header::read_from(&mut bytes.as_slice(), path).unwrap(),
+            Some((h2, bytes.len() as u64))
+        );
+    }
+
+    #[test]
+    fn test_header_damaged() {
+        let (header, _) = Header::new_pair(&Config::new("file"));
+        let bytes = header.to_bytes();
+        let path = Path::new("share");
+
+        // The length of the header fields and the magic of the first copy
+        for pos in [28, 0] {
+            let mut damaged = bytes.clone();
+            damaged[pos] ^= 0x01;
+
+            assert_eq!(
+                Header::read_from(&mut damaged.as_slice(), path).unwrap(),
+                Some((header.clone(), bytes.len() as u64))
+            );
+        }
+
+        // Both copies
+        let mut damaged = bytes.clone();
+        damaged[20] ^= 0x01;
+        damaged[bytes.len() / 2 + 20] ^= 0x01;
+
+        assert!(matches!(
+            Header::read_from(&mut damaged.as_slice(), path),
+            Err(Error::InvalidInput(_))
+        ));
+    }
+
+    #[test]
+    fn test_header_missing() {
+        let path = Path::new("share");
+
+        assert_eq!(
+            Header::read_from(&mut &b"Hello, world!"[..], path).unwrap(),
+            None
+        );
+        assert_eq!(Header::read_from(&mut &b""[..], path).unwrap(), None);
+    }
+
+    #[test]
+    fn test_metadata_split_join() {
+        let meta = Metadata {
+            digest: Some([7u8; DIGEST_LEN]),
+            name: Some(OsString::from("secret.txt")),
+            length: Some(1234),
+            attributes: FileAttributes {
+                mode: Some(0o640),
+                modified: Some(std::time::UNIX_EPOCH),
+                accessed: None,
+                owner: Some((1000, 100)),
+                xattrs: vec![(b"user.comment".to_vec(), b"secret".to_vec())],
+            },
+        };
+
+        let (part1, part2) = meta.split().unwrap();
+        assert_ne!(part1, meta.to_bytes());
+        assert_ne!(part2, meta.to_bytes());
+
+        let paths = [Path::new("share1"), Path::new("share2")];
+        assert_eq!(Metadata::join(&part1, &part2, paths).unwrap(), meta);
+
+        // The length of the name is hidden
+        let long = Metadata {
+            name: Some(OsString::from("a much longer name of a secret file.txt")),
+            ..meta.clone()
+        };
+        assert_eq!(long.split().unwrap().0.len(), part1.len());
+
+        assert!(matches!(
+            Metadata::join(&part1, &part2[1..], paths),
+            Err(Error::ShareMismatch(_))
+        ));
+    }
+
+    #[test]
+    fn test_truncated_field() {
+        let mut buf = Vec::new();
+        write_field(&mut buf, META_DIGEST, &[1, 2, 3]);
+        buf.pop();
+
+        assert!(Metadata::from_bytes(&buf).is_err());
+    }
+}
@@ -0,0 +1,110 @@
+//! Building blocks for the layers a share is read from or written to.
+
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+
+/// A destination for share data that needs to know when all data has been written.
+pub trait Sink: Write {
+    /// Writes any buffered data and finishes the underlying sinks.
+    fn finish(self: Box<Self>) -> io::Result<()>;
+
+    /// Makes sure all data written so far is stored durably.
+    fn sync(&mut self) -> io::Result<()> {
+        self.flush()
+    }
+}
+
+impl Sink for File {
+    fn finish(mut self: Box<Self>) -> io::Result<()> {
+        self.flush()
+    }
+
+    fn sync(&mut self) -> io::Result<()> {
+        self.flush()?;
+        self.sync_data()
+    }
+}
+
+/// A seekable source of share data.
+pub trait Source: Read + Seek {
+    /// Number of damaged blocks that were repaired while reading
+    fn repaired_blocks(&self) -> u64 {
+        0
+    }
+}
+
+impl Sink for Box<dyn Sink> {
+    fn finish(self: Box<Self>) -> io::Result<()> {
+        (*self).finish()
+    }
+
+    fn sync(&mut self) -> io::Result<()> {
+        (**self).sync()
+    }
+}
+
+impl Source for Box<dyn Source> {
+    fn repaired_blocks(&self) -> u64 {
+        (**self).repaired_blocks()
+    }
+}
+
+/// A window of `len` bytes starting at `start` within a seekable reader
+pub struct Section<R> {
+    inner: R,
+    start: u64,
+    len: u64,
+    pos: u64,
+}
+
+impl<R: Read + Seek> Section<R> {
+    pub fn new(mut inner: R, start: u64, len: u64) -> io::Result<Self> {
+        inner.seek(SeekFrom::Start(start))?;
+
+        Ok(Self {
+            inner,
+            start,
+            len,
+            pos: 0,
+        })
+    }
+}
+
+impl<R: Read + Seek> Read for Section<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let remaining = self.len.saturating_sub(self.pos);
+        let max = buf.len().min(remaining.try_into().unwrap_or(usize::MAX));
+
+        let bytes = self.inner.read(&mut buf[..max])?;
+        self.pos += bytes as u64;
+
+        Ok(bytes)
+    }
+}
+
+impl<R: Read + Seek> Seek for Section<R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.pos = resolve_seek(pos, self.pos, self.len)?;
+        self.inner.seek(SeekFrom::Start(self.start + self.pos))?;
+
+        Ok(self.pos)
+    }
+}
+
+impl<R: Read + Seek> Source for Section<R> {}
+
+/// Computes the absolute position a [`SeekFrom`] refers to.
+pub fn resolve_seek(pos: SeekFrom, current: u64, len: u64) -> io::Result<u64> {
+    let new = match pos {
+        SeekFrom::Start(p) => Some(p),
+        SeekFrom::End(p) => len.checked_add_signed(p),
+        SeekFrom::Current(p) => current.checked_add_signed(p),
+    };
+
+    new.ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "invalid seek to a negative or overflowing position",
+        )
+    })
+}
@@ -0,0 +1,368 @@
+//! Templates for the names of encrypted files, like `{dir}/{stem}.{index}.share`.
+
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::fs::{io_error, os_str_to_bytes, os_string_from_bytes};
+use crate::{Error, IoOperation};
+
+/// Length of `{set}` after expansion: the set id in lowercase hex
+const SET_LEN: usize = 32;
+
+/// Length of `{date}` after expansion: `YYYY-MM-DD`
+const DATE_LEN: usize = 10;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Placeholder {
+    Dir,
+    Name,
+    Stem,
+    Ext,
+    Index,
+    Set,
+    Date,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Part {
+    Literal(String),
+    Placeholder(Placeholder),
+}
+
+/// Values the placeholders of a template are replaced with
+pub struct Values<'a> {
+    /// File the encrypted files belong to
+    pub file: &'a Path,
+
+    /// Overrides the directory of `file` for `{dir}`
+    pub dir: Option<&'a Path>,
+
+    pub index: u8,
+
+    /// `None` if not known yet, see [`NameTemplate::find`]
+    pub set_id: Option<&'a [u8; 16]>,
+    pub date: Option<SystemTime>,
+}
+
+/// A parsed name template.
+///
+/// Supported placeholders are `{dir}`, `{name}`, `{stem}`, `{ext}`,
+/// `{index}`, `{set}` and `{date}`. `{{` and `}}` stand for literal braces.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NameTemplate {
+    parts: Vec<Part>,
+}
+
+/// A piece of an expanded template whose value is not known
+#[derive(Clone, Debug)]
+enum Piece {
+    Bytes(Vec<u8>),
+    Wildcard(usize, fn(u8, usize) -> bool),
+}
+
+impl NameTemplate {
+    pub fn parse(template: &str) -> Result<Self, Error> {
+        let invalid = |msg: &str| Error::InvalidInput(format!("Invalid name template: {msg}"));
+
+        let mut parts = Vec::new();
+        let mut literal = String::new();
+        let mut chars = template.chars();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.as_str().starts_with('{') => {
+                    chars.next();
+                    literal.push('{');
+                }
+                '}' if chars.as_str().starts_with('}') => {
+                    chars.next();
+                    literal.push('}');
+                }
+                '{' => {
+                    let rest = chars.as_str();
+                    let end = rest.find('}').ok_or_else(|| invalid("unclosed '{'"))?;
+
+                    let placeholder = match &rest[..end] {
+                        "dir" => Placeholder::Dir,
+                        "name" => Placeholder::Name,
+                        "stem" => Placeholder::Stem,
+                        "ext" => Placeholder::Ext,
+                        "index" => Placeholder::Index,
+                        "set" => Placeholder::Set,
+                        "date" => Placeholder::Date,
+                        other => return Err(invalid(&format!("unknown placeholder {{{other}}}"))),
+                    };
+
+                    if !literal.is_empty() {
+                        parts.push(Part::Literal(std::mem::take(&mut literal)));
+                    }
+                    parts.push(Part::Placeholder(placeholder));
+
+                    chars = rest[end + 1..].chars();
+                }
+                '}' => return Err(invalid("unmatched '}'")),
+                c => literal.push(c),
+            }
+        }
+
+        if !literal.is_empty() {
+            parts.push(Part::Literal(literal));
+        }
+
+        Ok(Self { parts })
+    }
+
+    /// Replaces all placeholders. `set_id` and `date` must be known.
+    pub fn expand(&self, values: &Values) -> PathBuf {
+        let bytes = self
+            .pieces(values)
+            .into_iter()
+            .flat_map(|piece| match piece {
+                Piece::Bytes(b) => b,
+                Piece::Wildcard(..) => unreachable!("set id and date are known"),
+            })
+            .collect::<Vec<u8>>();
+
+        PathBuf::from(os_string_from_bytes(&bytes))
+    }
+
+    /// Finds the file the template refers to. `{set}` and `{date}` match
+    /// any value if they are not known, but only within the file name.
+    pub fn find(&self, values: &Values) -> Result<PathBuf, Error> {
+        self.find_existing(values)?.ok_or_else(|| {
+            Error::InvalidInput(format!(
+                "No file matches the name template for file {}",
+                values.index
+            ))
+        })
+    }
+
+    /// Like [`find`](NameTemplate::find), but returns `Ok(None)` if no
+    /// file matches.
+    pub fn find_existing(&self, values: &Values) -> Result<Option<PathBuf>, Error> {
+        let pieces = self.pieces(values);
+
+        if pieces.iter().all(|p| matches!(p, Piece::Bytes(_))) {
+            return Ok(Some(self.expand(values)));
+        }
+
+        // Split off the directory, which must not contain any wildcards
+        let mut dir = Vec::new();
+        let mut name = Vec::new();
+        for piece in pieces {
+            match piece {
+                Piece::Bytes(b) => match b.iter().rposition(|&c| is_separator(c)) {
+                    Some(pos) => {
+                        if name.iter().any(|p| matches!(p, Piece::Wildcard(..))) {
+                            return Err(Error::InvalidInput(
+                                "Invalid name template: {set} and {date} can only be looked up \
+                                in the file name"
+                                    .to_string(),
+                            ));
+                        }
+
+                        for p in name.drain(..) {
+                            if let Piece::Bytes(b) = p {
+                                dir.extend(b);
+                            }
+                        }
+
+                        dir.extend_from_slice(&b[..=pos]);
+                        name.push(Piece::Bytes(b[pos + 1..].to_vec()));
+                    }
+                    None => name.push(Piece::Bytes(b)),
+                },
+                wildcard => name.push(wildcard),
+            }
+        }
+
+        let dir = match dir.is_empty() {
+            true => PathBuf::from("."),
+            false => PathBuf::from(os_string_from_bytes(&dir)),
+        };
+
+        let entries =
+            std::fs::read_dir(&dir).map_err(|error| io_error(&dir, IoOperation::Open, error))?;
+
+        let mut found: Vec<PathBuf> = entries
+            .filter_map(|e| e.ok())
+            .map(|e| e.file_name())
+            .filter(|n| matches_pieces(&os_str_to_bytes(n), &name))
+            .map(|n| dir.join(n))
+            .collect();
+
+        match found.len() {
+            1 => Ok(Some(found.remove(0))),
+            0 => Ok(None),
+            _ => {
+                found.sort();
+
+                Err(Error::InvalidInput(format!(
+                    "Several files match the name template for file {}: {found:?}",
+                    values.index
+                )))
+            }
+        }
+    }
+
+    fn pieces(&self, values: &Values) -> Vec<Piece> {
+        let file = values.file;
+
+        let dir = match values.dir {
+            Some(dir) => dir.as_os_str(),
+            None => match file.parent() {
+                Some(p) if !p.as_os_str().is_empty() => p.as_os_str(),
+                _ => OsStr::new("."),
+            },
+        };
+
+        let os = |s: Option<&OsStr>| Piece::Bytes(s.map(os_str_to_bytes).unwrap_or_default());
+
+        self.parts
+            .iter()
+            .map(|part| match part {
+                Part::Literal(s) => Piece::Bytes(s.as_bytes().to_vec()),
+                Part::Placeholder(p) => match p {
+                    Placeholder::Dir => Piece::Bytes(os_str_to_bytes(dir)),
+                    Placeholder::Name => os(file.file_name()),
+                    Placeholder::Stem => os(file.file_stem()),
+                    Placeholder::Ext => os(file.extension()),
+                    Placeholder::Index => Piece::Bytes(values.index.to_string().into_bytes()),
+                    Placeholder::Set => match values.set_id {
+                        Some(id) => Piece::Bytes(to_hex(id).into_bytes()),
+                        None => Piece::Wildcard(SET_LEN, |c, _| c.is_ascii_hexdigit()),
+                    },
+                    Placeholder::Date => match values.date {
+                        Some(date) => Piece::Bytes(format_date(date).into_bytes()),
+                        None => Piece::Wildcard(DATE_LEN, |c, i| match i {
+                            4 | 7 => c == b'-',
+                            _ => c.is_ascii_digit(),
+                        }),
+                    },
+                },
+            })
+            .collect()
+    }
+}
+
+fn matches_pieces(mut name: &[u8], pieces: &[Piece]) -> bool {
+    for piece in pieces {
+        match piece {
+            Piece::Bytes(b) => match name.strip_prefix(b.as_slice()) {
+                Some(rest) => name = rest,
+                None => return false,
+            },
+            Piece::Wildcard(len, accepts) => {
+                if name.len() < *len
+                    || !name[..*len].iter().enumerate().all(|(i, &c)| accepts(c, i))
+                {
+                    return false;
+                }
+
+                name = &name[*len..];
+            }
+        }
+    }
+
+    name.is_empty()
+}
+
+fn is_separator(c: u8) -> bool {
+    c == b'/' || (cfg!(windows) && c == b'\\')
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// Formats the UTC date of `time` as `YYYY-MM-DD`.
+fn format_date(time: SystemTime) -> String {
+    let days = match time.duration_since(UNIX_EPOCH) {
+        Ok(d) => (d.as_secs() / 86_400) as i64,
+        Err(e) => -(e.duration().as_secs().div_ceil(86_400) as i64),
+    };
+
+    // Converts days since 1970-01-01 to a civil date (proleptic Gregorian calendar)
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::time::Duration;
+
+    fn values<'a>(file: &'a Path, set_id: Option<&'a [u8; 16]>) -> Values<'a> {
+        Values {
+            file,
+            dir: None,
+            index: 1,
+            set_id,
+            date: Some(UNIX_EPOCH + Duration::from_secs(1_700_000_000)),
+        }
+    }
+
+    #[test]
+    fn test_expand() {
+        let t = NameTemplate::parse("{dir}/{stem}.{index}.{{{ext}}}.{date}.{set}").unwrap();
+        let file = Path::new("some/dir/secret.txt");
+
+        assert_eq!(
+            t.expand(&values(file, Some(&[0xab; 16]))),
+            PathBuf::from(format!(
+                "some/dir/secret.1.{{txt}}.2023-11-14.{}",
+                "ab".repeat(16)
+            ))
+        );
+
+        let t = NameTemplate::parse("{dir}/{name}.share").unwrap();
+        assert_eq!(
+            t.expand(&values(Path::new("secret"), None)),
+            PathBuf::from("./secret.share")
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(NameTemplate::parse("{stem").is_err());
+        assert!(NameTemplate::parse("{unknown}").is_err());
+        assert!(NameTemplate::parse("stem}").is_err());
+    }
+
+    #[test]
+    fn test_format_date() {
+        assert_eq!(format_date(UNIX_EPOCH), "1970-01-01");
+        assert_eq!(
+            format_date(UNIX_EPOCH + Duration::from_secs(951_782_400)),
+            "2000-02-29"
+        );
+        assert_eq!(
+            format_date(UNIX_EPOCH - Duration::from_secs(1)),
+            "1969-12-31"
+        );
+    }
+
+    #[test]
+    fn test_matches_pieces() {
+        let t = NameTemplate::parse("{stem}.{date}.{index}").unwrap();
+        let file = Path::new("secret.txt");
+        let pieces = t.pieces(&Values {
+            date: None,
+            ..values(file, None)
+        });
+
+        assert!(matches_pieces(b"secret.2023-11-14.1", &pieces));
+        assert!(!matches_pieces(b"secret.2023-11-14.0", &pieces));
+        assert!(!matches_pieces(b"secret.2023_11_14.1", &pieces));
+    }
+}
@@ -0,0 +1,311 @@
+//! Shares split into volumes of a fixed maximum size.
+//!
+//! The volumes of a share at `path` are named `path.001`, `path.002`, and
+//! so on. Each volume starts with a small header, followed by the next
+//! piece of the share file:
+//!
+//! ```text
+//! magic | set id | share index | volume number (u32) | last volume flag
+//! ```
+//!
+//! The header allows to tell volumes that are missing, renamed or belong to
+//! a different share apart.
+
+use std::fs::File;
+use std::io::{self, ErrorKind, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use crate::fs::{extend_extension, io_error, remove_file};
+use crate::share::Header;
+use crate::stream::{resolve_seek, Sink, Source};
+use crate::{Error, IoOperation};
+
+const MAGIC: &[u8; 8] = b"OTPVOLUM";
+
+/// Length of the volume header
+pub const VOLUME_HEADER_LEN: usize = 8 + 16 + 1 + 4 + 1;
+
+/// Position of the last volume flag within the header
+const LAST_FLAG_POS: u64 = VOLUME_HEADER_LEN as u64 - 1;
+
+/// Returns the path of the volume with the given number, starting at 1.
+pub fn volume_path(path: &Path, number: u32) -> PathBuf {
+    extend_extension(path, &format!("{number:03}"))
+}
+
+/// Returns whether the share at `path` is split into volumes.
+pub fn is_split(path: &Path) -> bool {
+    !path.exists() && volume_path(path, 1).exists()
+}
+
+/// Removes a share, which may be split into volumes.
+pub fn remove_share(path: &Path) -> Result<(), Error> {
+    if !is_split(path) {
+        return remove_file(path);
+    }
+
+    for number in 1.. {
+        let volume = volume_path(path, number);
+
+        if !volume.exists() {
+            break;
+        }
+
+        remove_file(&volume)?;
+    }
+
+    Ok(())
+}
+
+/// Writes a share as volumes of at most `volume_size` bytes each.
+pub struct VolumeWriter {
+    path: PathBuf,
+    id: [u8; 17],
+    capacity: u64,
+    number: u32,
+    file: File,
+    written: u64,
+}
+
+impl VolumeWriter {
+    /// Creates the first volume. `volume_size` must be larger than
+    /// [`VOLUME_HEADER_LEN`].
+    pub fn new(path: &Path, header: &Header, volume_size: u64) -> io::Result<Self> {
+        let mut id = [0u8; 17];
+        id[..16].copy_from_slice(&header.set_id);
+        id[16] = header.index;
+
+        Ok(Self {
+            path: path.to_owned(),
+            id,
+            capacity: volume_size - VOLUME_HEADER_LEN as u64,
+            number: 1,
+            file: Self::create_volume(path, &id, 1)?,
+            written: 0,
+        })
+    }
+
+    fn create_volume(path: &Path, id: &[u8; 17], number: u32) -> io::Result<File> {
+        let mut file = File::create(volume_path(path, number))?;
+
+        let mut header = MAGIC.to_vec();
+        header.extend_from_slice(id);
+        header.extend_from_slice(&number.to_le_bytes());
+        header.push(0);
+        file.write_all(&header)?;
+
+        Ok(file)
+    }
+}
+
+impl Write for VolumeWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        if self.written == self.capacity {
+            self.number += 1;
+            self.file = Self::create_volume(&self.path, &self.id, self.number)?;
+            self.written = 0;
+        }
+
+        let max = (self.capacity - self.written)
+            .try_into()
+            .unwrap_or(usize::MAX);
+        let bytes = self.file.write(&buf[..buf.len().min(max)])?;
+        self.written += bytes as u64;
+
+        Ok(bytes)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+impl Sink for VolumeWriter {
+    /// Marks the current volume as the last one.
+    fn finish(mut self: Box<Self>) -> io::Result<()> {
+        self.file.seek(SeekFrom::Start(LAST_FLAG_POS))?;
+        self.file.write_all(&[1])?;
+        self.file.flush()
+    }
+}
+
+/// Reads the volumes of a share as one continuous file.
+pub struct VolumeReader {
+    /// Volumes with the length of their data, without the header
+    volumes: Vec<(File, u64)>,
+    len: u64,
+    pos: u64,
+}
+
+impl VolumeReader {
+    /// Opens all volumes of the share at `path` and checks that none of
+    /// them are missing or out of order.
+    pub fn open(path: &Path) -> Result<Self, Error> {
+        let mut volumes = Vec::new();
+        let mut share_id = None;
+
+        for number in 1.. {
+            let volume = volume_path(path, number);
+            let io_err = |error| io_error(&volume, IoOperation::Read, error);
+            let invalid = |reason: &str| {
+                Error::InvalidInput(format!("{volume:?} is not a valid volume: {reason}"))
+            };
+
+            let mut file = match File::open(&volume) {
+                Ok(file) => file,
+                Err(e) if e.kind() == ErrorKind::NotFound => {
+                    return Err(Error::InvalidInput(format!("Volume {volume:?} is missing")));
+                }
+                Err(e) => return Err(io_err(e)),
+            };
+
+            let mut header = [0u8; VOLUME_HEADER_LEN];
+            file.read_exact(&mut header).map_err(|e| match e.kind() {
+                ErrorKind::UnexpectedEof => invalid("truncated header"),
+                _ => io_err(e),
+            })?;
+
+            if &header[..8] != MAGIC {
+                return Err(invalid("missing header"));
+            }
+
+            let id: [u8; 17] = header[8..25].try_into().unwrap();
+            if *share_id.get_or_insert(id) != id {
+                return Err(Error::InvalidInput(format!(
+                    "Volume {volume:?} belongs to a different share"
+                )));
+            }
+
+            let stored = u32::from_le_bytes(header[25..29].try_into().unwrap());
+            if stored != number {
+                return Err(Error::InvalidInput(format!(
+                    "Volume {volume:?} is out of order, it contains volume {stored}"
+                )));
+            }
+
+            let len = file.metadata().map_err(io_err)?.len() - VOLUME_HEADER_LEN as u64;
+            volumes.push((file, len));
+
+            if header[VOLUME_HEADER_LEN - 1] == 1 {
+                break;
+            }
+        }
+
+        Ok(Self {
+            len: volumes.iter().map(|(_, len)| len).sum(),
+            volumes,
+            pos: 0,
+        })
+    }
+
+    /// Length of the share
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+}
+
+impl Read for VolumeReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut start = 0;
+
+        for (file, len) in &mut self.volumes {
+            if self.pos < start + *len {
+                let offset = self.pos - start;
+                let max = buf
+                    .len()
+                    .min((*len - offset).try_into().unwrap_or(usize::MAX));
+
+                file.seek(SeekFrom::Start(VOLUME_HEADER_LEN as u64 + offset))?;
+                let bytes = file.read(&mut buf[..max])?;
+                self.pos += bytes as u64;
+
+                return Ok(bytes);
+            }
+
+            start += *len;
+        }
+
+        Ok(0)
+    }
+}
+
+impl Seek for VolumeReader {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.pos = resolve_seek(pos, self.pos, self.len)?;
+
+        Ok(self.pos)
+    }
+}
+
+impl Source for VolumeReader {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn write_volumes(dir: &Path, data: &[u8], volume_size: u64) -> PathBuf {
+        let path = dir.join("share");
+        let header = Header::new_pair(&crate::Config::new("file")).0;
+
+        let mut writer = Box::new(VolumeWriter::new(&path, &header, volume_size).unwrap());
+        writer.write_all(data).unwrap();
+        writer.finish().unwrap();
+
+        path
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let dir = std::env::temp_dir().join("onetime-cli-test-volumes");
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let data: Vec<u8> = (0..1000).map(|i| (i % 251) as u8).collect();
+        let path = write_volumes(&dir, &data, VOLUME_HEADER_LEN as u64 + 100);
+
+        assert!(is_split(&path));
+        assert!(volume_path(&path, 10).exists());
+        assert!(!volume_path(&path, 11).exists());
+
+        let mut reader = VolumeReader::open(&path).unwrap();
+        assert_eq!(reader.len(), 1000);
+
+        let mut read = Vec::new();
+        reader.read_to_end(&mut read).unwrap();
+        assert_eq!(read, data);
+
+        reader.seek(SeekFrom::Start(950)).unwrap();
+        let mut end = [0u8; 50];
+        reader.read_exact(&mut end).unwrap();
+        assert_eq!(end, data[950..]);
+
+        // Swapped volumes
+        std::fs::rename(volume_path(&path, 3), dir.join("tmp")).unwrap();
+        std::fs::rename(volume_path(&path, 4), volume_path(&path, 3)).unwrap();
+        std::fs::rename(dir.join("tmp"), volume_path(&path, 4)).unwrap();
+        assert_eq!(
+            VolumeReader::open(&path).err().unwrap().to_string(),
+            format!(
+                "Invalid input: Volume {:?} is out of order, it contains volume 4",
+                volume_path(&path, 3)
+            )
+        );
+
+        // Missing last volume
+        remove_share(&path).unwrap();
+        let path = write_volumes(&dir, &data, VOLUME_HEADER_LEN as u64 + 100);
+        std::fs::remove_file(volume_path(&path, 10)).unwrap();
+        assert_eq!(
+            VolumeReader::open(&path).err().unwrap().to_string(),
+            format!(
+                "Invalid input: Volume {:?} is missing",
+                volume_path(&path, 10)
+            )
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
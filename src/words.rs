@@ -0,0 +1,225 @@
+//! Encoding of small shares as words, for writing them down by hand.
+//!
+//! The data is split into groups of 11 bits, each of which selects one word
+//! of the 2048 words of the BIP39 English word list. A single `1` bit and
+//! as many `0` bits as needed pad the data to a multiple of 11 bits. The
+//! last word is a checksum: the first 11 bits of the SHA-256 digest of the
+//! data.
+//!
+//! All words of the list differ in their first four letters, so a word is
+//! recognized by these alone. Typos after the fourth letter don't matter.
+
+use std::io::{self, Write};
+use std::sync::OnceLock;
+
+use sha2::{Digest, Sha256};
+
+use crate::stream::Sink;
+
+/// Maximum number of bytes a share in this format may contain
+pub const MAX_DATA_LEN: usize = 1024;
+
+const BITS_PER_WORD: usize = 11;
+const WORDS_PER_LINE: usize = 6;
+
+/// Number of letters that identify a word
+const PREFIX_LEN: usize = 4;
+
+fn word_list() -> &'static [&'static str] {
+    static WORDS: OnceLock<Vec<&'static str>> = OnceLock::new();
+
+    WORDS.get_or_init(|| include_str!("words.txt").lines().collect())
+}
+
+fn checksum(data: &[u8]) -> usize {
+    let digest = Sha256::digest(data);
+
+    ((digest[0] as usize) << 3) | (digest[1] as usize >> 5)
+}
+
+/// Encodes `data` as words, including the checksum word.
+pub fn encode(data: &[u8]) -> Vec<&'static str> {
+    let list = word_list();
+
+    let mut bits: Vec<bool> = data
+        .iter()
+        .flat_map(|byte| (0..8).rev().map(move |i| byte >> i & 1 == 1))
+        .collect();
+
+    bits.push(true);
+    while bits.len() % BITS_PER_WORD != 0 {
+        bits.push(false);
+    }
+
+    let mut words: Vec<&str> = bits
+        .chunks(BITS_PER_WORD)
+        .map(|chunk| list[chunk.iter().fold(0, |acc, &bit| acc << 1 | bit as usize)])
+        .collect();
+
+    words.push(list[checksum(data)]);
+
+    words
+}
+
+/// Looks up a single word, allowing typos after its unique prefix.
+fn lookup(word: &str) -> Option<usize> {
+    let list = word_list();
+    let word = word.to_ascii_lowercase();
+
+    if let Ok(index) = list.binary_search(&word.as_str()) {
+        return Some(index);
+    }
+
+    let prefix: String = word.chars().take(PREFIX_LEN).collect();
+    let mut candidates = (0..list.len()).filter(|&i| list[i].starts_with(&prefix));
+
+    match (candidates.next(), candidates.next()) {
+        (Some(index), None) => Some(index),
+        _ => None,
+    }
+}
+
+/// Decodes words produced by [`encode`] and checks the checksum word.
+pub fn decode(text: &str) -> Result<Vec<u8>, String> {
+    let indices = text
+        .split_whitespace()
+        .enumerate()
+        .map(|(i, word)| {
+            lookup(word).ok_or_else(|| format!("unknown word {word:?} at position {}", i + 1))
+        })
+        .collect::<Result<Vec<usize>, String>>()?;
+
+    let (&check, indices) = indices
+        .split_last()
+        .filter(|(_, rest)| !rest.is_empty())
+        .ok_or_else(|| "not enough words".to_string())?;
+
+    let mut bits: Vec<bool> = indices
+        .iter()
+        .flat_map(|index| (0..BITS_PER_WORD).rev().map(move |i| index >> i & 1 == 1))
+        .collect();
+
+    // Remove the padding
+    while bits.last() == Some(&false) {
+        bits.pop();
+    }
+    if bits.pop() != Some(true) || bits.len() % 8 != 0 {
+        return Err("invalid padding, a word is missing or wrong".to_string());
+    }
+
+    let data: Vec<u8> = bits
+        .chunks(8)
+        .map(|chunk| chunk.iter().fold(0, |acc, &bit| acc << 1 | bit as u8))
+        .collect();
+
+    if checksum(&data) != check {
+        return Err("checksum mismatch, a word is missing or wrong".to_string());
+    }
+
+    Ok(data)
+}
+
+/// Returns whether `start` could be the beginning of a share in this format.
+pub fn looks_like_words(start: &[u8]) -> bool {
+    start.iter().any(u8::is_ascii_alphabetic)
+        && start
+            .iter()
+            .all(|c| c.is_ascii_alphabetic() || c.is_ascii_whitespace())
+}
+
+/// Collects the data passed to it and writes it as words when finished.
+pub struct WordsWriter<W: Sink> {
+    inner: W,
+    buf: Vec<u8>,
+}
+
+impl<W: Sink> WordsWriter<W> {
+    pub fn new(inner: W) -> Self {
+        Self {
+            inner,
+            buf: Vec::new(),
+        }
+    }
+}
+
+impl<W: Sink> Write for WordsWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.buf.len() + buf.len() > MAX_DATA_LEN {
+            return Err(io::Error::other(format!(
+                "the word list format holds at most {MAX_DATA_LEN} bytes"
+            )));
+        }
+
+        self.buf.extend_from_slice(buf);
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<W: Sink> Sink for WordsWriter<W> {
+    fn finish(mut self: Box<Self>) -> io::Result<()> {
+        for line in encode(&self.buf).chunks(WORDS_PER_LINE) {
+            writeln!(self.inner, "{}", line.join(" "))?;
+        }
+
+        Box::new(self.inner).finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_word_list() {
+        let list = word_list();
+
+        assert_eq!(list.len(), 2048);
+        assert!(list.windows(2).all(|w| w[0] < w[1]));
+        assert_eq!(lookup("abandon"), Some(0));
+        assert_eq!(lookup("zoo"), Some(2047));
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        for len in 0..40 {
+            let data: Vec<u8> = (0..len).map(|i| (i * 37 + 11) as u8).collect();
+            let words = encode(&data);
+
+            assert_eq!(decode(&words.join(" ")), Ok(data));
+        }
+    }
+
+    #[test]
+    fn test_typos() {
+        let data = b"correct horse battery staple".to_vec();
+        let mut words: Vec<String> = encode(&data).iter().map(|w| w.to_string()).collect();
+
+        // Typos after the fourth letter and different case are tolerated
+        for word in words.iter_mut().filter(|w| w.len() > 4) {
+            word.truncate(4);
+            word.push_str("xx");
+        }
+        words[0] = words[0].to_uppercase();
+        assert_eq!(decode(&words.join("\n")), Ok(data.clone()));
+
+        words.swap(0, 1);
+        assert!(decode(&words.join(" ")).is_err());
+
+        assert_eq!(
+            decode("abandon qqqq zoo"),
+            Err("unknown word \"qqqq\" at position 2".to_string())
+        );
+    }
+
+    #[test]
+    fn test_looks_like_words() {
+        assert!(looks_like_words(b"abandon ability\nzoo"));
+        assert!(!looks_like_words(b"OTPSHARE\x01"));
+        assert!(!looks_like_words(b" \n"));
+    }
+}
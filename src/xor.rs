@@ -0,0 +1,152 @@
+//! The one-time pad on byte slices.
+//!
+//! Nothing in here needs `std`, so these functions are also available
+//! without the `std` feature, e.g. on embedded targets. Randomness comes
+//! from any [`RngCore`] that is also a [`CryptoRng`].
+
+use rand::{CryptoRng, RngCore};
+
+use crate::Error;
+
+/// Encrypts data using the one-time pad, with random bytes from the
+/// thread-local generator of the operating system.
+///
+/// ## Error
+/// Will return an [`Error::InvalidBufferSizes`] if either
+/// of `buf_out1` or `buf_out2` is smaller than `buf_in`.
+///
+/// ## Example
+/// ```
+/// use onetime_cli::encrypt;
+///
+/// # fn main() -> Result<(), onetime_cli::Error> {
+/// let data: [u8; 10] = [1,2,3,4,5,6,7,8,9,10];
+/// let mut out1 = [0u8; 10];
+/// let mut out2 = [0u8; 10];
+///
+/// encrypt(&data, &mut out1, &mut out2)?;
+/// // The encrypted parts are stored in `out1` and `out2`.
+///
+/// println!("{:?}", out1);
+/// println!("{:?}", out2);
+/// # Ok(())
+/// # }
+/// ```
+#[cfg(feature = "std")]
+pub fn encrypt(buf_in: &[u8], buf_out1: &mut [u8], buf_out2: &mut [u8]) -> Result<(), Error> {
+    encrypt_with_rng(&mut rand::thread_rng(), buf_in, buf_out1, buf_out2)
+}
+
+/// Encrypts data using the one-time pad, with random bytes from `rng`.
+///
+/// This is [`encrypt`] for targets without `std`, where `rng` would
+/// typically be a hardware random number generator.
+///
+/// ## Error
+/// Will return an [`Error::InvalidBufferSizes`] if either
+/// of `buf_out1` or `buf_out2` is smaller than `buf_in`.
+pub fn encrypt_with_rng<R: RngCore + CryptoRng + ?Sized>(
+    rng: &mut R,
+    buf_in: &[u8],
+    buf_out1: &mut [u8],
+    buf_out2: &mut [u8],
+) -> Result<(), Error> {
+    if (buf_out1.len() < buf_in.len()) || (buf_out2.len() < buf_in.len()) {
+        return Err(Error::InvalidBufferSizes);
+    }
+
+    rng.fill_bytes(&mut buf_out1[..buf_in.len()]);
+
+    for i in 0..buf_in.len() {
+        buf_out2[i] = buf_in[i] ^ buf_out1[i];
+    }
+
+    Ok(())
+}
+
+/// Decrypts data using the one-time pad.
+///
+/// `buf_in1` and `buf_in2` must have the same size.
+///
+/// ## Error
+/// Will return an [`Error::InvalidBufferSizes`] if:
+///   * `buf_in1` and `buf_in2` don't have the same size.
+///   * `buf_out` is smaller than the `buf_in`s.
+///
+/// ## Example
+/// ```
+/// use onetime_cli::decrypt;
+///
+/// # fn main() -> Result<(), onetime_cli::Error> {
+/// let in1: [u8; 10] = [1, 2, 3, 4, 5, 6, 7, 8, 9, 10];
+/// let in2: [u8; 10] = [26, 89, 3, 93, 78, 12, 60, 23, 4, 71];
+/// let mut data = [0u8; 10];
+///
+/// decrypt(&in1, &in2, &mut data)?;
+/// // The decrypted data is stored in `data`.
+///
+/// assert_eq!(data, [27, 91, 0, 89, 75, 10, 59, 31, 13, 77]);
+/// # Ok(())
+/// # }
+/// ```
+pub fn decrypt(buf_in1: &[u8], buf_in2: &[u8], buf_out: &mut [u8]) -> Result<(), Error> {
+    if (buf_in1.len() != buf_in2.len()) || (buf_out.len() < buf_in1.len()) {
+        return Err(Error::InvalidBufferSizes);
+    }
+
+    for i in 0..buf_in1.len() {
+        buf_out[i] = buf_in1[i] ^ buf_in2[i];
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use rand::rngs::mock::StepRng;
+
+    #[test]
+    fn test_encrypt_with_rng() {
+        let data = [10u8, 20, 30, 40];
+        let mut out1 = [0u8; 4];
+        let mut out2 = [0u8; 4];
+        let mut rng = TestRng(StepRng::new(1, 1));
+
+        encrypt_with_rng(&mut rng, &data, &mut out1, &mut out2).unwrap();
+        assert_ne!(out2, data);
+
+        let mut decrypted = [0u8; 4];
+        decrypt(&out1, &out2, &mut decrypted).unwrap();
+        assert_eq!(decrypted, data);
+
+        assert!(matches!(
+            encrypt_with_rng(&mut rng, &data, &mut [0; 3], &mut out2),
+            Err(Error::InvalidBufferSizes)
+        ));
+    }
+
+    /// [`StepRng`] is not a [`CryptoRng`], which is fine for a test.
+    struct TestRng(StepRng);
+
+    impl RngCore for TestRng {
+        fn next_u32(&mut self) -> u32 {
+            self.0.next_u32()
+        }
+
+        fn next_u64(&mut self) -> u64 {
+            self.0.next_u64()
+        }
+
+        fn fill_bytes(&mut self, dest: &mut [u8]) {
+            self.0.fill_bytes(dest)
+        }
+
+        fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+            self.0.try_fill_bytes(dest)
+        }
+    }
+
+    impl CryptoRng for TestRng {}
+}
@@ -1,9 +1,14 @@
 use assert_cmd::Command;
 use md5_rs::Context;
+use predicates::boolean::PredicateBooleanExt;
 use scopeguard::defer;
+use sha2::{Digest, Sha256};
 use std::ffi::OsString;
 use std::fs::File;
-use std::fs::{copy, create_dir, create_dir_all, metadata, read_dir, remove_dir_all, remove_file};
+use std::fs::{
+    copy, create_dir, create_dir_all, metadata, read, read_dir, remove_dir_all, remove_file,
+    rename, write,
+};
 use std::io::{ErrorKind, Read};
 
 const CARGO_BIN_NAME: &str = "onetime-cli";
@@ -308,10 +313,7 @@ fn test_encrypt_decrypt_quiet() {
         .arg("file1.txt")
         .assert();
 
-    assert
-        .success()
-        .stdout("")
-        .stderr("");
+    assert.success().stdout("").stderr("");
 
     remove_file("./tests/files/test_encrypt_decrypt_quiet/file1.txt").unwrap();
 
@@ -324,12 +326,1411 @@ fn test_encrypt_decrypt_quiet() {
         .arg("file1.txt")
         .assert();
 
+    assert.success().stdout("").stderr("");
+
+    let md5_now = get_md5_sum("./tests/files/test_encrypt_decrypt_quiet/file1.txt").unwrap();
+
+    assert_eq!(original_md5, md5_now);
+}
+
+#[test]
+fn test_decrypt_detects_modified_share() {
+    copy_files("files_1", "test_decrypt_detects_modified_share").unwrap();
+
+    defer! {
+        clear_files("test_decrypt_detects_modified_share")
+    }
+
+    // Encrypt command
+    let mut cmd = Command::cargo_bin(CARGO_BIN_NAME).unwrap();
+    let assert = cmd
+        .current_dir("./tests/files/test_decrypt_detects_modified_share")
+        .arg("encrypt")
+        .arg("--rm")
+        .arg("file1.txt")
+        .assert();
+
+    assert.success();
+
+    let share = format!("{FILES_DIR}/test_decrypt_detects_modified_share/file1.txt.otp.0");
+    let mut data = read(&share).unwrap();
+    data[100] ^= 0x01;
+    write(&share, data).unwrap();
+
+    // Decrypt command
+    let mut cmd = Command::cargo_bin(CARGO_BIN_NAME).unwrap();
+    let assert = cmd
+        .current_dir("./tests/files/test_decrypt_detects_modified_share")
+        .arg("decrypt")
+        .arg("file1.txt")
+        .assert();
+
     assert
-        .success()
+        .failure()
+        .code(5)
         .stdout("")
+        .stderr(predicates::str::contains(
+            "Damaged data in \"file1.txt.otp.0\" at bytes 0..5652",
+        ));
+
+    assert_path_not_found(&format!(
+        "{FILES_DIR}/test_decrypt_detects_modified_share/file1.txt"
+    ));
+}
+
+#[test]
+fn test_decrypt_repairs_damaged_share() {
+    copy_files("files_1", "test_decrypt_repairs_damaged_share").unwrap();
+
+    defer! {
+        clear_files("test_decrypt_repairs_damaged_share")
+    }
+
+    let original_md5 =
+        get_md5_sum("./tests/files/test_decrypt_repairs_damaged_share/file1.txt").unwrap();
+
+    // Encrypt command
+    let mut cmd = Command::cargo_bin(CARGO_BIN_NAME).unwrap();
+    let assert = cmd
+        .current_dir("./tests/files/test_decrypt_repairs_damaged_share")
+        .arg("encrypt")
+        .args(["--fec", "2"])
+        .args(["--fec-block-size", "64"])
+        .arg("--rm")
+        .arg("file1.txt")
+        .assert();
+
+    assert
+        .success()
+        .stdout("Successfully encrypted file1.txt\n")
         .stderr("");
 
-    let md5_now = get_md5_sum("./tests/files/test_encrypt_decrypt_quiet/file1.txt").unwrap();
+    let share = format!("{FILES_DIR}/test_decrypt_repairs_damaged_share/file1.txt.otp.1");
+    let mut data = read(&share).unwrap();
+    data[100] ^= 0xff;
+    data[1000] ^= 0x01;
+    write(&share, data).unwrap();
+
+    // Decrypt command
+    let mut cmd = Command::cargo_bin(CARGO_BIN_NAME).unwrap();
+    let assert = cmd
+        .current_dir("./tests/files/test_decrypt_repairs_damaged_share")
+        .arg("decrypt")
+        .arg("file1.txt")
+        .assert();
+
+    assert
+        .success()
+        .stdout("Repaired 2 damaged block(s)\nSuccessfully decrypted file1.txt\n")
+        .stderr("");
+
+    let md5_now =
+        get_md5_sum("./tests/files/test_decrypt_repairs_damaged_share/file1.txt").unwrap();
 
     assert_eq!(original_md5, md5_now);
 }
+
+#[test]
+fn test_decrypt_unrepairable_share() {
+    copy_files("files_1", "test_decrypt_unrepairable_share").unwrap();
+
+    defer! {
+        clear_files("test_decrypt_unrepairable_share")
+    }
+
+    let dir = format!("{FILES_DIR}/test_decrypt_unrepairable_share");
+
+    // Encrypt command
+    let mut cmd = Command::cargo_bin(CARGO_BIN_NAME).unwrap();
+    let assert = cmd
+        .current_dir(&dir)
+        .arg("encrypt")
+        .args(["--fec", "2"])
+        .args(["--fec-block-size", "64"])
+        .arg("file1.txt")
+        .assert();
+
+    assert.success();
+
+    // Three blocks of the first group, one more than can be repaired. The
+    // header and its copy take less than 100 bytes, stored blocks are 72 bytes long.
+    let share = format!("{dir}/file1.txt.otp.1");
+    let mut data = read(&share).unwrap();
+    data[200] ^= 0x01;
+    data[272] ^= 0x01;
+    data[344] ^= 0x01;
+    write(&share, data).unwrap();
+
+    // Decrypt command
+    let mut cmd = Command::cargo_bin(CARGO_BIN_NAME).unwrap();
+    let assert = cmd
+        .current_dir(&dir)
+        .args(["decrypt", "-o", "decrypted.txt", "file1.txt"])
+        .assert();
+
+    assert.failure().code(5).stderr(predicates::str::contains(
+        "Damaged data in \"file1.txt.otp.1\"",
+    ));
+}
+
+#[test]
+fn test_verify_locates_damage() {
+    copy_files("files_1", "test_verify_locates_damage").unwrap();
+
+    defer! {
+        clear_files("test_verify_locates_damage")
+    }
+
+    // Encrypt command
+    let mut cmd = Command::cargo_bin(CARGO_BIN_NAME).unwrap();
+    let assert = cmd
+        .current_dir("./tests/files/test_verify_locates_damage")
+        .arg("encrypt")
+        .args(["-b", "1024"])
+        .arg("file1.txt")
+        .assert();
+
+    assert.success();
+
+    // Verify command, intact
+    let mut cmd = Command::cargo_bin(CARGO_BIN_NAME).unwrap();
+    let assert = cmd
+        .current_dir("./tests/files/test_verify_locates_damage")
+        .arg("verify")
+        .arg("file1.txt")
+        .assert();
+
+    assert
+        .success()
+        .stdout("file1.txt.otp.0: OK\nfile1.txt.otp.1: OK\nDecrypted data: OK\n")
+        .stderr("");
+
+    // The header and its copy are 34 bytes long each
+    let share = format!("{FILES_DIR}/test_verify_locates_damage/file1.txt.otp.1");
+    let mut data = read(&share).unwrap();
+    data[68 + 2000] ^= 0x01;
+    data[68 + 5000] ^= 0x01;
+    write(&share, data).unwrap();
+
+    // Verify command, damaged
+    let mut cmd = Command::cargo_bin(CARGO_BIN_NAME).unwrap();
+    let assert = cmd
+        .current_dir("./tests/files/test_verify_locates_damage")
+        .arg("verify")
+        .arg("--locate")
+        .arg("file1.txt")
+        .assert();
+
+    assert
+        .failure()
+        .code(5)
+        .stdout(
+            "file1.txt.otp.0: OK\n\
+            file1.txt.otp.1: damaged\n  \
+            bytes 1024..2048 of the encrypted data\n  \
+            bytes 4096..5120 of the encrypted data\n\
+            Decrypted data: does not match the original\n",
+        )
+        .stderr(predicates::str::contains("Verification failed"));
+
+    // Decrypt command, zero-filling the damaged chunks
+    let mut cmd = Command::cargo_bin(CARGO_BIN_NAME).unwrap();
+    let assert = cmd
+        .current_dir("./tests/files/test_verify_locates_damage")
+        .arg("decrypt")
+        .args(["--on-damage", "zero"])
+        .arg("--rm")
+        .arg("file1.txt")
+        .assert();
+
+    assert
+        .success()
+        .stdout("Successfully decrypted file1.txt\n")
+        .stderr(
+            "Warning: bytes 1024..2048 of file1.txt are damaged\n\
+            Warning: bytes 4096..5120 of file1.txt are damaged\n",
+        );
+
+    let original = read(format!("{FILES_ORIG_DIR}/files_1/file1.txt")).unwrap();
+    let decrypted = read(format!("{FILES_DIR}/test_verify_locates_damage/file1.txt")).unwrap();
+
+    assert_eq!(decrypted.len(), original.len());
+    assert_eq!(decrypted[..1024], original[..1024]);
+    assert!(decrypted[1024..2048].iter().all(|b| *b == 0));
+    assert_eq!(decrypted[2048..4096], original[2048..4096]);
+    assert_path_exists(&format!(
+        "{FILES_DIR}/test_verify_locates_damage/file1.txt.otp.1"
+    ));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_preserve_file_attributes() {
+    use std::fs::{set_permissions, FileTimes, Permissions};
+    use std::os::unix::fs::PermissionsExt;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    copy_files("files_1", "test_preserve_file_attributes").unwrap();
+
+    defer! {
+        clear_files("test_preserve_file_attributes")
+    }
+
+    let path = format!("{FILES_DIR}/test_preserve_file_attributes/file1.txt");
+    let mtime = UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+
+    File::options()
+        .write(true)
+        .open(&path)
+        .unwrap()
+        .set_times(FileTimes::new().set_modified(mtime))
+        .unwrap();
+    set_permissions(&path, Permissions::from_mode(0o640)).unwrap();
+
+    for decrypt_args in [vec![], vec!["--no-preserve"]] {
+        // Encrypt command
+        let mut cmd = Command::cargo_bin(CARGO_BIN_NAME).unwrap();
+        let assert = cmd
+            .current_dir("./tests/files/test_preserve_file_attributes")
+            .arg("encrypt")
+            .arg("--rm")
+            .arg("file1.txt")
+            .assert();
+
+        assert.success();
+
+        // Decrypt command
+        let mut cmd = Command::cargo_bin(CARGO_BIN_NAME).unwrap();
+        let assert = cmd
+            .current_dir("./tests/files/test_preserve_file_attributes")
+            .arg("decrypt")
+            .args(&decrypt_args)
+            .arg("--rm")
+            .arg("file1.txt")
+            .assert();
+
+        assert.success();
+
+        let meta = metadata(&path).unwrap();
+
+        if decrypt_args.is_empty() {
+            assert_eq!(meta.permissions().mode() & 0o7777, 0o640);
+            assert_eq!(meta.modified().unwrap(), mtime);
+        } else {
+            assert_ne!(meta.modified().unwrap(), mtime);
+        }
+    }
+}
+
+#[test]
+fn test_decrypt_from_encrypted_files() {
+    copy_files("files_1", "test_decrypt_from_encrypted_files").unwrap();
+
+    defer! {
+        clear_files("test_decrypt_from_encrypted_files")
+    }
+
+    let dir = format!("{FILES_DIR}/test_decrypt_from_encrypted_files");
+    let original_md5 = get_md5_sum(&format!("{dir}/file1.txt")).unwrap();
+
+    // Encrypt command
+    let mut cmd = Command::cargo_bin(CARGO_BIN_NAME).unwrap();
+    let assert = cmd
+        .current_dir(&dir)
+        .arg("encrypt")
+        .arg("--rm")
+        .arg("file1.txt")
+        .assert();
+
+    assert.success();
+
+    rename(format!("{dir}/file1.txt.otp.0"), format!("{dir}/a.share")).unwrap();
+    rename(format!("{dir}/file1.txt.otp.1"), format!("{dir}/b.share")).unwrap();
+
+    // Decrypt command, restoring the original name
+    let mut cmd = Command::cargo_bin(CARGO_BIN_NAME).unwrap();
+    let assert = cmd
+        .current_dir(&dir)
+        .arg("decrypt")
+        .args(["b.share", "a.share"])
+        .assert();
+
+    assert
+        .success()
+        .stdout("Successfully decrypted file1.txt\n")
+        .stderr("");
+
+    assert_eq!(
+        get_md5_sum(&format!("{dir}/file1.txt")).unwrap(),
+        original_md5
+    );
+
+    // Decrypt command, with explicit output file
+    let mut cmd = Command::cargo_bin(CARGO_BIN_NAME).unwrap();
+    let assert = cmd
+        .current_dir(&dir)
+        .arg("decrypt")
+        .args(["-o", "restored.txt"])
+        .arg("--rm")
+        .args(["a.share", "b.share"])
+        .assert();
+
+    assert
+        .success()
+        .stdout("Successfully decrypted restored.txt\n")
+        .stderr("");
+
+    assert_eq!(
+        get_md5_sum(&format!("{dir}/restored.txt")).unwrap(),
+        original_md5
+    );
+    assert_path_not_found(&format!("{dir}/a.share"));
+    assert_path_not_found(&format!("{dir}/b.share"));
+}
+
+#[test]
+fn test_encrypt_decrypt_with_separate_directories() {
+    copy_files("files_1", "test_encrypt_decrypt_with_separate_directories").unwrap();
+
+    defer! {
+        clear_files("test_encrypt_decrypt_with_separate_directories")
+    }
+
+    let dir = format!("{FILES_DIR}/test_encrypt_decrypt_with_separate_directories");
+    let original_md5 = get_md5_sum(&format!("{dir}/file1.txt")).unwrap();
+
+    create_dir(format!("{dir}/stick1")).unwrap();
+    create_dir(format!("{dir}/stick2")).unwrap();
+
+    // Encrypt command
+    let mut cmd = Command::cargo_bin(CARGO_BIN_NAME).unwrap();
+    let assert = cmd
+        .current_dir(&dir)
+        .arg("encrypt")
+        .args(["--out1-dir", "stick1"])
+        .args(["--out2-dir", "stick2"])
+        .arg("--rm")
+        .arg("file1.txt")
+        .assert();
+
+    // Both directories are on the same device in the test environment
+    assert
+        .success()
+        .stdout("Successfully encrypted file1.txt\n")
+        .stderr(predicates::str::contains(
+            "both output files are on the same device",
+        ));
+
+    assert_path_exists(&format!("{dir}/stick1/file1.txt.otp.0"));
+    assert_path_exists(&format!("{dir}/stick2/file1.txt.otp.1"));
+
+    // Decrypt command
+    let mut cmd = Command::cargo_bin(CARGO_BIN_NAME).unwrap();
+    let assert = cmd
+        .current_dir(&dir)
+        .arg("decrypt")
+        .args(["--in1-dir", "stick1"])
+        .args(["--in2-dir", "stick2"])
+        .arg("file1.txt")
+        .assert();
+
+    assert
+        .success()
+        .stdout("Successfully decrypted file1.txt\n")
+        .stderr("");
+
+    assert_eq!(
+        get_md5_sum(&format!("{dir}/file1.txt")).unwrap(),
+        original_md5
+    );
+}
+
+#[test]
+fn test_encrypt_decrypt_with_name_template() {
+    copy_files("files_1", "test_encrypt_decrypt_with_name_template").unwrap();
+
+    defer! {
+        clear_files("test_encrypt_decrypt_with_name_template")
+    }
+
+    let dir = format!("{FILES_DIR}/test_encrypt_decrypt_with_name_template");
+    let original_md5 = get_md5_sum(&format!("{dir}/file1.txt")).unwrap();
+
+    // Encrypt command
+    let mut cmd = Command::cargo_bin(CARGO_BIN_NAME).unwrap();
+    let assert = cmd
+        .current_dir(&dir)
+        .arg("encrypt")
+        .args(["--name-template", "{dir}/{stem}.{index}.{set}.share"])
+        .arg("--rm")
+        .arg("file1.txt")
+        .assert();
+
+    assert
+        .success()
+        .stdout("Successfully encrypted file1.txt\n")
+        .stderr("");
+
+    let shares: Vec<String> = read_dir(&dir)
+        .unwrap()
+        .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+        .filter(|n| n.ends_with(".share"))
+        .collect();
+    assert_eq!(shares.len(), 2);
+
+    // Decrypt command, which has to look up the set id
+    let mut cmd = Command::cargo_bin(CARGO_BIN_NAME).unwrap();
+    let assert = cmd
+        .current_dir(&dir)
+        .arg("decrypt")
+        .args(["--name-template", "{dir}/{stem}.{index}.{set}.share"])
+        .arg("file1.txt")
+        .assert();
+
+    assert
+        .success()
+        .stdout("Successfully decrypted file1.txt\n")
+        .stderr("");
+
+    assert_eq!(
+        get_md5_sum(&format!("{dir}/file1.txt")).unwrap(),
+        original_md5
+    );
+}
+
+#[test]
+fn test_encrypt_decrypt_armored() {
+    copy_files("files_1", "test_encrypt_decrypt_armored").unwrap();
+
+    defer! {
+        clear_files("test_encrypt_decrypt_armored")
+    }
+
+    let dir = format!("{FILES_DIR}/test_encrypt_decrypt_armored");
+    let original_md5 = get_md5_sum(&format!("{dir}/file1.txt")).unwrap();
+
+    // Encrypt command
+    let mut cmd = Command::cargo_bin(CARGO_BIN_NAME).unwrap();
+    let assert = cmd
+        .current_dir(&dir)
+        .arg("encrypt")
+        .arg("--armor")
+        .arg("--rm")
+        .arg("file1.txt")
+        .assert();
+
+    assert
+        .success()
+        .stdout("Successfully encrypted file1.txt\n")
+        .stderr("");
+
+    for (index, name) in ["file1.txt.otp.0", "file1.txt.otp.1"].iter().enumerate() {
+        let text = String::from_utf8(read(format!("{dir}/{name}")).unwrap()).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+
+        assert_eq!(lines[0], "-----BEGIN ONETIME SHARE-----");
+        assert_eq!(lines[1], format!("Index: {index}"));
+        assert!(lines[2].starts_with("Set: "));
+        assert!(lines[lines.len() - 2].starts_with('='));
+        assert_eq!(lines[lines.len() - 1], "-----END ONETIME SHARE-----");
+        assert!(lines.iter().all(|l| l.len() <= 64));
+    }
+
+    // Decrypt command
+    let mut cmd = Command::cargo_bin(CARGO_BIN_NAME).unwrap();
+    let assert = cmd
+        .current_dir(&dir)
+        .arg("decrypt")
+        .arg("file1.txt")
+        .assert();
+
+    assert
+        .success()
+        .stdout("Successfully decrypted file1.txt\n")
+        .stderr("");
+
+    assert_eq!(
+        get_md5_sum(&format!("{dir}/file1.txt")).unwrap(),
+        original_md5
+    );
+}
+
+#[test]
+fn test_encrypt_decrypt_words() {
+    copy_files("files_1", "test_encrypt_decrypt_words").unwrap();
+
+    defer! {
+        clear_files("test_encrypt_decrypt_words")
+    }
+
+    let dir = format!("{FILES_DIR}/test_encrypt_decrypt_words");
+    write(
+        format!("{dir}/password.txt"),
+        "correct horse battery staple\n",
+    )
+    .unwrap();
+
+    // Encrypt command
+    let mut cmd = Command::cargo_bin(CARGO_BIN_NAME).unwrap();
+    let assert = cmd
+        .current_dir(&dir)
+        .arg("encrypt")
+        .arg("--words")
+        .arg("--rm")
+        .arg("password.txt")
+        .assert();
+
+    assert
+        .success()
+        .stdout("Successfully encrypted password.txt\n")
+        .stderr("");
+
+    // Make a typo after the first four letters of every long word
+    let share = format!("{dir}/password.txt.otp.1");
+    let text = String::from_utf8(read(&share).unwrap()).unwrap();
+    assert!(text
+        .chars()
+        .all(|c| c.is_ascii_lowercase() || c.is_ascii_whitespace()));
+
+    let typos: Vec<String> = text
+        .split_whitespace()
+        .map(|w| match w.len() {
+            0..=4 => w.to_string(),
+            _ => format!("{}x", &w[..4]),
+        })
+        .collect();
+    write(&share, typos.join(" ")).unwrap();
+
+    // Decrypt command
+    let mut cmd = Command::cargo_bin(CARGO_BIN_NAME).unwrap();
+    let assert = cmd
+        .current_dir(&dir)
+        .arg("decrypt")
+        .arg("password.txt")
+        .assert();
+
+    assert
+        .success()
+        .stdout("Successfully decrypted password.txt\n")
+        .stderr("");
+
+    assert_eq!(
+        read(format!("{dir}/password.txt")).unwrap(),
+        b"correct horse battery staple\n"
+    );
+}
+
+#[test]
+fn test_encrypt_qr_and_decrypt_from_qr_texts() {
+    copy_files("files_1", "test_encrypt_qr_and_decrypt_from_qr_texts").unwrap();
+
+    defer! {
+        clear_files("test_encrypt_qr_and_decrypt_from_qr_texts")
+    }
+
+    let dir = format!("{FILES_DIR}/test_encrypt_qr_and_decrypt_from_qr_texts");
+    let original_md5 = get_md5_sum(&format!("{dir}/file1.txt")).unwrap();
+
+    // Encrypt command
+    let mut cmd = Command::cargo_bin(CARGO_BIN_NAME).unwrap();
+    let assert = cmd
+        .current_dir(&dir)
+        .arg("encrypt")
+        .arg("--qr")
+        .arg("--rm")
+        .arg("file1.txt")
+        .assert();
+
+    assert
+        .success()
+        .stdout(predicates::str::starts_with(
+            "Successfully encrypted file1.txt\n\
+            Wrote QR code file1.txt.otp.0.qr-1.png\n\
+            Wrote QR code file1.txt.otp.0.qr-2.png\n",
+        ))
+        .stderr("");
+
+    for share in ["file1.txt.otp.0", "file1.txt.otp.1"] {
+        let image = read(format!("{dir}/{share}.qr-1.png")).unwrap();
+        assert!(image.starts_with(b"\x89PNG\r\n\x1a\n"));
+
+        // What a scanner would read from the codes, in reverse order
+        let mut texts = onetime_cli::qr_payloads(format!("{dir}/{share}").as_ref()).unwrap();
+        assert!(texts.len() > 1);
+
+        texts.reverse();
+        write(format!("{dir}/{share}.txt"), texts.join("\n")).unwrap();
+    }
+
+    // Decrypt command
+    let mut cmd = Command::cargo_bin(CARGO_BIN_NAME).unwrap();
+    let assert = cmd
+        .current_dir(&dir)
+        .arg("decrypt")
+        .arg("file1.txt.otp.0.txt")
+        .arg("file1.txt.otp.1.txt")
+        .assert();
+
+    assert
+        .success()
+        .stdout("Successfully decrypted file1.txt\n")
+        .stderr("");
+
+    assert_eq!(
+        get_md5_sum(&format!("{dir}/file1.txt")).unwrap(),
+        original_md5
+    );
+}
+
+#[test]
+fn test_encrypt_qr_terminal() {
+    copy_files("files_1", "test_encrypt_qr_terminal").unwrap();
+
+    defer! {
+        clear_files("test_encrypt_qr_terminal")
+    }
+
+    let dir = format!("{FILES_DIR}/test_encrypt_qr_terminal");
+    write(
+        format!("{dir}/password.txt"),
+        "correct horse battery staple\n",
+    )
+    .unwrap();
+
+    // Encrypt command
+    let mut cmd = Command::cargo_bin(CARGO_BIN_NAME).unwrap();
+    let assert = cmd
+        .current_dir(&dir)
+        .args(["-q", "encrypt", "--qr", "terminal"])
+        .arg("password.txt")
+        .assert();
+
+    let output = String::from_utf8(assert.success().get_output().stdout.clone()).unwrap();
+    let lines: Vec<&str> = output.lines().collect();
+
+    assert_eq!(lines[0], "password.txt.otp.0, code 1 of 1:");
+    assert!(lines[1].chars().all(|c| c == '█'));
+    assert!(output.contains("\npassword.txt.otp.1, code 1 of 1:\n"));
+}
+
+#[test]
+fn test_paper_backup() {
+    copy_files("files_1", "test_paper_backup").unwrap();
+
+    defer! {
+        clear_files("test_paper_backup")
+    }
+
+    let dir = format!("{FILES_DIR}/test_paper_backup");
+    let original_md5 = get_md5_sum(&format!("{dir}/file1.txt")).unwrap();
+
+    let mut cmd = Command::cargo_bin(CARGO_BIN_NAME).unwrap();
+    cmd.current_dir(&dir)
+        .args(["encrypt", "--rm", "file1.txt"])
+        .assert()
+        .success();
+
+    for share in ["file1.txt.otp.0", "file1.txt.otp.1"] {
+        // Export command
+        let mut cmd = Command::cargo_bin(CARGO_BIN_NAME).unwrap();
+        let assert = cmd.current_dir(&dir).args(["export-paper", share]).assert();
+
+        assert
+            .success()
+            .stdout(format!(
+                "Wrote paper backup {share}.paper.txt\n\
+                Wrote paper backup {share}.paper.pdf\n"
+            ))
+            .stderr("");
+
+        let pdf = read(format!("{dir}/{share}.paper.pdf")).unwrap();
+        assert!(pdf.starts_with(b"%PDF-"));
+
+        // Type in the numbered lines, with a typo in line 5 (line 0 holds the size)
+        let paper = String::from_utf8(read(format!("{dir}/{share}.paper.txt")).unwrap()).unwrap();
+        let mut typed: Vec<String> = paper
+            .lines()
+            .filter(|l| {
+                l.split_once(':')
+                    .is_some_and(|(number, _)| number.trim().parse::<usize>().is_ok())
+            })
+            .map(|l| l.to_lowercase())
+            .collect();
+        let correct = typed[5].clone();
+        let pos = correct.find(": ").unwrap() + 2;
+        let typo = if &correct[pos..pos + 1] == "a" {
+            "b"
+        } else {
+            "a"
+        };
+        typed[5].replace_range(pos..pos + 1, typo);
+        assert_ne!(typed[5], correct);
+        write(format!("{dir}/typed.txt"), typed.join("\n")).unwrap();
+
+        // Import command
+        let mut cmd = Command::cargo_bin(CARGO_BIN_NAME).unwrap();
+        let assert = cmd
+            .current_dir(&dir)
+            .args(["import-paper", "typed.txt", share])
+            .assert();
+
+        assert.failure().stderr(predicates::str::contains(
+            "Typo in line(s) 5 of the paper backup",
+        ));
+
+        typed[5] = correct;
+        write(format!("{dir}/typed.txt"), typed.join("\n")).unwrap();
+        remove_file(format!("{dir}/{share}")).unwrap();
+
+        let mut cmd = Command::cargo_bin(CARGO_BIN_NAME).unwrap();
+        let assert = cmd
+            .current_dir(&dir)
+            .args(["import-paper", "typed.txt", share])
+            .assert();
+
+        assert
+            .success()
+            .stdout(format!("Successfully restored {share}\n"))
+            .stderr("");
+    }
+
+    // Decrypt command
+    let mut cmd = Command::cargo_bin(CARGO_BIN_NAME).unwrap();
+    let assert = cmd
+        .current_dir(&dir)
+        .args(["decrypt", "file1.txt.otp.0", "file1.txt.otp.1"])
+        .assert();
+
+    assert
+        .success()
+        .stdout("Successfully decrypted file1.txt\n")
+        .stderr("");
+
+    assert_eq!(
+        get_md5_sum(&format!("{dir}/file1.txt")).unwrap(),
+        original_md5
+    );
+}
+
+#[test]
+fn test_encrypt_decrypt_compressed() {
+    create_dir_all(format!("{FILES_DIR}/test_encrypt_decrypt_compressed")).unwrap();
+
+    defer! {
+        clear_files("test_encrypt_decrypt_compressed")
+    }
+
+    let dir = format!("{FILES_DIR}/test_encrypt_decrypt_compressed");
+    let data = "2024-01-01 12:00:00 INFO request handled\n".repeat(5000);
+    write(format!("{dir}/log.txt"), &data).unwrap();
+    let original_md5 = get_md5_sum(&format!("{dir}/log.txt")).unwrap();
+
+    // Encrypt command
+    let mut cmd = Command::cargo_bin(CARGO_BIN_NAME).unwrap();
+    let assert = cmd
+        .current_dir(&dir)
+        .args(["encrypt", "--compress", "--rm", "log.txt"])
+        .assert();
+
+    assert
+        .success()
+        .stdout("Successfully encrypted log.txt\n")
+        .stderr("");
+
+    for name in ["log.txt.otp.0", "log.txt.otp.1"] {
+        let len = metadata(format!("{dir}/{name}")).unwrap().len();
+        assert!(len < data.len() as u64 / 10);
+    }
+
+    // Decrypt command, which reads the codec from the headers
+    let mut cmd = Command::cargo_bin(CARGO_BIN_NAME).unwrap();
+    let assert = cmd.current_dir(&dir).args(["decrypt", "log.txt"]).assert();
+
+    assert
+        .success()
+        .stdout("Successfully decrypted log.txt\n")
+        .stderr("");
+
+    assert_eq!(
+        get_md5_sum(&format!("{dir}/log.txt")).unwrap(),
+        original_md5
+    );
+}
+
+#[test]
+fn test_encrypt_decrypt_padded() {
+    copy_files("files_1", "test_encrypt_decrypt_padded").unwrap();
+
+    defer! {
+        clear_files("test_encrypt_decrypt_padded")
+    }
+
+    let dir = format!("{FILES_DIR}/test_encrypt_decrypt_padded");
+    let original_md5 = get_md5_sum(&format!("{dir}/file1.txt")).unwrap();
+    let original_len = metadata(format!("{dir}/file1.txt")).unwrap().len();
+
+    for (mode, data_len) in [
+        ("pow2", original_len.next_power_of_two()),
+        ("64KiB", original_len.div_ceil(65536) * 65536),
+    ] {
+        // Encrypt command
+        let mut cmd = Command::cargo_bin(CARGO_BIN_NAME).unwrap();
+        let assert = cmd
+            .current_dir(&dir)
+            .args(["encrypt", "--pad-length", mode, "--rm", "file1.txt"])
+            .assert();
+
+        assert
+            .success()
+            .stdout("Successfully encrypted file1.txt\n")
+            .stderr("");
+
+        let share_len = metadata(format!("{dir}/file1.txt.otp.0")).unwrap().len();
+        assert!(share_len > data_len);
+        assert!(share_len < data_len + 1024);
+
+        // Decrypt command
+        let mut cmd = Command::cargo_bin(CARGO_BIN_NAME).unwrap();
+        let assert = cmd
+            .current_dir(&dir)
+            .args(["decrypt", "--rm", "file1.txt"])
+            .assert();
+
+        assert
+            .success()
+            .stdout("Successfully decrypted file1.txt\n")
+            .stderr("");
+
+        assert_eq!(
+            get_md5_sum(&format!("{dir}/file1.txt")).unwrap(),
+            original_md5
+        );
+    }
+}
+
+#[test]
+fn test_encrypt_decrypt_volumes() {
+    copy_files("files_1", "test_encrypt_decrypt_volumes").unwrap();
+
+    defer! {
+        clear_files("test_encrypt_decrypt_volumes")
+    }
+
+    let dir = format!("{FILES_DIR}/test_encrypt_decrypt_volumes");
+    let original_md5 = get_md5_sum(&format!("{dir}/file1.txt")).unwrap();
+
+    // Encrypt command
+    let mut cmd = Command::cargo_bin(CARGO_BIN_NAME).unwrap();
+    let assert = cmd
+        .current_dir(&dir)
+        .args(["encrypt", "--volume-size", "2KiB", "--rm", "file1.txt"])
+        .assert();
+
+    assert
+        .success()
+        .stdout("Successfully encrypted file1.txt\n")
+        .stderr("");
+
+    for share in ["file1.txt.otp.0", "file1.txt.otp.1"] {
+        assert!(metadata(format!("{dir}/{share}")).is_err());
+
+        for volume in ["001", "002", "003"] {
+            let len = metadata(format!("{dir}/{share}.{volume}")).unwrap().len();
+            assert!(len <= 2048);
+        }
+    }
+
+    // Decrypt command with a missing volume
+    rename(
+        format!("{dir}/file1.txt.otp.1.002"),
+        format!("{dir}/file1.txt.otp.1.tmp"),
+    )
+    .unwrap();
+
+    let mut cmd = Command::cargo_bin(CARGO_BIN_NAME).unwrap();
+    let assert = cmd
+        .current_dir(&dir)
+        .args(["decrypt", "file1.txt"])
+        .assert();
+
+    assert.failure().stderr(predicates::str::contains(
+        "file1.txt.otp.1.002\" is missing",
+    ));
+
+    rename(
+        format!("{dir}/file1.txt.otp.1.tmp"),
+        format!("{dir}/file1.txt.otp.1.002"),
+    )
+    .unwrap();
+
+    // Decrypt command
+    let mut cmd = Command::cargo_bin(CARGO_BIN_NAME).unwrap();
+    let assert = cmd
+        .current_dir(&dir)
+        .args(["decrypt", "--rm", "file1.txt"])
+        .assert();
+
+    assert
+        .success()
+        .stdout("Successfully decrypted file1.txt\n")
+        .stderr("");
+
+    assert_eq!(
+        get_md5_sum(&format!("{dir}/file1.txt")).unwrap(),
+        original_md5
+    );
+    assert_eq!(read_dir(&dir).unwrap().count(), 1);
+}
+
+#[test]
+fn test_decrypt_range() {
+    copy_files("files_1", "test_decrypt_range").unwrap();
+
+    defer! {
+        clear_files("test_decrypt_range")
+    }
+
+    let dir = format!("{FILES_DIR}/test_decrypt_range");
+    let original = read(format!("{dir}/file1.txt")).unwrap();
+
+    // Encrypt command
+    let mut cmd = Command::cargo_bin(CARGO_BIN_NAME).unwrap();
+    let assert = cmd
+        .current_dir(&dir)
+        .args(["encrypt", "-b", "1024", "file1.txt"])
+        .assert();
+
+    assert
+        .success()
+        .stdout("Successfully encrypted file1.txt\n")
+        .stderr("");
+
+    // Decrypt command, writing to stdout
+    let mut cmd = Command::cargo_bin(CARGO_BIN_NAME).unwrap();
+    let assert = cmd
+        .current_dir(&dir)
+        .args(["decrypt", "--range", "1000..1000+2500", "file1.txt"])
+        .assert();
+
+    assert
+        .success()
+        .stdout(predicates::ord::eq(&original[1000..3500]))
+        .stderr("");
+
+    // Decrypt command, writing the end of the file to another file
+    let mut cmd = Command::cargo_bin(CARGO_BIN_NAME).unwrap();
+    let assert = cmd
+        .current_dir(&dir)
+        .args(["decrypt", "--range", "5000..", "-o", "end.txt", "file1.txt"])
+        .assert();
+
+    assert
+        .success()
+        .stdout(format!(
+            "Decrypted bytes 5000..{} to end.txt\n",
+            original.len()
+        ))
+        .stderr("");
+
+    assert_eq!(read(format!("{dir}/end.txt")).unwrap(), original[5000..]);
+
+    // Decrypt command with a range after the end of the file
+    let mut cmd = Command::cargo_bin(CARGO_BIN_NAME).unwrap();
+    let assert = cmd
+        .current_dir(&dir)
+        .args(["decrypt", "--range", "100000..100100", "file1.txt"])
+        .assert();
+
+    assert.success().stdout("").stderr("");
+
+    // Decrypt command, writing to one of the input files
+    let share = read(format!("{dir}/file1.txt.otp.0")).unwrap();
+
+    let mut cmd = Command::cargo_bin(CARGO_BIN_NAME).unwrap();
+    let assert = cmd
+        .current_dir(&dir)
+        .args(["decrypt", "--range", "0..1000", "-o", "file1.txt.otp.0"])
+        .arg("file1.txt")
+        .assert();
+
+    assert
+        .failure()
+        .code(2)
+        .stderr(predicates::str::contains("is one of the input files"));
+    assert_eq!(read(format!("{dir}/file1.txt.otp.0")).unwrap(), share);
+
+    // Decrypt command with damaged data in the range, the header and its copy
+    // are 34 bytes long each
+    let mut damaged = share;
+    damaged[68 + 1500] ^= 0x01;
+    write(format!("{dir}/file1.txt.otp.0"), damaged).unwrap();
+
+    let mut cmd = Command::cargo_bin(CARGO_BIN_NAME).unwrap();
+    let assert = cmd
+        .current_dir(&dir)
+        .args([
+            "decrypt",
+            "--range",
+            "1000..3500",
+            "-o",
+            "part.txt",
+            "file1.txt",
+        ])
+        .assert();
+
+    assert.failure().code(5);
+    assert_path_not_found(&format!("{dir}/part.txt"));
+}
+
+#[test]
+fn test_decrypt_refuses_to_overwrite_input() {
+    copy_files("files_1", "test_decrypt_refuses_to_overwrite_input").unwrap();
+
+    defer! {
+        clear_files("test_decrypt_refuses_to_overwrite_input")
+    }
+
+    let dir = format!("{FILES_DIR}/test_decrypt_refuses_to_overwrite_input");
+
+    // Encrypt command
+    let mut cmd = Command::cargo_bin(CARGO_BIN_NAME).unwrap();
+    let assert = cmd
+        .current_dir(&dir)
+        .args(["encrypt", "file1.txt"])
+        .assert();
+
+    assert
+        .success()
+        .stdout("Successfully encrypted file1.txt\n")
+        .stderr("");
+
+    let share = read(format!("{dir}/file1.txt.otp.0")).unwrap();
+
+    // Decrypt command, writing to one of the input files
+    let mut cmd = Command::cargo_bin(CARGO_BIN_NAME).unwrap();
+    let assert = cmd
+        .current_dir(&dir)
+        .args(["decrypt", "-o", "file1.txt.otp.0", "file1.txt"])
+        .assert();
+
+    assert
+        .failure()
+        .stderr(predicates::str::contains("is one of the input files"));
+
+    assert_eq!(read(format!("{dir}/file1.txt.otp.0")).unwrap(), share);
+}
+
+#[test]
+fn test_exit_codes() {
+    // No subcommand
+    let mut cmd = Command::cargo_bin(CARGO_BIN_NAME).unwrap();
+    cmd.assert().failure().code(2).stdout("");
+
+    // Missing input file
+    let mut cmd = Command::cargo_bin(CARGO_BIN_NAME).unwrap();
+    let assert = cmd
+        .current_dir(FILES_DIR)
+        .args(["decrypt", "does_not_exist.txt"])
+        .assert();
+
+    assert
+        .failure()
+        .code(3)
+        .stderr(predicates::str::contains("does_not_exist.txt.otp.0"));
+
+    // Invalid configuration
+    let mut cmd = Command::cargo_bin(CARGO_BIN_NAME).unwrap();
+    let assert = cmd
+        .current_dir(FILES_DIR)
+        .args(["decrypt", "-b", "0", "does_not_exist.txt"])
+        .assert();
+
+    assert
+        .failure()
+        .code(2)
+        .stderr(predicates::str::contains("the buffer size must not be 0"));
+
+    for (args, message) in [
+        (
+            ["--volume-size", "10"],
+            "the volume size must be larger than",
+        ),
+        (
+            ["--pad-length", "0"],
+            "the padding block size must not be 0",
+        ),
+    ] {
+        let mut cmd = Command::cargo_bin(CARGO_BIN_NAME).unwrap();
+        let assert = cmd
+            .current_dir(FILES_DIR)
+            .arg("encrypt")
+            .args(args)
+            .arg("does_not_exist.txt")
+            .assert();
+
+        assert
+            .failure()
+            .code(2)
+            .stderr(predicates::str::contains(message));
+    }
+}
+
+#[test]
+fn test_json_output() {
+    copy_files("files_1", "test_json_output").unwrap();
+
+    defer! {
+        clear_files("test_json_output")
+    }
+
+    let dir = format!("{FILES_DIR}/test_json_output");
+
+    // Encrypt command
+    let mut cmd = Command::cargo_bin(CARGO_BIN_NAME).unwrap();
+    let assert = cmd
+        .current_dir(&dir)
+        .args(["--message-format", "json", "encrypt", "file1.txt"])
+        .assert();
+
+    let output = assert.success().stderr("").get_output().stdout.clone();
+    let output = String::from_utf8(output).unwrap();
+
+    assert_eq!(output.lines().count(), 1);
+    assert!(output.starts_with(r#"{"operation":"encrypt","status":"ok","#));
+    assert!(output.contains(r#"{"path":"file1.txt.otp.0","sha256":""#));
+    assert!(output.contains(r#"{"path":"file1.txt.otp.1","sha256":""#));
+
+    // Decrypt command with missing input files
+    let mut cmd = Command::cargo_bin(CARGO_BIN_NAME).unwrap();
+    let assert = cmd
+        .current_dir(&dir)
+        .args(["--message-format", "json", "decrypt", "does_not_exist.txt"])
+        .assert();
+
+    assert
+        .failure()
+        .code(3)
+        .stdout(predicates::str::contains(
+            r#""status":"error","duration_ms":"#,
+        ))
+        .stdout(predicates::str::contains(
+            r#""error":{"category":"io","code":3,"message":"Failed to open"#,
+        ));
+}
+
+#[test]
+fn test_message_format_and_output() {
+    copy_files("files_1", "test_message_format_and_output").unwrap();
+
+    defer! {
+        clear_files("test_message_format_and_output")
+    }
+
+    let dir = format!("{FILES_DIR}/test_message_format_and_output");
+
+    let mut cmd = Command::cargo_bin(CARGO_BIN_NAME).unwrap();
+    cmd.current_dir(&dir)
+        .args(["encrypt", "file1.txt"])
+        .assert()
+        .success();
+
+    // `--output` of decrypt names the decrypted file, `--message-format` the
+    // format of the results, before or after the subcommand
+    for args in [
+        [
+            "--message-format",
+            "json",
+            "decrypt",
+            "--output",
+            "json",
+            "file1.txt",
+        ],
+        [
+            "decrypt",
+            "--output",
+            "json",
+            "--message-format",
+            "json",
+            "file1.txt",
+        ],
+    ] {
+        let mut cmd = Command::cargo_bin(CARGO_BIN_NAME).unwrap();
+        let assert = cmd.current_dir(&dir).args(args).assert();
+
+        assert
+            .success()
+            .stdout(predicates::str::starts_with(
+                r#"{"operation":"decrypt","status":"ok","#,
+            ))
+            .stdout(predicates::str::contains(r#"{"path":"json","sha256":""#));
+
+        assert_eq!(
+            read(format!("{dir}/json")).unwrap(),
+            read(format!("{dir}/file1.txt")).unwrap(),
+        );
+        remove_file(format!("{dir}/json")).unwrap();
+    }
+}
+
+#[test]
+fn test_color() {
+    // stderr is not a terminal
+    let mut cmd = Command::cargo_bin(CARGO_BIN_NAME).unwrap();
+    let assert = cmd
+        .current_dir(FILES_DIR)
+        .env_remove("CLICOLOR_FORCE")
+        .args(["decrypt", "does_not_exist.txt"])
+        .assert();
+
+    assert
+        .failure()
+        .stderr(predicates::str::starts_with("Error: Failed to open"));
+
+    // Forced by the environment
+    let mut cmd = Command::cargo_bin(CARGO_BIN_NAME).unwrap();
+    let assert = cmd
+        .current_dir(FILES_DIR)
+        .env("CLICOLOR_FORCE", "1")
+        .args(["decrypt", "does_not_exist.txt"])
+        .assert();
+
+    assert
+        .failure()
+        .stderr(predicates::str::starts_with("\x1b[1;91mError\x1b[0m: "));
+
+    // --color takes precedence over the environment
+    let mut cmd = Command::cargo_bin(CARGO_BIN_NAME).unwrap();
+    let assert = cmd
+        .current_dir(FILES_DIR)
+        .env("CLICOLOR_FORCE", "1")
+        .args(["--color", "never", "decrypt", "does_not_exist.txt"])
+        .assert();
+
+    assert
+        .failure()
+        .stderr(predicates::str::starts_with("Error: Failed to open"));
+
+    let mut cmd = Command::cargo_bin(CARGO_BIN_NAME).unwrap();
+    let assert = cmd
+        .current_dir(FILES_DIR)
+        .env("NO_COLOR", "1")
+        .args(["decrypt", "--color=always", "does_not_exist.txt"])
+        .assert();
+
+    assert
+        .failure()
+        .stderr(predicates::str::starts_with("\x1b[1;91mError\x1b[0m: "));
+}
+
+#[test]
+fn test_verbose() {
+    copy_files("files_1", "test_verbose").unwrap();
+
+    defer! {
+        clear_files("test_verbose")
+    }
+
+    let dir = format!("{FILES_DIR}/test_verbose");
+
+    // Encrypt command
+    let mut cmd = Command::cargo_bin(CARGO_BIN_NAME).unwrap();
+    let assert = cmd
+        .current_dir(&dir)
+        .env_remove("RUST_LOG")
+        .args(["-v", "encrypt", "-b", "1024", "file1.txt"])
+        .assert();
+
+    assert
+        .success()
+        .stdout("Successfully encrypted file1.txt\n")
+        .stderr(predicates::str::contains(
+            "[INFO onetime_cli::otp] Encrypted 5652 bytes in 6 chunks",
+        ))
+        .stderr(predicates::str::contains("DEBUG").not());
+
+    // Decrypt command, logging every chunk
+    let mut cmd = Command::cargo_bin(CARGO_BIN_NAME).unwrap();
+    let assert = cmd
+        .current_dir(&dir)
+        .env("RUST_LOG", "onetime_cli::otp=trace")
+        .args(["decrypt", "-o", "decrypted.txt", "file1.txt"])
+        .assert();
+
+    assert
+        .success()
+        .stderr(predicates::str::contains(
+            "[TRACE onetime_cli::otp] Decrypting chunk 5 at bytes 5120..5652",
+        ))
+        .stderr(predicates::str::contains("Opening").not())
+        .stderr(predicates::str::contains("Lorem ipsum").not());
+}
+
+#[test]
+fn test_encrypt_resume() {
+    copy_files("files_1", "test_encrypt_resume").unwrap();
+
+    defer! {
+        clear_files("test_encrypt_resume")
+    }
+
+    let dir = format!("{FILES_DIR}/test_encrypt_resume");
+    let original = read(format!("{dir}/file1.txt")).unwrap();
+
+    // Encrypt command
+    let mut cmd = Command::cargo_bin(CARGO_BIN_NAME).unwrap();
+    let assert = cmd
+        .current_dir(&dir)
+        .args(["encrypt", "--resume", "-b", "1024", "file1.txt"])
+        .assert();
+
+    assert.success().stderr("");
+    assert_path_not_found(&format!("{dir}/file1.txt.otp.0.journal"));
+
+    // Interrupt the run after 2048 bytes, in the middle of a write, and write
+    // the journal of the last checkpoint: magic, set id, offset and the
+    // digests of the encrypted data of both files
+    let offset = 2048;
+    let mut journal = b"OTPJOURN".to_vec();
+
+    for (i, share) in ["file1.txt.otp.0", "file1.txt.otp.1"].iter().enumerate() {
+        let path = format!("{dir}/{share}");
+        let bytes = read(&path).unwrap();
+        let fields_len = u32::from_le_bytes(bytes[26..30].try_into().unwrap()) as usize;
+        // The header, with a checksum, and its copy
+        let start = 2 * (30 + fields_len + 4);
+
+        if i == 0 {
+            journal.extend_from_slice(&bytes[10..26]);
+            journal.extend_from_slice(&(offset as u64).to_le_bytes());
+            journal.push(2);
+        }
+
+        journal.extend_from_slice(&Sha256::digest(&bytes[start..start + offset]));
+        write(&path, &bytes[..start + offset + 100]).unwrap();
+    }
+
+    write(format!("{dir}/file1.txt.otp.0.journal"), &journal).unwrap();
+
+    // Encrypt command, resuming the interrupted run
+    let mut cmd = Command::cargo_bin(CARGO_BIN_NAME).unwrap();
+    let assert = cmd
+        .current_dir(&dir)
+        .args(["encrypt", "--resume", "-b", "1024", "file1.txt"])
+        .assert();
+
+    assert
+        .success()
+        .stdout("Successfully encrypted file1.txt\n")
+        .stderr("");
+    assert_path_not_found(&format!("{dir}/file1.txt.otp.0.journal"));
+
+    // Decrypt command
+    let mut cmd = Command::cargo_bin(CARGO_BIN_NAME).unwrap();
+    let assert = cmd
+        .current_dir(&dir)
+        .args(["decrypt", "-o", "decrypted.txt", "file1.txt"])
+        .assert();
+
+    assert.success().stderr("");
+    assert_eq!(read(format!("{dir}/decrypted.txt")).unwrap(), original);
+}